@@ -0,0 +1,81 @@
+//! Times garbled reads of a Circuit ORAM, with the garbler and the evaluator in two threads that
+//! talk over a `UnixStream`. Only the reads are timed, not the OT setup or the creation of the
+//! ORAM. Run it with `cargo bench --bench circuit_oram`.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use obliv_rust::{
+    circuit_oram::CircuitOram,
+    executor::{Evaluator, Executor, Garbler},
+    free_xor_garbling::FreeXorBit,
+    mpc_core::GarblingSession,
+    obliv_array::ObliviousMemory,
+};
+use ocelot::ot::{KosReceiver, KosSender, Receiver, Sender};
+use scuttlebutt::{AesRng, TrackChannel};
+use std::{
+    io::{BufReader, BufWriter},
+    os::unix::net::UnixStream,
+    time::{Duration, Instant},
+};
+
+const VALUE_BITS: usize = 32;
+const INDEX_BITS: usize = 32;
+
+/// Returns the bits of `value`, least significant first.
+fn to_bits(value: u64) -> Vec<bool> {
+    (0..INDEX_BITS).map(|i| (value >> i) & 1 == 1).collect()
+}
+
+/// Creates an ORAM of `length` zero elements on both sides and returns the time the evaluator
+/// spends on `iterations` reads.
+fn time_reads(length: usize, iterations: u64) -> Duration {
+    let (sender, receiver) = UnixStream::pair().unwrap();
+    let handle = std::thread::spawn(move || {
+        let mut rng = AesRng::new();
+        let reader = BufReader::new(sender.try_clone().unwrap());
+        let writer = BufWriter::new(sender);
+        let mut channel = TrackChannel::new(reader, writer);
+        let mut session = GarblingSession::new(&mut rng);
+        let mut ot = KosSender::init(&mut channel, &mut rng).unwrap();
+        let mut garbler: Garbler<FreeXorBit, _, _, _> =
+            Garbler::new(&mut channel, &mut ot, &mut session, &mut rng);
+
+        let mut oram = CircuitOram::zeroed(length, VALUE_BITS, &mut garbler).unwrap();
+        for iteration in 0..iterations {
+            let index = garbler.input(&to_bits(iteration % length as u64)).unwrap();
+            oram.read(&index, &mut garbler).unwrap();
+        }
+    });
+
+    let mut rng = AesRng::new();
+    let reader = BufReader::new(receiver.try_clone().unwrap());
+    let writer = BufWriter::new(receiver);
+    let mut channel = TrackChannel::new(reader, writer);
+    let mut ot = KosReceiver::init(&mut channel, &mut rng).unwrap();
+    let mut evaluator: Evaluator<FreeXorBit, _, _, _> =
+        Evaluator::new(&mut channel, &mut ot, &mut rng);
+
+    let mut oram = CircuitOram::zeroed(length, VALUE_BITS, &mut evaluator).unwrap();
+    let start = Instant::now();
+    for _ in 0..iterations {
+        let index = evaluator.peer_input(INDEX_BITS).unwrap();
+        oram.read(&index, &mut evaluator).unwrap();
+    }
+    let elapsed = start.elapsed();
+    handle.join().unwrap();
+    elapsed
+}
+
+fn criterion_benchmark(c: &mut Criterion) {
+    let mut group = c.benchmark_group("circuit_oram_read");
+    group.sample_size(10);
+    for length in [64, 1024, 16384].iter() {
+        group.bench_with_input(BenchmarkId::from_parameter(length), length, |b, &length| {
+            b.iter_custom(|iterations| time_reads(length, iterations))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, criterion_benchmark);
+criterion_main!(benches);
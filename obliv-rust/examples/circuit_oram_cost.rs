@@ -0,0 +1,41 @@
+//! Prints how the number of AND gates of an access to a Circuit ORAM grows with its size, next to
+//! the number of AND gates of a linear scan. Run with `cargo run --example circuit_oram_cost`.
+
+use obliv_rust::{circuit::CircuitBuilder, circuit_oram::access_and_count};
+
+const VALUE_BITS: usize = 32;
+
+/// Linear scans above this size take too long to compile to be worth comparing.
+const MAX_LINEAR_LOG_LENGTH: usize = 16;
+
+fn linear_scan_and_count(length: usize) -> usize {
+    let mut builder = CircuitBuilder::new();
+    let index = builder.input(1, length.trailing_zeros());
+    let elements = (0..length)
+        .map(|_| builder.input(2, VALUE_BITS as u32))
+        .collect::<Vec<_>>();
+    let output = builder.array_read(&elements, &index);
+    builder.output(&output);
+    builder.build().and_count()
+}
+
+fn main() {
+    println!(
+        "{:>10} {:>20} {:>20}",
+        "entries", "circuit oram ANDs", "linear scan ANDs"
+    );
+    for log_length in (10..=20).step_by(2) {
+        let length = 1 << log_length;
+        let linear = if log_length <= MAX_LINEAR_LOG_LENGTH {
+            linear_scan_and_count(length).to_string()
+        } else {
+            "-".to_string()
+        };
+        println!(
+            "{:>10} {:>20} {:>20}",
+            format!("2^{}", log_length),
+            access_and_count(length, VALUE_BITS),
+            linear
+        );
+    }
+}
@@ -0,0 +1,524 @@
+//! Implements the integer arithmetic gadgets on top of `CircuitBuilder`. Every gadget takes and
+//! returns groups of wires with the most significant bit first.
+
+use crate::circuit::{CircuitBuilder, WireId};
+
+/// The width from which multiplications are split with the Karatsuba algorithm. Below it,
+/// shift-and-add needs fewer AND gates.
+const KARATSUBA_THRESHOLD: usize = 20;
+
+/// The output of a division gadget.
+pub struct Division {
+    pub quotient: Vec<WireId>,
+    pub remainder: Vec<WireId>,
+    /// Set if the divisor is zero. The quotient then has all bits set and the remainder is the
+    /// dividend.
+    pub divide_by_zero: WireId,
+}
+
+impl CircuitBuilder {
+    /// Adds two values of the same width. The final carry is dropped, so the result wraps around.
+    pub fn add(&mut self, input_1: &[WireId], input_2: &[WireId]) -> Vec<WireId> {
+        let carry = self.constant(false);
+        self.ripple_carry(input_1, input_2, carry)
+    }
+
+    /// Subtracts the second value from the first one, i.e., adds the two's complement of the
+    /// second value. The result wraps around.
+    pub fn sub(&mut self, input_1: &[WireId], input_2: &[WireId]) -> Vec<WireId> {
+        let inverted = input_2
+            .iter()
+            .map(|bit| self.inv(*bit))
+            .collect::<Vec<WireId>>();
+        let carry = self.constant(true);
+        self.ripple_carry(input_1, &inverted, carry)
+    }
+
+    /// Adds two values of the same width and an incoming carry. Returns the sum and the carry out
+    /// of the most significant bit, so values wider than a wire can be added with a carry chain.
+    pub fn add_with_carry(
+        &mut self,
+        input_1: &[WireId],
+        input_2: &[WireId],
+        carry: WireId,
+    ) -> (Vec<WireId>, WireId) {
+        let (sum, carry) = self.carry_chain(input_1, input_2, carry, true);
+        (sum, carry.expect("the carry out is computed"))
+    }
+
+    /// Subtracts the second value from the first one. Returns the difference and the borrow out of
+    /// the most significant bit, which is set if the first value is less than the second one as
+    /// unsigned integers.
+    pub fn sub_with_borrow(
+        &mut self,
+        input_1: &[WireId],
+        input_2: &[WireId],
+    ) -> (Vec<WireId>, WireId) {
+        let inverted = input_2
+            .iter()
+            .map(|bit| self.inv(*bit))
+            .collect::<Vec<WireId>>();
+        let carry = self.constant(true);
+        let (difference, carry) = self.add_with_carry(input_1, &inverted, carry);
+        (difference, self.inv(carry))
+    }
+
+    /// Extends a value to `width` bits by repeating its sign bit if `signed`, or with zeros
+    /// otherwise.
+    pub fn extend(&mut self, input: &[WireId], width: usize, signed: bool) -> Vec<WireId> {
+        assert!(width >= input.len(), "cannot extend to a smaller width");
+        let padding = if signed {
+            input[0]
+        } else {
+            self.constant(false)
+        };
+        let mut output = vec![padding; width - input.len()];
+        output.extend_from_slice(input);
+        output
+    }
+
+    /// Multiplies two values of the same width. The result is truncated to the width of the
+    /// operands, so it is the same for signed and unsigned values. Wide values are split in halves,
+    /// and the product of the low halves uses Karatsuba multiplication.
+    pub fn mul(&mut self, input_1: &[WireId], input_2: &[WireId]) -> Vec<WireId> {
+        check_widths(input_1, input_2);
+        let width = input_1.len();
+        if width < KARATSUBA_THRESHOLD {
+            return self.schoolbook_truncated(input_1, input_2);
+        }
+        // low(a * b) = a_0 * b_0 + ((a_1 * b_0 + a_0 * b_1) << half), where a_0 and b_0 are the
+        // `half` least significant bits. As `2 * half >= width`, a_1 * b_1 does not contribute.
+        let high = width / 2;
+        let half = width - high;
+        let (a_1, a_0) = input_1.split_at(high);
+        let (b_1, b_0) = input_2.split_at(high);
+        let low = self.mul_full(a_0, b_0, false);
+        let low = &low[2 * half - width..];
+        let cross_1 = self.mul(a_1, &b_0[half - high..]);
+        let cross_2 = self.mul(&a_0[half - high..], b_1);
+        let cross = self.add(&cross_1, &cross_2);
+        let upper = self.add(&low[..high], &cross);
+        [upper, low[high..].to_vec()].concat()
+    }
+
+    /// Multiplies two values of the same width and returns the full product, which is twice as
+    /// wide. Wide values are multiplied with the Karatsuba algorithm.
+    pub fn mul_full(
+        &mut self,
+        input_1: &[WireId],
+        input_2: &[WireId],
+        signed: bool,
+    ) -> Vec<WireId> {
+        check_widths(input_1, input_2);
+        let width = input_1.len();
+        let mut output = self.karatsuba(input_1, input_2);
+        if signed {
+            // The most significant bit of a signed value weighs `-2^(width - 1)` instead of
+            // `2^(width - 1)`, so each operand is subtracted from the high half of the unsigned
+            // product if the sign of the other operand is set.
+            let masked_1 = self.mask(input_1, input_2[0]);
+            let masked_2 = self.mask(input_2, input_1[0]);
+            let high = self.sub(&output[..width], &masked_1);
+            let high = self.sub(&high, &masked_2);
+            output.splice(..width, high);
+        }
+        output
+    }
+
+    /// Divides the first value by the second one, which have the same width. Signed division
+    /// rounds toward zero and the remainder has the sign of the dividend, like `/` and `%` on Rust
+    /// integers. Dividing the smallest signed value by -1 wraps around to the smallest value with a
+    /// zero remainder.
+    pub fn div_rem(&mut self, input_1: &[WireId], input_2: &[WireId], signed: bool) -> Division {
+        check_widths(input_1, input_2);
+        if !signed {
+            return self.restoring_division(input_1, input_2);
+        }
+        let sign_1 = input_1[0];
+        let sign_2 = input_2[0];
+        let dividend = self.negate_if(sign_1, input_1);
+        let divisor = self.negate_if(sign_2, input_2);
+        let division = self.restoring_division(&dividend, &divisor);
+        let negative = self.xor(sign_1, sign_2);
+        let quotient = self.negate_if(negative, &division.quotient);
+        let ones = vec![self.constant(true); quotient.len()];
+        Division {
+            quotient: self.mux(division.divide_by_zero, &ones, &quotient),
+            remainder: self.negate_if(sign_1, &division.remainder),
+            divide_by_zero: division.divide_by_zero,
+        }
+    }
+
+    /// Returns the quotient of the first value divided by the second one.
+    pub fn div(&mut self, input_1: &[WireId], input_2: &[WireId], signed: bool) -> Vec<WireId> {
+        self.div_rem(input_1, input_2, signed).quotient
+    }
+
+    /// Returns the remainder of the first value divided by the second one.
+    pub fn rem(&mut self, input_1: &[WireId], input_2: &[WireId], signed: bool) -> Vec<WireId> {
+        self.div_rem(input_1, input_2, signed).remainder
+    }
+
+    /// Divides two unsigned values with restoring long division. Each step shifts the next bit of
+    /// the dividend into the remainder and subtracts the divisor if the remainder is not smaller.
+    fn restoring_division(&mut self, dividend: &[WireId], divisor: &[WireId]) -> Division {
+        let width = dividend.len();
+        let zero = self.constant(false);
+        let divisor_wide = self.extend(divisor, width + 1, false);
+        let mut remainder = vec![zero; width];
+        let mut quotient = Vec::with_capacity(width);
+        for bit in dividend {
+            // The shifted remainder is less than twice the divisor, so it fits in `width + 1` bits.
+            let shifted = [remainder, vec![*bit]].concat();
+            let (difference, borrow) = self.sub_with_borrow(&shifted, &divisor_wide);
+            let quotient_bit = self.inv(borrow);
+            remainder = self.mux(quotient_bit, &difference[1..], &shifted[1..]);
+            quotient.push(quotient_bit);
+        }
+        let nonzero = self.any(divisor);
+        Division {
+            quotient,
+            remainder,
+            divide_by_zero: self.inv(nonzero),
+        }
+    }
+
+    /// Returns the two's complement of the value if `condition` is set, otherwise the value. The
+    /// negation inverts the bits and adds the condition as the incoming carry.
+    fn negate_if(&mut self, condition: WireId, input: &[WireId]) -> Vec<WireId> {
+        let flipped = input
+            .iter()
+            .map(|bit| self.xor(*bit, condition))
+            .collect::<Vec<WireId>>();
+        let zero = vec![self.constant(false); input.len()];
+        self.ripple_carry(&flipped, &zero, condition)
+    }
+
+    /// Multiplies two unsigned values of the same width and returns the full product.
+    fn karatsuba(&mut self, input_1: &[WireId], input_2: &[WireId]) -> Vec<WireId> {
+        let width = input_1.len();
+        if width < KARATSUBA_THRESHOLD {
+            return self.schoolbook_full(input_1, input_2);
+        }
+        // a * b = z_2 << (2 * half) + z_1 << half + z_0, where
+        // z_1 = (a_1 + a_0) * (b_1 + b_0) - z_2 - z_0.
+        let half = width / 2;
+        let high = width - half;
+        let (a_1, a_0) = input_1.split_at(high);
+        let (b_1, b_0) = input_2.split_at(high);
+        let z_0 = self.karatsuba(a_0, b_0);
+        let z_2 = self.karatsuba(a_1, b_1);
+        let sum_a = self.add_halves(a_1, a_0);
+        let sum_b = self.add_halves(b_1, b_0);
+        let z_1 = self.karatsuba(&sum_a, &sum_b);
+        let z_0_wide = self.extend(&z_0, z_1.len(), false);
+        let z_2_wide = self.extend(&z_2, z_1.len(), false);
+        let z_1 = self.sub(&z_1, &z_0_wide);
+        let z_1 = self.sub(&z_1, &z_2_wide);
+
+        // z_2 and z_0 do not overlap, so only z_1 has to be added.
+        let upper_width = 2 * width - half;
+        let z_1 = self.extend(&z_1, upper_width, false);
+        let upper = [z_2, z_0[..half].to_vec()].concat();
+        let upper = self.add(&upper, &z_1);
+        [upper, z_0[half..].to_vec()].concat()
+    }
+
+    /// Returns `high + low` with one more bit than `high`, which is at least as wide as `low`.
+    fn add_halves(&mut self, high: &[WireId], low: &[WireId]) -> Vec<WireId> {
+        let low = self.extend(low, high.len(), false);
+        let zero = self.constant(false);
+        let (sum, carry) = self.add_with_carry(high, &low, zero);
+        [vec![carry], sum].concat()
+    }
+
+    /// Multiplies two values with shift-and-add and returns the full product. Row `i` adds the
+    /// `i`-th partial product to the bits of the product that are not final yet.
+    fn schoolbook_full(&mut self, input_1: &[WireId], input_2: &[WireId]) -> Vec<WireId> {
+        let width = input_1.len();
+        let zero = self.constant(false);
+        let mut accumulator = self.mask(input_1, input_2[width - 1]);
+        let mut carry = zero;
+        let mut low = Vec::with_capacity(width);
+        for row in 1..width {
+            low.push(accumulator[width - 1]);
+            let shifted = [vec![carry], accumulator[..width - 1].to_vec()].concat();
+            let partial = self.mask(input_1, input_2[width - 1 - row]);
+            let (sum, next) = self.add_with_carry(&shifted, &partial, zero);
+            accumulator = sum;
+            carry = next;
+        }
+        low.reverse();
+        [vec![carry], accumulator, low].concat()
+    }
+
+    /// Multiplies two values with shift-and-add, only computing the bits of the truncated product.
+    fn schoolbook_truncated(&mut self, input_1: &[WireId], input_2: &[WireId]) -> Vec<WireId> {
+        let width = input_1.len();
+        let mut output = self.mask(input_1, input_2[width - 1]);
+        for shift in 1..width {
+            // Only the `width - shift` most significant bits of the output can still change.
+            let partial = self.mask(&input_1[shift..], input_2[width - 1 - shift]);
+            let sum = self.add(&output[..width - shift], &partial);
+            output.splice(..width - shift, sum);
+        }
+        output
+    }
+
+    /// Returns the value if `bit` is set, and zero otherwise.
+    fn mask(&mut self, input: &[WireId], bit: WireId) -> Vec<WireId> {
+        input.iter().map(|value| self.and(*value, bit)).collect()
+    }
+
+    /// Adds two values of the same width and an incoming carry. The carry out of the most
+    /// significant bit is not computed, which saves an AND gate.
+    pub(crate) fn ripple_carry(
+        &mut self,
+        input_1: &[WireId],
+        input_2: &[WireId],
+        carry: WireId,
+    ) -> Vec<WireId> {
+        self.carry_chain(input_1, input_2, carry, false).0
+    }
+
+    /// Adds two values with a chain of full adders, from the least significant bit up.
+    fn carry_chain(
+        &mut self,
+        input_1: &[WireId],
+        input_2: &[WireId],
+        mut carry: WireId,
+        carry_out: bool,
+    ) -> (Vec<WireId>, Option<WireId>) {
+        assert_eq!(
+            input_1.len(),
+            input_2.len(),
+            "operands must have the same width"
+        );
+        let mut output = Vec::with_capacity(input_1.len());
+        for (bit_1, bit_2) in input_1.iter().zip(input_2).skip(1).rev() {
+            let (sum, next) = self.full_adder(*bit_1, *bit_2, carry);
+            output.push(sum);
+            carry = next;
+        }
+        let carry = if carry_out {
+            let (sum, next) = self.full_adder(input_1[0], input_2[0], carry);
+            output.push(sum);
+            Some(next)
+        } else {
+            let sum = self.xor(input_1[0], input_2[0]);
+            output.push(self.xor(sum, carry));
+            None
+        };
+        output.reverse();
+        (output, carry)
+    }
+
+    /// Returns the sum and the carry of three bits. It costs a single AND gate.
+    pub(crate) fn full_adder(
+        &mut self,
+        input_1: WireId,
+        input_2: WireId,
+        carry: WireId,
+    ) -> (WireId, WireId) {
+        let input_1_carry = self.xor(input_1, carry);
+        let input_2_carry = self.xor(input_2, carry);
+        let sum = self.xor(input_1_carry, input_2);
+        let both = self.and(input_1_carry, input_2_carry);
+        (sum, self.xor(both, carry))
+    }
+}
+
+pub(crate) fn check_widths(input_1: &[WireId], input_2: &[WireId]) {
+    assert_eq!(
+        input_1.len(),
+        input_2.len(),
+        "operands must have the same width"
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{circuit::CircuitBuilder, mpc_core::WireValue};
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+    const SEED: [u8; 32] = [42u8; 32];
+
+    #[test]
+    fn test_add_sub_and_mul_u8() {
+        let mut rng = StdRng::from_seed(SEED);
+        let mut builder = CircuitBuilder::new();
+        let a = builder.input(1, 8);
+        let b = builder.input(2, 8);
+        let sum = builder.add(&a, &b);
+        let product = builder.mul(&a, &b);
+        let difference = builder.sub(&a, &b);
+        builder.output(&sum);
+        builder.output(&product);
+        builder.output(&difference);
+        let circuit = builder.build();
+        assert_eq!(circuit.and_count(), 7 + 57 + 7);
+
+        for _ in 0..100 {
+            let x: u8 = rng.gen();
+            let y: u8 = rng.gen();
+            let got = circuit.compute(&[x.to_bits(), y.to_bits()]);
+            assert_eq!(u8::from_bits(&got[0]), x.wrapping_add(y), "{} + {}", x, y);
+            assert_eq!(u8::from_bits(&got[1]), x.wrapping_mul(y), "{} * {}", x, y);
+            assert_eq!(u8::from_bits(&got[2]), x.wrapping_sub(y), "{} - {}", x, y);
+        }
+    }
+
+    /// Returns the `width` least significant bits of the value.
+    fn low_bits(value: u128, width: usize) -> Vec<bool> {
+        value.to_bits()[128 - width..].to_vec()
+    }
+
+    /// Checks the full and truncated products of random values against native multiplication.
+    fn check_mul(width: usize, signed: bool) {
+        let mut rng = StdRng::from_seed(SEED);
+        let mut builder = CircuitBuilder::new();
+        let a = builder.input(1, width as u32);
+        let b = builder.input(2, width as u32);
+        let full = builder.mul_full(&a, &b, signed);
+        let truncated = builder.mul(&a, &b);
+        builder.output(&full);
+        builder.output(&truncated);
+        let circuit = builder.build();
+
+        for _ in 0..50 {
+            let x = u128::from_bits(&low_bits(rng.gen(), width));
+            let y = u128::from_bits(&low_bits(rng.gen(), width));
+            let got = circuit.compute(&[low_bits(x, width), low_bits(y, width)]);
+            if width <= 64 {
+                let product = if signed {
+                    let shift = 128 - width;
+                    (((x << shift) as i128 >> shift) * ((y << shift) as i128 >> shift)) as u128
+                } else {
+                    x * y
+                };
+                assert_eq!(
+                    got[0],
+                    low_bits(product, 2 * width),
+                    "{}-bit {} * {}",
+                    width,
+                    x,
+                    y
+                );
+            }
+            let product = x.wrapping_mul(y);
+            assert_eq!(
+                got[1],
+                low_bits(product, width),
+                "{}-bit {} * {}",
+                width,
+                x,
+                y
+            );
+        }
+    }
+
+    #[test]
+    fn test_mul() {
+        for width in &[1, 8, 19, 20, 21, 32, 41, 64, 128] {
+            check_mul(*width, false);
+            check_mul(*width, true);
+        }
+    }
+
+    #[test]
+    fn test_karatsuba_saves_and_gates() {
+        let mut builder = CircuitBuilder::new();
+        let a = builder.input(1, 64);
+        let b = builder.input(2, 64);
+        let product = builder.schoolbook_full(&a, &b);
+        builder.output(&product);
+        let schoolbook = builder.build().and_count();
+
+        let mut builder = CircuitBuilder::new();
+        let a = builder.input(1, 64);
+        let b = builder.input(2, 64);
+        let product = builder.mul_full(&a, &b, false);
+        builder.output(&product);
+        assert!(builder.build().and_count() < schoolbook);
+    }
+
+    #[test]
+    fn test_div_rem() {
+        let mut rng = StdRng::from_seed(SEED);
+        let mut builder = CircuitBuilder::new();
+        let a = builder.input(1, 16);
+        let b = builder.input(2, 16);
+        let unsigned = builder.div_rem(&a, &b, false);
+        let signed = builder.div_rem(&a, &b, true);
+        builder.output(&unsigned.quotient);
+        builder.output(&unsigned.remainder);
+        builder.output(&signed.quotient);
+        builder.output(&signed.remainder);
+        builder.output(&[unsigned.divide_by_zero, signed.divide_by_zero]);
+        let circuit = builder.build();
+
+        let mut samples = vec![(0, 0), (1234, 0), (-1234, 0), (i16::MIN, -1), (i16::MIN, 1)];
+        for _ in 0..200 {
+            // Small divisors make large quotients more likely.
+            samples.push((rng.gen(), rng.gen::<i16>() >> rng.gen_range(0, 16)));
+        }
+        for (x, y) in samples {
+            let got = circuit.compute(&[x.to_bits(), y.to_bits()]);
+            let (ux, uy) = (x as u16, y as u16);
+            let error = vec![y == 0, y == 0];
+            assert_eq!(got[4], error, "{} / {}", x, y);
+            if y == 0 {
+                assert_eq!(u16::from_bits(&got[0]), u16::MAX, "{} / 0", ux);
+                assert_eq!(u16::from_bits(&got[1]), ux, "{} % 0", ux);
+                assert_eq!(i16::from_bits(&got[2]), -1, "{} / 0", x);
+                assert_eq!(i16::from_bits(&got[3]), x, "{} % 0", x);
+                continue;
+            }
+            assert_eq!(u16::from_bits(&got[0]), ux / uy, "{} / {}", ux, uy);
+            assert_eq!(u16::from_bits(&got[1]), ux % uy, "{} % {}", ux, uy);
+            assert_eq!(i16::from_bits(&got[2]), x.wrapping_div(y), "{} / {}", x, y);
+            assert_eq!(i16::from_bits(&got[3]), x.wrapping_rem(y), "{} % {}", x, y);
+        }
+    }
+
+    #[test]
+    fn test_carry_chain() {
+        let mut rng = StdRng::from_seed(SEED);
+        let mut builder = CircuitBuilder::new();
+        let a = builder.input(1, 64);
+        let b = builder.input(2, 64);
+        // Adds two 64-bit values as two 32-bit words.
+        let zero = builder.constant(false);
+        let (low, carry) = builder.add_with_carry(&a[32..], &b[32..], zero);
+        let (high, carry) = builder.add_with_carry(&a[..32], &b[..32], carry);
+        let (difference, borrow) = builder.sub_with_borrow(&a, &b);
+        builder.output(&[high, low].concat());
+        builder.output(&[carry, borrow]);
+        builder.output(&difference);
+        let circuit = builder.build();
+
+        for _ in 0..100 {
+            let x: u64 = rng.gen::<u64>() >> rng.gen_range(0, 2);
+            let y: u64 = rng.gen::<u64>() >> rng.gen_range(0, 2);
+            let got = circuit.compute(&[x.to_bits(), y.to_bits()]);
+            let (sum, overflow) = x.overflowing_add(y);
+            assert_eq!(u64::from_bits(&got[0]), sum, "{} + {}", x, y);
+            assert_eq!(got[1], vec![overflow, x < y], "{} {}", x, y);
+            assert_eq!(u64::from_bits(&got[2]), x.wrapping_sub(y), "{} - {}", x, y);
+        }
+    }
+
+    #[test]
+    fn test_extend() {
+        let mut builder = CircuitBuilder::new();
+        let a = builder.input(1, 8);
+        let signed = builder.extend(&a, 16, true);
+        let unsigned = builder.extend(&a, 16, false);
+        builder.output(&signed);
+        builder.output(&unsigned);
+        let circuit = builder.build();
+
+        let got = circuit.compute(&[(-3i8).to_bits()]);
+        assert_eq!(i16::from_bits(&got[0]), -3);
+        assert_eq!(u16::from_bits(&got[1]), 253);
+    }
+}
@@ -0,0 +1,306 @@
+//! Implements the bitwise, shift, and rotate gadgets on top of `CircuitBuilder`. Every gadget takes
+//! and returns groups of wires with the most significant bit first. Shifts and rotates by public
+//! amounts only rewire the input, so they are free. Shifts and rotates by secret amounts use a
+//! barrel shifter, which costs one mux per bit of the distance.
+
+use crate::{
+    arithmetic::check_widths,
+    circuit::{CircuitBuilder, WireId},
+};
+
+impl CircuitBuilder {
+    /// Returns the bitwise AND of two values of the same width.
+    pub fn bitwise_and(&mut self, input_1: &[WireId], input_2: &[WireId]) -> Vec<WireId> {
+        check_widths(input_1, input_2);
+        input_1
+            .iter()
+            .zip(input_2)
+            .map(|(bit_1, bit_2)| self.and(*bit_1, *bit_2))
+            .collect()
+    }
+
+    /// Returns the bitwise OR of two values of the same width.
+    pub fn bitwise_or(&mut self, input_1: &[WireId], input_2: &[WireId]) -> Vec<WireId> {
+        check_widths(input_1, input_2);
+        input_1
+            .iter()
+            .zip(input_2)
+            .map(|(bit_1, bit_2)| self.or(*bit_1, *bit_2))
+            .collect()
+    }
+
+    /// Returns the bitwise XOR of two values of the same width. It is free with Free-XOR.
+    pub fn bitwise_xor(&mut self, input_1: &[WireId], input_2: &[WireId]) -> Vec<WireId> {
+        check_widths(input_1, input_2);
+        input_1
+            .iter()
+            .zip(input_2)
+            .map(|(bit_1, bit_2)| self.xor(*bit_1, *bit_2))
+            .collect()
+    }
+
+    /// Returns the bitwise NOT of a value.
+    pub fn bitwise_not(&mut self, input: &[WireId]) -> Vec<WireId> {
+        input.iter().map(|bit| self.inv(*bit)).collect()
+    }
+
+    /// Shifts a value to the left by a public amount. The bits that are shifted out are dropped.
+    pub fn shift_left(&mut self, input: &[WireId], amount: usize) -> Vec<WireId> {
+        let amount = amount.min(input.len());
+        let zero = self.constant(false);
+        let mut output = input[amount..].to_vec();
+        output.resize(input.len(), zero);
+        output
+    }
+
+    /// Shifts a value to the right by a public amount. Signed values are shifted arithmetically,
+    /// i.e., the sign bit is copied into the vacated bits.
+    pub fn shift_right(&mut self, input: &[WireId], amount: usize, signed: bool) -> Vec<WireId> {
+        let width = input.len();
+        let amount = amount.min(width);
+        let padding = if signed {
+            input[0]
+        } else {
+            self.constant(false)
+        };
+        let mut output = vec![padding; amount];
+        output.extend_from_slice(&input[..width - amount]);
+        output
+    }
+
+    /// Rotates a value to the left by a public amount.
+    pub fn rotate_left(&mut self, input: &[WireId], amount: usize) -> Vec<WireId> {
+        let mut output = input.to_vec();
+        if !input.is_empty() {
+            output.rotate_left(amount % input.len());
+        }
+        output
+    }
+
+    /// Rotates a value to the right by a public amount.
+    pub fn rotate_right(&mut self, input: &[WireId], amount: usize) -> Vec<WireId> {
+        let mut output = input.to_vec();
+        if !input.is_empty() {
+            output.rotate_right(amount % input.len());
+        }
+        output
+    }
+
+    /// Shifts a value to the left by a secret `distance`, which is an unsigned value of any width.
+    /// Shifting by the width of the value or more returns zero.
+    pub fn shift_left_secret(&mut self, input: &[WireId], distance: &[WireId]) -> Vec<WireId> {
+        let width = input.len();
+        self.barrel_shifter(
+            input,
+            distance,
+            saturated_stages(width),
+            |builder, value, shift| builder.shift_left(value, shift),
+        )
+    }
+
+    /// Shifts a value to the right by a secret `distance`, which is an unsigned value of any width.
+    /// Signed values are shifted arithmetically. Shifting by the width of the value or more returns
+    /// zero, or minus one for negative signed values.
+    pub fn shift_right_secret(
+        &mut self,
+        input: &[WireId],
+        distance: &[WireId],
+        signed: bool,
+    ) -> Vec<WireId> {
+        let width = input.len();
+        self.barrel_shifter(
+            input,
+            distance,
+            saturated_stages(width),
+            |builder, value, shift| builder.shift_right(value, shift, signed),
+        )
+    }
+
+    /// Rotates a value to the left by a secret `distance`, which is an unsigned value of any width.
+    pub fn rotate_left_secret(&mut self, input: &[WireId], distance: &[WireId]) -> Vec<WireId> {
+        let width = input.len();
+        self.barrel_shifter(
+            input,
+            distance,
+            modular_stages(width),
+            |builder, value, shift| builder.rotate_left(value, shift),
+        )
+    }
+
+    /// Rotates a value to the right by a secret `distance`, which is an unsigned value of any
+    /// width.
+    pub fn rotate_right_secret(&mut self, input: &[WireId], distance: &[WireId]) -> Vec<WireId> {
+        let width = input.len();
+        self.barrel_shifter(
+            input,
+            distance,
+            modular_stages(width),
+            |builder, value, shift| builder.rotate_right(value, shift),
+        )
+    }
+
+    /// Applies the `i`-th least significant bit of `distance` as a mux between the value and the
+    /// value moved by `stage_amount(i)` bits.
+    fn barrel_shifter<A, F>(
+        &mut self,
+        input: &[WireId],
+        distance: &[WireId],
+        stage_amount: A,
+        stage: F,
+    ) -> Vec<WireId>
+    where
+        A: Fn(usize) -> usize,
+        F: Fn(&mut Self, &[WireId], usize) -> Vec<WireId>,
+    {
+        let mut output = input.to_vec();
+        for (power, bit) in distance.iter().rev().enumerate() {
+            let moved = stage(self, &output, stage_amount(power));
+            output = self.mux(*bit, &moved, &output);
+        }
+        output
+    }
+}
+
+/// Returns the amount of each stage of a shift: `2^i`, or the width if that is larger.
+fn saturated_stages(width: usize) -> impl Fn(usize) -> usize {
+    move |power| {
+        if power < 64 && (1u64 << power) < width as u64 {
+            1 << power
+        } else {
+            width
+        }
+    }
+}
+
+/// Returns the amount of each stage of a rotation: `2^i` modulo the width.
+fn modular_stages(width: usize) -> impl Fn(usize) -> usize {
+    move |power| {
+        let mut amount = 1 % width.max(1);
+        for _ in 0..power {
+            amount = (2 * amount) % width.max(1);
+        }
+        amount
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{circuit::CircuitBuilder, mpc_core::WireValue};
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+    const SEED: [u8; 32] = [42u8; 32];
+
+    #[test]
+    fn test_bitwise() {
+        let mut rng = StdRng::from_seed(SEED);
+        let mut builder = CircuitBuilder::new();
+        let a = builder.input(1, 16);
+        let b = builder.input(2, 16);
+        let and = builder.bitwise_and(&a, &b);
+        let or = builder.bitwise_or(&a, &b);
+        let xor = builder.bitwise_xor(&a, &b);
+        let not = builder.bitwise_not(&a);
+        for output in [and, or, xor, not].iter() {
+            builder.output(output);
+        }
+        let circuit = builder.build();
+        assert_eq!(circuit.and_count(), 2 * 16);
+
+        for _ in 0..20 {
+            let x: u16 = rng.gen();
+            let y: u16 = rng.gen();
+            let got = circuit.compute(&[x.to_bits(), y.to_bits()]);
+            assert_eq!(u16::from_bits(&got[0]), x & y);
+            assert_eq!(u16::from_bits(&got[1]), x | y);
+            assert_eq!(u16::from_bits(&got[2]), x ^ y);
+            assert_eq!(u16::from_bits(&got[3]), !x);
+        }
+    }
+
+    #[test]
+    fn test_public_shifts() {
+        let mut builder = CircuitBuilder::new();
+        let a = builder.input(1, 8);
+        for amount in 0..10 {
+            let left = builder.shift_left(&a, amount);
+            let logical = builder.shift_right(&a, amount, false);
+            let arithmetic = builder.shift_right(&a, amount, true);
+            let rotate_left = builder.rotate_left(&a, amount);
+            let rotate_right = builder.rotate_right(&a, amount);
+            builder.output(&[left, logical, arithmetic, rotate_left, rotate_right].concat());
+        }
+        let circuit = builder.build();
+        assert_eq!(circuit.and_count(), 0);
+
+        let x = 0b1011_0110u8;
+        let got = circuit.compute(&[x.to_bits()]);
+        for (amount, output) in got.iter().enumerate() {
+            let shifts = output.chunks(8).map(u8::from_bits).collect::<Vec<u8>>();
+            let amount = amount as u32;
+            let want = vec![
+                x.checked_shl(amount).unwrap_or(0),
+                x.checked_shr(amount).unwrap_or(0),
+                (x as i8).checked_shr(amount).unwrap_or(-1) as u8,
+                x.rotate_left(amount),
+                x.rotate_right(amount),
+            ];
+            assert_eq!(shifts, want, "{}", amount);
+        }
+    }
+
+    #[test]
+    fn test_secret_shifts() {
+        let mut rng = StdRng::from_seed(SEED);
+        let mut builder = CircuitBuilder::new();
+        let a = builder.input(1, 32);
+        let distance = builder.input(2, 8);
+        let left = builder.shift_left_secret(&a, &distance);
+        let logical = builder.shift_right_secret(&a, &distance, false);
+        let arithmetic = builder.shift_right_secret(&a, &distance, true);
+        let rotate_left = builder.rotate_left_secret(&a, &distance);
+        let rotate_right = builder.rotate_right_secret(&a, &distance);
+        for output in [left, logical, arithmetic, rotate_left, rotate_right].iter() {
+            builder.output(output);
+        }
+        let circuit = builder.build();
+
+        for _ in 0..100 {
+            let x: u32 = rng.gen();
+            let y: u8 = if rng.gen() {
+                rng.gen_range(0, 40)
+            } else {
+                rng.gen()
+            };
+            let got = circuit.compute(&[x.to_bits(), y.to_bits()]);
+            let amount = y as u32;
+            assert_eq!(u32::from_bits(&got[0]), x.checked_shl(amount).unwrap_or(0));
+            assert_eq!(u32::from_bits(&got[1]), x.checked_shr(amount).unwrap_or(0));
+            let signed =
+                (x as i32)
+                    .checked_shr(amount)
+                    .unwrap_or(if (x as i32) < 0 { -1 } else { 0 });
+            assert_eq!(i32::from_bits(&got[2]), signed, "{} >> {}", x as i32, y);
+            assert_eq!(u32::from_bits(&got[3]), x.rotate_left(amount));
+            assert_eq!(u32::from_bits(&got[4]), x.rotate_right(amount));
+        }
+    }
+
+    #[test]
+    fn test_secret_rotate_on_any_width() {
+        let mut rng = StdRng::from_seed(SEED);
+        let mut builder = CircuitBuilder::new();
+        let a = builder.input(1, 10);
+        let distance = builder.input(2, 8);
+        let rotated = builder.rotate_left_secret(&a, &distance);
+        builder.output(&rotated);
+        let circuit = builder.build();
+
+        for _ in 0..20 {
+            let x: u16 = rng.gen_range(0, 1 << 10);
+            let y: u8 = rng.gen();
+            let got = circuit.compute(&[x.to_bits()[6..].to_vec(), y.to_bits()]);
+            let shift = y as u32 % 10;
+            let want = ((x << shift) | (x >> (10 - shift))) & 0x3FF;
+            assert_eq!(u16::from_bits(&got[0]), want, "{} {}", x, y);
+        }
+    }
+}
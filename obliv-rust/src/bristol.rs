@@ -0,0 +1,719 @@
+//! Loads circuits written in the Bristol Fashion and the older Bristol Format into `Circuit`s,
+//! and writes `Circuit`s in the Bristol Fashion.
+//!
+//! Both formats list the bits of a value least significant bit first, while `Circuit` stores the
+//! most significant bit first. The wires of every input and output value are reversed while
+//! loading and writing, so a loaded circuit can be used with `GarblingWire` and `EvaluatingWire`
+//! directly. The `i`-th input value is provided by party `i + 1`; change `Circuit::inputs` to
+//! assign it differently.
+
+use crate::circuit::{BooleanGate, Circuit, CircuitBuilder, WireId};
+use std::{
+    collections::{BTreeMap, HashMap},
+    convert::TryFrom,
+    fmt, fs, io,
+    path::Path,
+};
+
+// ----------------------------------------------------------------------------------------------
+// -                                  Type Definitions                                          -
+// ----------------------------------------------------------------------------------------------
+
+/// The errors that can happen while loading a Bristol circuit.
+#[derive(Debug)]
+pub enum BristolError {
+    Io(io::Error),
+    /// The file is not a valid Bristol circuit. `line` starts from 1.
+    Parse {
+        line: usize,
+        message: String,
+    },
+}
+
+// ----------------------------------------------------------------------------------------------
+// -                                    Impl blocks                                             -
+// ----------------------------------------------------------------------------------------------
+
+impl fmt::Display for BristolError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BristolError::Io(error) => write!(f, "failed to read the circuit: {}", error),
+            BristolError::Parse { line, message } => write!(f, "line {}: {}", line, message),
+        }
+    }
+}
+
+impl std::error::Error for BristolError {}
+
+impl From<io::Error> for BristolError {
+    fn from(error: io::Error) -> Self {
+        BristolError::Io(error)
+    }
+}
+
+impl Circuit {
+    /// Loads a circuit from a file in the Bristol Fashion.
+    pub fn from_bristol_fashion_file<P: AsRef<Path>>(path: P) -> Result<Circuit, BristolError> {
+        Circuit::from_bristol_fashion(&fs::read_to_string(path)?)
+    }
+
+    /// Loads a circuit from a file in the old Bristol Format.
+    pub fn from_bristol_format_file<P: AsRef<Path>>(path: P) -> Result<Circuit, BristolError> {
+        Circuit::from_bristol_format(&fs::read_to_string(path)?)
+    }
+
+    /// Parses a circuit in the Bristol Fashion, i.e., a header that lists the width of every input
+    /// and output value, followed by the gates.
+    pub fn from_bristol_fashion(text: &str) -> Result<Circuit, BristolError> {
+        let mut lines = Lines::new(text);
+        let (gate_count, wire_count) = lines.counts()?;
+        let input_widths = lines.widths()?;
+        let output_widths = lines.widths()?;
+        parse_gates(lines, gate_count, wire_count, &input_widths, &output_widths)
+    }
+
+    /// Parses a circuit in the old Bristol Format, i.e., a header that lists the width of the two
+    /// inputs and the single output, followed by the gates.
+    pub fn from_bristol_format(text: &str) -> Result<Circuit, BristolError> {
+        let mut lines = Lines::new(text);
+        let (gate_count, wire_count) = lines.counts()?;
+        let (line, widths) = lines.numbers()?;
+        if widths.len() != 3 {
+            return Err(parse_error(
+                line,
+                "expected the widths of two inputs and one output",
+            ));
+        }
+        parse_gates(lines, gate_count, wire_count, &widths[..2], &widths[2..])
+    }
+
+    /// Writes the circuit to a file in the Bristol Fashion. See `to_bristol_fashion`.
+    pub fn to_bristol_fashion_file<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        fs::write(path, self.to_bristol_fashion())
+    }
+
+    /// Returns the circuit in the Bristol Fashion.
+    ///
+    /// The Bristol Fashion has no notion of parties, so the input groups of each party are merged
+    /// into a single input value, in their order, and the values are sorted by the id of the party
+    /// that provides them. Loading the circuit gives the `i`-th value to party `i + 1`, so the
+    /// owners survive the round trip when the parties are numbered from 1 without gaps. The output
+    /// values keep their order. Outputs that are inputs of the circuit, or that repeat an earlier
+    /// output, are copied to their place with `EQW` gates.
+    pub fn to_bristol_fashion(&self) -> String {
+        let mut inputs: BTreeMap<usize, Vec<WireId>> = BTreeMap::new();
+        for input in self.inputs.iter() {
+            inputs
+                .entry(input.party)
+                .or_default()
+                .extend(input.wires.iter().copied());
+        }
+
+        let mut wires: Vec<Option<usize>> = vec![None; self.wire_count];
+        let mut next_wire = 0;
+        for input in inputs.values() {
+            for wire in input.iter().rev() {
+                wires[*wire] = Some(next_wire);
+                next_wire += 1;
+            }
+        }
+
+        // The outputs have to be the last wires, so the gates that compute an output write to it
+        // directly, and every other output is copied after all gates.
+        let output_wires = self
+            .outputs
+            .iter()
+            .flat_map(|output| output.iter().rev())
+            .collect::<Vec<_>>();
+        let mut is_gate_output = vec![false; self.wire_count];
+        for gate in self.gates.iter() {
+            is_gate_output[gate.output()] = true;
+        }
+        let mut claimed = vec![false; self.wire_count];
+        let mut copies = vec![];
+        for (index, wire) in output_wires.iter().enumerate() {
+            if is_gate_output[**wire] && !claimed[**wire] {
+                claimed[**wire] = true;
+            } else {
+                copies.push((**wire, index));
+            }
+        }
+        let gate_wire_count = self
+            .gates
+            .iter()
+            .filter(|gate| !claimed[gate.output()])
+            .count();
+        let first_output = next_wire + gate_wire_count;
+        for (index, wire) in output_wires.iter().enumerate() {
+            if claimed[**wire] && wires[**wire].is_none() {
+                wires[**wire] = Some(first_output + index);
+            }
+        }
+
+        let mut gates = String::new();
+        for gate in self.gates.iter() {
+            let output = match wires[gate.output()] {
+                Some(output) => output,
+                None => {
+                    wires[gate.output()] = Some(next_wire);
+                    next_wire += 1;
+                    next_wire - 1
+                }
+            };
+            let wire = |wire: WireId| wires[wire].expect("the circuit is not topologically sorted");
+            let line = match *gate {
+                BooleanGate::And(input_1, input_2, _) => {
+                    format!("2 1 {} {} {} AND\n", wire(input_1), wire(input_2), output)
+                }
+                BooleanGate::Xor(input_1, input_2, _) => {
+                    format!("2 1 {} {} {} XOR\n", wire(input_1), wire(input_2), output)
+                }
+                BooleanGate::Inv(input, _) => format!("1 1 {} {} INV\n", wire(input), output),
+                BooleanGate::Const(value, _) => format!("1 1 {} {} EQ\n", value as u8, output),
+            };
+            gates.push_str(&line);
+        }
+        for (wire, index) in copies.iter() {
+            let input = wires[*wire].expect("an output wire is never set");
+            gates.push_str(&format!("1 1 {} {} EQW\n", input, first_output + index));
+        }
+
+        let widths = |values: Vec<usize>| {
+            let mut line = vec![values.len()];
+            line.extend(values);
+            line.iter()
+                .map(|value| value.to_string())
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+        format!(
+            "{} {}\n{}\n{}\n\n{}",
+            self.gates.len() + copies.len(),
+            first_output + output_wires.len(),
+            widths(inputs.values().map(|input| input.len()).collect()),
+            widths(self.outputs.iter().map(|output| output.len()).collect()),
+            gates
+        )
+    }
+}
+
+// ----------------------------------------------------------------------------------------------
+//                                   Internal Functions                                         -
+// ----------------------------------------------------------------------------------------------
+
+/// Iterates over the non-empty lines of a circuit together with their line number.
+struct Lines<'a> {
+    lines: std::iter::Enumerate<std::str::Lines<'a>>,
+}
+
+impl<'a> Lines<'a> {
+    fn new(text: &'a str) -> Lines<'a> {
+        Lines {
+            lines: text.lines().enumerate(),
+        }
+    }
+
+    fn next_tokens(&mut self) -> Option<(usize, Vec<&'a str>)> {
+        self.lines
+            .by_ref()
+            .map(|(index, line)| (index + 1, line.split_whitespace().collect::<Vec<_>>()))
+            .find(|(_, tokens)| !tokens.is_empty())
+    }
+
+    fn numbers(&mut self) -> Result<(usize, Vec<usize>), BristolError> {
+        let (line, tokens) = self
+            .next_tokens()
+            .ok_or_else(|| parse_error(0, "unexpected end of the circuit"))?;
+        let numbers = tokens
+            .iter()
+            .map(|token| parse_number(line, token))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok((line, numbers))
+    }
+
+    fn counts(&mut self) -> Result<(usize, usize), BristolError> {
+        match self.numbers()? {
+            (_, numbers) if numbers.len() == 2 => Ok((numbers[0], numbers[1])),
+            (line, _) => Err(parse_error(line, "expected the number of gates and wires")),
+        }
+    }
+
+    /// Parses a line of the form `count width_1 ... width_count`.
+    fn widths(&mut self) -> Result<Vec<usize>, BristolError> {
+        match self.numbers()? {
+            (_, numbers) if !numbers.is_empty() && numbers.len() == numbers[0] + 1 => {
+                Ok(numbers[1..].to_vec())
+            }
+            (line, _) => Err(parse_error(line, "expected a count followed by the widths")),
+        }
+    }
+}
+
+fn parse_gates(
+    mut lines: Lines,
+    gate_count: usize,
+    wire_count: usize,
+    input_widths: &[usize],
+    output_widths: &[usize],
+) -> Result<Circuit, BristolError> {
+    // The header is checked before anything is allocated, so that a hostile header cannot
+    // overflow the widths. Only the wires that are set are stored, so the memory does not depend
+    // on the announced number of wires.
+    total_width(input_widths, wire_count, "inputs")?;
+    let output_total = total_width(output_widths, wire_count, "outputs")?;
+    let input_widths = input_widths
+        .iter()
+        .enumerate()
+        .map(|(index, width)| {
+            u32::try_from(*width)
+                .map_err(|_| parse_error(0, &format!("input {} is too wide", index + 1)))
+        })
+        .collect::<Result<Vec<u32>, _>>()?;
+    let mut builder = CircuitBuilder::new();
+    let mut wires: HashMap<usize, WireId> = HashMap::new();
+
+    let mut next_wire = 0;
+    for (index, width) in input_widths.into_iter().enumerate() {
+        let inputs = builder.input(index + 1, width);
+        for wire in inputs.into_iter().rev() {
+            wires.insert(next_wire, wire);
+            next_wire += 1;
+        }
+    }
+
+    for _ in 0..gate_count {
+        let (line, tokens) = lines
+            .next_tokens()
+            .ok_or_else(|| parse_error(0, "fewer gates than announced"))?;
+        if tokens.len() < 3 {
+            return Err(parse_error(line, "incomplete gate"));
+        }
+        let input_count = parse_number(line, tokens[0])?;
+        let output_count = parse_number(line, tokens[1])?;
+        let wire_tokens = input_count.checked_add(output_count);
+        if wire_tokens.and_then(|count| count.checked_add(3)) != Some(tokens.len()) {
+            return Err(parse_error(line, "wrong number of wires"));
+        }
+        let inputs = &tokens[2..2 + input_count];
+        let outputs = &tokens[2 + input_count..2 + input_count + output_count];
+        let operation = tokens[tokens.len() - 1];
+
+        let read = |token: &str| -> Result<WireId, BristolError> {
+            let wire = parse_number(line, token)?;
+            wires
+                .get(&wire)
+                .copied()
+                .ok_or_else(|| parse_error(line, &format!("wire {} is read before set", wire)))
+        };
+        let results = match (operation, input_count, output_count) {
+            ("XOR", 2, 1) => vec![builder.xor(read(inputs[0])?, read(inputs[1])?)],
+            ("AND", 2, 1) => vec![builder.and(read(inputs[0])?, read(inputs[1])?)],
+            ("INV", 1, 1) | ("NOT", 1, 1) => vec![builder.inv(read(inputs[0])?)],
+            ("EQW", 1, 1) => vec![read(inputs[0])?],
+            ("EQ", 1, 1) => match inputs[0] {
+                "0" => vec![builder.constant(false)],
+                "1" => vec![builder.constant(true)],
+                _ => return Err(parse_error(line, "EQ expects a constant 0 or 1")),
+            },
+            ("MAND", _, _) if input_count == 2 * output_count => {
+                let mut results = Vec::with_capacity(output_count);
+                for index in 0..output_count {
+                    let input_1 = read(inputs[index])?;
+                    let input_2 = read(inputs[output_count + index])?;
+                    results.push(builder.and(input_1, input_2));
+                }
+                results
+            }
+            _ => {
+                return Err(parse_error(
+                    line,
+                    &format!(
+                        "unsupported gate {} with {} inputs and {} outputs",
+                        operation, input_count, output_count
+                    ),
+                ))
+            }
+        };
+        for (token, result) in outputs.iter().zip(results) {
+            let wire = parse_number(line, token)?;
+            if wire >= wire_count {
+                return Err(parse_error(line, &format!("wire {} is out of range", wire)));
+            }
+            wires.insert(wire, result);
+        }
+    }
+
+    let mut next_wire = wire_count - output_total;
+    for width in output_widths.iter() {
+        let mut output = (next_wire..next_wire + width)
+            .map(|wire| {
+                wires
+                    .get(&wire)
+                    .copied()
+                    .ok_or_else(|| parse_error(0, &format!("output wire {} is unset", wire)))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        output.reverse();
+        builder.output(&output);
+        next_wire += width;
+    }
+    Ok(builder.build())
+}
+
+/// Returns the total width of the groups, which must fit in the wires of the circuit.
+fn total_width(widths: &[usize], wire_count: usize, groups: &str) -> Result<usize, BristolError> {
+    widths
+        .iter()
+        .try_fold(0usize, |total, width| total.checked_add(*width))
+        .filter(|total| *total <= wire_count)
+        .ok_or_else(|| parse_error(0, &format!("the {} do not fit in the wires", groups)))
+}
+
+fn parse_number(line: usize, token: &str) -> Result<usize, BristolError> {
+    token
+        .parse()
+        .map_err(|_| parse_error(line, &format!("expected a number, found {}", token)))
+}
+
+fn parse_error(line: usize, message: &str) -> BristolError {
+    BristolError::Parse {
+        line,
+        message: message.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ot::OtKind;
+    use crate::{
+        free_xor_garbling::FreeXorBit,
+        mpc_core::{
+            evaluate_circuit, garble_circuit, DecodingInfo, EvaluatingWire, GarblingSession,
+            GarblingWire, Operation, Party, Protocol, Role, WireValue,
+        },
+        wires::Wire8Bit,
+    };
+    use ocelot::ot::{Receiver, Sender};
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+    use scuttlebutt::{AbstractChannel, AesRng, Block, TrackChannel};
+    use std::{
+        io::{BufReader, BufWriter},
+        os::unix::net::UnixStream,
+    };
+    const SEED: [u8; 32] = [42u8; 32];
+
+    /// A 2-bit adder that drops the final carry, in the Bristol Fashion.
+    const ADDER_2: &str = "4 8
+2 2 2
+1 2
+
+2 1 0 2 4 AND
+2 1 1 3 5 XOR
+2 1 0 2 6 XOR
+2 1 5 4 7 XOR
+";
+
+    /// An 8-bit adder built from a chain of full adders, in the Bristol Fashion.
+    fn adder_8() -> String {
+        // Inputs are wires 0..8 and 8..16, least significant bit first.
+        let mut gates = vec![];
+        let mut next = 16;
+        let mut carry = None;
+        let mut sums = vec![];
+        for bit in 0..8 {
+            let (a, b) = (bit, 8 + bit);
+            let sum = next;
+            gates.push(format!("2 1 {} {} {} XOR", a, b, sum));
+            next += 1;
+            match carry {
+                None => {
+                    sums.push(sum);
+                    gates.push(format!("2 1 {} {} {} AND", a, b, next));
+                    carry = Some(next);
+                    next += 1;
+                }
+                Some(c) => {
+                    sums.push(next);
+                    gates.push(format!("2 1 {} {} {} XOR", sum, c, next));
+                    let (ac, bc, both) = (next + 1, next + 2, next + 3);
+                    gates.push(format!("2 1 {} {} {} XOR", a, c, ac));
+                    gates.push(format!("2 1 {} {} {} XOR", b, c, bc));
+                    gates.push(format!("2 1 {} {} {} AND", ac, bc, both));
+                    gates.push(format!("2 1 {} {} {} XOR", both, c, next + 4));
+                    carry = Some(next + 4);
+                    next += 5;
+                }
+            }
+        }
+        // The outputs have to be the last wires.
+        for sum in sums {
+            gates.push(format!("1 1 {} {} EQW", sum, next));
+            next += 1;
+        }
+        format!(
+            "{} {}\n2 8 8\n1 8\n\n{}\n",
+            gates.len(),
+            next,
+            gates.join("\n")
+        )
+    }
+
+    #[test]
+    fn test_parse_bristol_fashion() {
+        let circuit = Circuit::from_bristol_fashion(ADDER_2).unwrap();
+        assert_eq!(circuit.inputs.len(), 2);
+        assert_eq!(circuit.inputs[0].party, 1);
+        assert_eq!(circuit.inputs[1].party, 2);
+        assert_eq!(circuit.outputs[0].len(), 2);
+        for x in 0..4u8 {
+            for y in 0..4u8 {
+                let got =
+                    circuit.compute(&[vec![x & 2 != 0, x & 1 != 0], vec![y & 2 != 0, y & 1 != 0]]);
+                assert_eq!(u8::from_bits(&got[0]), (x + y) % 4);
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse_bristol_format() {
+        let text = "3 5\n1 1 2\n\n2 1 0 1 2 AND\n1 1 0 3 INV\n2 1 3 1 4 XOR\n";
+        let circuit = Circuit::from_bristol_format(text).unwrap();
+        for &(x, y) in [(false, false), (false, true), (true, false), (true, true)].iter() {
+            let got = circuit.compute(&[vec![x], vec![y]]);
+            assert_eq!(got, vec![vec![!x ^ y, !x]]);
+        }
+    }
+
+    #[test]
+    fn test_parse_special_gates() {
+        let text = "3 7\n2 2 2\n1 2\n\n1 1 1 4 EQ\n4 2 0 1 2 3 5 6 MAND\n1 1 4 6 EQW\n";
+        let circuit = Circuit::from_bristol_fashion(text).unwrap();
+        let got = circuit.compute(&[vec![true, true], vec![true, false]]);
+        // Wire 5 is the AND of the least significant bits, wire 6 is overwritten by the constant.
+        assert_eq!(got, vec![vec![true, false]]);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(matches!(
+            Circuit::from_bristol_fashion("1 3\n1 1\n1 1\n\n2 1 0 1 2 AND\n"),
+            Err(BristolError::Parse { line: 5, .. })
+        ));
+        assert!(matches!(
+            Circuit::from_bristol_fashion("1 3\n2 1 1\n1 1\n\n2 1 0 1 2 NAND\n"),
+            Err(BristolError::Parse { line: 5, .. })
+        ));
+        assert!(matches!(
+            Circuit::from_bristol_fashion("2 3\n2 1 1\n1 1\n\n2 1 0 1 2 AND\n"),
+            Err(BristolError::Parse { .. })
+        ));
+    }
+
+    #[test]
+    fn test_hostile_headers() {
+        let max = usize::MAX;
+        for text in [
+            format!("1 3\n2 {} 2\n1 1\n\n2 1 0 1 2 AND\n", max),
+            format!("1 3\n2 1 1\n2 {} 2\n\n2 1 0 1 2 AND\n", max),
+            "1 3\n2 1 5\n1 1\n\n2 1 0 1 2 AND\n".to_string(),
+            format!("1 {}\n2 {} 1\n1 1\n\n2 1 0 1 2 AND\n", max, max - 1),
+            format!("1 {}\n2 1 1\n1 1\n\n2 1 0 1 2 AND\n", max),
+        ]
+        .iter()
+        {
+            assert!(matches!(
+                Circuit::from_bristol_fashion(text),
+                Err(BristolError::Parse { line: 0, .. })
+            ));
+        }
+        assert!(matches!(
+            Circuit::from_bristol_fashion(&format!("1 3\n2 1 1\n1 1\n\n{} 1 0 1 2 AND\n", max)),
+            Err(BristolError::Parse { line: 5, .. })
+        ));
+    }
+
+    #[test]
+    fn test_export_operations() {
+        let mut rng = StdRng::from_seed(SEED);
+        for operation in [Operation::Add, Operation::Mul].iter() {
+            let circuit = operation.circuit(8, false);
+            let text = circuit.to_bristol_fashion();
+            assert!(text.starts_with(&format!("{} ", circuit.gates.len())));
+            let loaded = Circuit::from_bristol_fashion(&text).unwrap();
+            assert_eq!(loaded.and_count(), circuit.and_count());
+            for _ in 0..20 {
+                let inputs = [rng.gen::<u8>().to_bits(), rng.gen::<u8>().to_bits()];
+                // Both operands are intermediate values of party 0, so they are merged.
+                assert_eq!(loaded.compute(&[inputs.concat()]), circuit.compute(&inputs));
+            }
+        }
+    }
+
+    #[test]
+    fn test_export_groups_inputs_by_party() {
+        let mut builder = CircuitBuilder::new();
+        let b = builder.input(2, 2);
+        let a = builder.input(1, 3);
+        let one = builder.constant(true);
+        let sum = builder.xor(a[2], b[1]);
+        // Outputs that are not written by a gate of their own are copied.
+        builder.output(&[a[0], sum, sum, one]);
+        builder.output(&[b[0]]);
+        let circuit = builder.build();
+
+        let text = circuit.to_bristol_fashion();
+        assert!(text.contains("\n2 3 2\n2 4 1\n"));
+        assert_eq!(text.matches("EQW").count(), 3);
+        let loaded = Circuit::from_bristol_fashion(&text).unwrap();
+        let got = loaded.compute(&[vec![true, false, true], vec![true, false]]);
+        assert_eq!(got, vec![vec![true, true, true, true], vec![true]]);
+        assert_eq!(
+            circuit.compute(&[vec![true, false], vec![true, false, true]]),
+            got
+        );
+    }
+
+    #[test]
+    fn test_export_merges_the_inputs_of_a_party() {
+        let mut rng = StdRng::from_seed(SEED);
+        let mut builder = CircuitBuilder::new();
+        let a = builder.input(1, 8);
+        let b = builder.input(2, 8);
+        let c = builder.input(1, 8);
+        let sum = builder.add(&a, &b);
+        let product = builder.mul(&sum, &c);
+        builder.output(&product);
+        let circuit = builder.build();
+
+        let loaded = Circuit::from_bristol_fashion(&circuit.to_bristol_fashion()).unwrap();
+        let parties = loaded
+            .inputs
+            .iter()
+            .map(|input| (input.party, input.wires.len()))
+            .collect::<Vec<_>>();
+        assert_eq!(parties, vec![(1, 16), (2, 8)]);
+        for _ in 0..20 {
+            let (x, y, z) = (rng.gen::<u8>(), rng.gen::<u8>(), rng.gen::<u8>());
+            let merged = [x.to_bits(), z.to_bits()].concat();
+            assert_eq!(
+                loaded.compute(&[merged, y.to_bits()]),
+                circuit.compute(&[x.to_bits(), y.to_bits(), z.to_bits()])
+            );
+        }
+    }
+
+    #[test]
+    fn test_bristol_circuit_with_ot() {
+        let circuit = Circuit::from_bristol_fashion(&adder_8()).unwrap();
+        let garbler_circuit = circuit.clone();
+        let (sender, receiver) = UnixStream::pair().unwrap();
+        let handle = std::thread::spawn(move || {
+            // Garbler
+            let rng = AesRng::new();
+            let reader = BufReader::new(sender.try_clone().unwrap());
+            let writer = BufWriter::new(sender);
+            let channel = TrackChannel::new(reader, writer);
+            let alice = Party { id: 1 };
+            let bob = Party { id: 2 };
+            let mut protocol = Protocol::new(
+                vec![alice.clone(), bob],
+                alice,
+                Role::Garbler,
+                channel,
+                rng,
+                OtKind::ChouOrlandi,
+            )
+            .unwrap();
+            let mut session = GarblingSession::new(&mut protocol.rng);
+
+            // assign!(a <- party 1, value 100);
+            let a =
+                GarblingWire::<FreeXorBit, Wire8Bit>::with_delta(&mut protocol.rng, &session.delta);
+            let ser = bincode::serialize(&a.clone().encode(100)).unwrap();
+            protocol.channel.write_usize(ser.len()).unwrap();
+            protocol.channel.write_bytes(&ser).unwrap();
+            protocol.channel.flush().unwrap();
+
+            // assign!(b <- party 2);
+            let b =
+                GarblingWire::<FreeXorBit, Wire8Bit>::with_delta(&mut protocol.rng, &session.delta);
+            protocol
+                .ot
+                .sender()
+                .send(
+                    &mut protocol.channel,
+                    &b.clone().to_blocks(),
+                    &mut protocol.rng,
+                )
+                .unwrap();
+
+            // c = adder_8(a, b);
+            let (mut outputs, id, tables) = garble_circuit(
+                &garbler_circuit,
+                vec![a.bits, b.bits],
+                &mut session,
+                &mut protocol.rng,
+            );
+            let ser = bincode::serialize(&(id, tables)).unwrap();
+            protocol.channel.write_usize(ser.len()).unwrap();
+            protocol.channel.write_bytes(&ser).unwrap();
+
+            // reveal!(c);
+            let c = GarblingWire::<FreeXorBit, Wire8Bit>::from_bits(outputs.remove(0));
+            let ser = bincode::serialize(&c.decoding_info()).unwrap();
+            protocol.channel.write_usize(ser.len()).unwrap();
+            protocol.channel.write_bytes(&ser).unwrap();
+            protocol.channel.flush().unwrap();
+        });
+
+        // Evaluator
+        let rng = AesRng::new();
+        let reader = BufReader::new(receiver.try_clone().unwrap());
+        let writer = BufWriter::new(receiver);
+        let channel = TrackChannel::new(reader, writer);
+        let alice = Party { id: 1 };
+        let bob = Party { id: 2 };
+        let mut protocol = Protocol::new(
+            vec![alice, bob.clone()],
+            bob,
+            Role::Evaluator,
+            channel,
+            rng,
+            OtKind::ChouOrlandi,
+        )
+        .unwrap();
+
+        // assign!(a <- party 1);
+        let size = protocol.channel.read_usize().unwrap();
+        let ser = protocol.channel.read_vec(size).unwrap();
+        let a: EvaluatingWire<FreeXorBit> = bincode::deserialize(&ser).unwrap();
+
+        // assign!(b <- party 2, value 200);
+        let b = protocol
+            .ot
+            .receiver()
+            .receive(&mut protocol.channel, &200u8.to_bits(), &mut protocol.rng)
+            .unwrap()
+            .into_iter()
+            .map(FreeXorBit)
+            .collect::<Vec<FreeXorBit>>();
+
+        // c = adder_8(a, b);
+        let size = protocol.channel.read_usize().unwrap();
+        let ser = protocol.channel.read_vec(size).unwrap();
+        let (id, tables): (u64, Vec<Vec<Block>>) = bincode::deserialize(&ser).unwrap();
+        let c = evaluate_circuit(&circuit, vec![a.bits, b], id, &tables);
+
+        // reveal!(c);
+        let size = protocol.channel.read_usize().unwrap();
+        let ser = protocol.channel.read_vec(size).unwrap();
+        let decoding: DecodingInfo<Wire8Bit> = bincode::deserialize(&ser).unwrap();
+        let c = EvaluatingWire {
+            bits: c.into_iter().next().unwrap(),
+        };
+        assert_eq!(decoding.decode(&c), 100u8.wrapping_add(200));
+
+        handle.join().unwrap();
+    }
+}
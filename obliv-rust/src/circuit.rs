@@ -0,0 +1,262 @@
+//! Defines the boolean circuit representation that every garbling mode consumes. Operations are
+//! compiled down to a `Circuit` once, and a garbling mode only has to know how to garble and
+//! evaluate the 1-bit gates of the circuit.
+//!
+//! A group of wires that represents a value stores the most significant bit first, the same way
+//! `GarblingWire` and `EvaluatingWire` do.
+
+use serde::{Deserialize, Serialize};
+
+// ----------------------------------------------------------------------------------------------
+// -                                  Type Definitions                                          -
+// ----------------------------------------------------------------------------------------------
+
+/// Identifies a 1-bit wire of a circuit.
+pub type WireId = usize;
+
+/// A 1-bit gate of a circuit. The last `WireId` of every variant is the output wire.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BooleanGate {
+    And(WireId, WireId, WireId),
+    Xor(WireId, WireId, WireId),
+    Inv(WireId, WireId),
+    Const(bool, WireId),
+}
+
+/// A group of input wires that is provided by a single party.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CircuitInput {
+    /// The id of the party that provides the input. Party 0 means the input is an intermediate
+    /// value that is not owned by any party.
+    pub party: usize,
+    pub wires: Vec<WireId>,
+}
+
+/// A boolean circuit. The gates are topologically sorted, i.e., every gate only reads wires that
+/// are inputs of the circuit or outputs of earlier gates.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Circuit {
+    pub wire_count: usize,
+    pub inputs: Vec<CircuitInput>,
+    pub outputs: Vec<Vec<WireId>>,
+    pub gates: Vec<BooleanGate>,
+}
+
+/// Builds a circuit gate by gate. Gates whose inputs are constants are folded away, so gadgets
+/// can freely use constants without paying for them.
+#[derive(Default)]
+pub struct CircuitBuilder {
+    circuit: Circuit,
+    constants: Vec<Option<bool>>,
+    constant_wires: [Option<WireId>; 2],
+}
+
+// ----------------------------------------------------------------------------------------------
+// -                                    Impl blocks                                             -
+// ----------------------------------------------------------------------------------------------
+
+impl BooleanGate {
+    /// Returns the output wire of the gate.
+    pub fn output(&self) -> WireId {
+        match *self {
+            BooleanGate::And(_, _, output)
+            | BooleanGate::Xor(_, _, output)
+            | BooleanGate::Inv(_, output)
+            | BooleanGate::Const(_, output) => output,
+        }
+    }
+
+    /// Returns true if garbling the gate produces a table that is sent to the evaluator.
+    pub fn has_table(&self) -> bool {
+        !matches!(self, BooleanGate::Inv(..))
+    }
+}
+
+impl Circuit {
+    /// Returns the number of garbled tables the circuit needs, i.e., the number of gate indices
+    /// it consumes in a garbling session.
+    pub fn table_count(&self) -> usize {
+        self.gates.iter().filter(|gate| gate.has_table()).count()
+    }
+
+    /// Returns the number of AND gates, which dominates the cost of garbling in Free-XOR modes.
+    pub fn and_count(&self) -> usize {
+        self.gates
+            .iter()
+            .filter(|gate| matches!(gate, BooleanGate::And(..)))
+            .count()
+    }
+
+    /// Evaluates the circuit on cleartext inputs, one `Vec<bool>` per input group.
+    pub fn compute(&self, inputs: &[Vec<bool>]) -> Vec<Vec<bool>> {
+        assert_eq!(inputs.len(), self.inputs.len(), "wrong number of inputs");
+        let mut values = vec![false; self.wire_count];
+        for (input, value) in self.inputs.iter().zip(inputs) {
+            assert_eq!(input.wires.len(), value.len(), "wrong input length");
+            for (wire, bit) in input.wires.iter().zip(value) {
+                values[*wire] = *bit;
+            }
+        }
+        for gate in self.gates.iter() {
+            values[gate.output()] = match *gate {
+                BooleanGate::And(input_1, input_2, _) => values[input_1] & values[input_2],
+                BooleanGate::Xor(input_1, input_2, _) => values[input_1] ^ values[input_2],
+                BooleanGate::Inv(input, _) => !values[input],
+                BooleanGate::Const(value, _) => value,
+            };
+        }
+        self.outputs
+            .iter()
+            .map(|output| output.iter().map(|wire| values[*wire]).collect())
+            .collect()
+    }
+}
+
+impl CircuitBuilder {
+    pub fn new() -> CircuitBuilder {
+        CircuitBuilder::default()
+    }
+
+    /// Adds a group of `bits` input wires provided by `party`.
+    pub fn input(&mut self, party: usize, bits: u32) -> Vec<WireId> {
+        let wires = (0..bits).map(|_| self.new_wire(None)).collect::<Vec<_>>();
+        self.circuit.inputs.push(CircuitInput {
+            party,
+            wires: wires.clone(),
+        });
+        wires
+    }
+
+    /// Marks a group of wires as an output of the circuit.
+    pub fn output(&mut self, wires: &[WireId]) {
+        self.circuit.outputs.push(wires.to_vec());
+    }
+
+    /// Returns a wire that always carries `value`. Each constant is created at most once.
+    pub fn constant(&mut self, value: bool) -> WireId {
+        if let Some(wire) = self.constant_wires[value as usize] {
+            return wire;
+        }
+        let wire = self.new_wire(Some(value));
+        self.circuit.gates.push(BooleanGate::Const(value, wire));
+        self.constant_wires[value as usize] = Some(wire);
+        wire
+    }
+
+    /// Returns the `width` least significant bits of `value` as constant wires, most significant
+    /// bit first.
+    pub fn constant_word(&mut self, value: u128, width: usize) -> Vec<WireId> {
+        (0..width)
+            .rev()
+            .map(|index| self.constant(index < 128 && (value >> index) & 1 == 1))
+            .collect()
+    }
+
+    /// Returns the constant value of a wire, if it is known while building the circuit.
+    pub fn constant_value(&self, wire: WireId) -> Option<bool> {
+        self.constants[wire]
+    }
+
+    pub fn and(&mut self, input_1: WireId, input_2: WireId) -> WireId {
+        match (self.constants[input_1], self.constants[input_2]) {
+            (Some(false), _) => input_1,
+            (_, Some(false)) => input_2,
+            (Some(true), _) => input_2,
+            (_, Some(true)) => input_1,
+            _ if input_1 == input_2 => input_1,
+            _ => {
+                let output = self.new_wire(None);
+                self.circuit
+                    .gates
+                    .push(BooleanGate::And(input_1, input_2, output));
+                output
+            }
+        }
+    }
+
+    pub fn xor(&mut self, input_1: WireId, input_2: WireId) -> WireId {
+        match (self.constants[input_1], self.constants[input_2]) {
+            (Some(value_1), Some(value_2)) => self.constant(value_1 ^ value_2),
+            (Some(false), _) => input_2,
+            (_, Some(false)) => input_1,
+            (Some(true), _) => self.inv(input_2),
+            (_, Some(true)) => self.inv(input_1),
+            _ if input_1 == input_2 => self.constant(false),
+            _ => {
+                let output = self.new_wire(None);
+                self.circuit
+                    .gates
+                    .push(BooleanGate::Xor(input_1, input_2, output));
+                output
+            }
+        }
+    }
+
+    pub fn inv(&mut self, input: WireId) -> WireId {
+        match self.constants[input] {
+            Some(value) => self.constant(!value),
+            None => {
+                let output = self.new_wire(None);
+                self.circuit.gates.push(BooleanGate::Inv(input, output));
+                output
+            }
+        }
+    }
+
+    pub fn or(&mut self, input_1: WireId, input_2: WireId) -> WireId {
+        let both = self.and(input_1, input_2);
+        let either = self.xor(input_1, input_2);
+        self.xor(both, either)
+    }
+
+    /// Returns the finished circuit.
+    pub fn build(self) -> Circuit {
+        self.circuit
+    }
+
+    fn new_wire(&mut self, constant: Option<bool>) -> WireId {
+        self.constants.push(constant);
+        self.circuit.wire_count += 1;
+        self.circuit.wire_count - 1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compute_gates() {
+        let mut builder = CircuitBuilder::new();
+        let a = builder.input(1, 1)[0];
+        let b = builder.input(2, 1)[0];
+        let and = builder.and(a, b);
+        let xor = builder.xor(a, b);
+        let inv = builder.inv(a);
+        let or = builder.or(a, b);
+        builder.output(&[and, xor, inv, or]);
+        let circuit = builder.build();
+
+        for &(x, y) in [(false, false), (false, true), (true, false), (true, true)].iter() {
+            let got = circuit.compute(&[vec![x], vec![y]]);
+            assert_eq!(got, vec![vec![x & y, x ^ y, !x, x | y]]);
+        }
+    }
+
+    #[test]
+    fn test_constant_folding() {
+        let mut builder = CircuitBuilder::new();
+        let a = builder.input(1, 1)[0];
+        let zero = builder.constant(false);
+        let one = builder.constant(true);
+        assert_eq!(builder.and(a, zero), zero);
+        assert_eq!(builder.and(a, one), a);
+        assert_eq!(builder.xor(a, zero), a);
+        let not_a = builder.xor(one, a);
+        builder.output(&[not_a]);
+        let circuit = builder.build();
+
+        assert_eq!(circuit.and_count(), 0);
+        assert_eq!(circuit.compute(&[vec![true]]), vec![vec![false]]);
+    }
+}
@@ -0,0 +1,694 @@
+//! Implements the Circuit ORAM of Wang, Chan, and Shi (CCS 2015) on top of garbled circuits. It
+//! gives secret-indexed access to very large arrays, with a polylogarithmic number of AND gates per
+//! access.
+//!
+//! The elements are stored in a binary tree of buckets. Every element is mapped to a random leaf
+//! and lives in a bucket on the path from the root to its leaf, or in a small stash. An access
+//! looks up the leaf of the element in the position map, reveals it, removes the element from the
+//! path, and puts it back in the stash, mapped to a fresh random leaf. Revealing the leaf is safe
+//! because it was chosen at random and is never revealed again. Every access then evicts elements
+//! from the stash towards the leaves along two public paths, in reverse lexicographic order. The
+//! eviction makes a single pass over the path and moves at most one element per level, so its cost
+//! is linear in the size of the path.
+//!
+//! The position map is itself a Circuit ORAM that packs several leaves in every element, down to a
+//! size at which a linear scan is cheaper. Elements that were never written are not in the tree,
+//! and read as zero.
+
+use crate::{
+    circuit::{Circuit, CircuitBuilder, WireId},
+    executor::{Executor, ProtocolError},
+    mpc_core::WireValue,
+    obliv_array::ObliviousMemory,
+};
+
+/// The number of elements in a bucket of the tree.
+const BUCKET_SIZE: usize = 3;
+
+/// The number of elements in the stash. Wang, Chan, and Shi (Circuit ORAM, CCS 2015) show that,
+/// with buckets of at least two elements and two evictions per access along paths in reverse
+/// lexicographic order, the probability that the stash holds more than `R` elements decreases
+/// exponentially in `R`. With buckets of three elements, 24 slots make an overflow negligible. An
+/// overflow is still detected and returned as `ProtocolError::StashOverflow`, since the element
+/// that does not fit would otherwise be lost.
+const STASH_SIZE: usize = 24;
+
+/// The number of evictions after every access.
+const EVICTIONS: usize = 2;
+
+/// The number of leaves packed in every element of a recursive position map.
+const PACKING: usize = 8;
+const PACKING_BITS: usize = 3;
+
+/// Position maps with at most this many entries are scanned linearly instead of recursing.
+const LINEAR_THRESHOLD: usize = 64;
+
+// ----------------------------------------------------------------------------------------------
+// -                                  Type Definitions                                          -
+// ----------------------------------------------------------------------------------------------
+
+/// A Circuit ORAM on either side of the computation. `K` is the key type of the executor, so both
+/// parties hold the same structure with their own keys.
+pub struct CircuitOram<K> {
+    length: usize,
+    layout: Layout,
+    /// The buckets of the tree in heap order: the root first, and the children of bucket `i` at
+    /// `2i + 1` and `2i + 2`. Every element of a bucket is laid out as described by `Layout`.
+    buckets: Vec<Vec<Vec<K>>>,
+    stash: Vec<Vec<K>>,
+    /// The number of evictions so far, which picks the path of the next eviction.
+    evictions: usize,
+    position_map: PositionMap<K>,
+}
+
+enum PositionMap<K> {
+    /// One entry per element: a bit that is set once the element has a leaf, and the leaf.
+    Linear(Vec<Vec<K>>),
+    /// `PACKING` entries per element.
+    Recursive(Box<CircuitOram<K>>),
+}
+
+/// The layout of an element in the tree: a bit that is set if the slot is used, the logical index,
+/// the leaf, and the value.
+#[derive(Clone, Copy)]
+struct Layout {
+    index_bits: usize,
+    /// The depth of the tree, which is also the width of a leaf.
+    depth: usize,
+    value_bits: usize,
+}
+
+/// Computes the new value and the result of an access from the old value and the arguments of
+/// the access.
+type Update = fn(&mut CircuitBuilder, &[WireId], &[WireId]) -> (Vec<WireId>, Vec<WireId>);
+
+// ----------------------------------------------------------------------------------------------
+// -                                    Impl blocks                                             -
+// ----------------------------------------------------------------------------------------------
+
+impl Layout {
+    fn new(length: usize, value_bits: usize) -> Layout {
+        let bits = bits(length - 1);
+        Layout {
+            index_bits: bits,
+            depth: bits,
+            value_bits,
+        }
+    }
+
+    fn block_bits(&self) -> usize {
+        1 + self.index_bits + self.depth + self.value_bits
+    }
+
+    fn index<'a>(&self, block: &'a [WireId]) -> &'a [WireId] {
+        &block[1..1 + self.index_bits]
+    }
+
+    fn leaf<'a>(&self, block: &'a [WireId]) -> &'a [WireId] {
+        &block[1 + self.index_bits..1 + self.index_bits + self.depth]
+    }
+
+    fn value<'a>(&self, block: &'a [WireId]) -> &'a [WireId] {
+        &block[1 + self.index_bits + self.depth..]
+    }
+
+    /// Returns the buckets on the path from the root to a leaf.
+    fn path(&self, leaf: usize) -> Vec<usize> {
+        (0..=self.depth)
+            .map(|level| (1 << level) - 1 + (leaf >> (self.depth - level)))
+            .collect()
+    }
+}
+
+impl<K: Clone> CircuitOram<K> {
+    /// Creates an ORAM that holds the given elements, which must all have the same width. Every
+    /// element is written with an access, so both parties have to call it in lockstep.
+    pub fn new<E: Executor<Key = K>>(
+        elements: Vec<Vec<K>>,
+        executor: &mut E,
+    ) -> Result<CircuitOram<K>, ProtocolError> {
+        assert!(!elements.is_empty(), "an ORAM needs at least one element");
+        let mut oram = CircuitOram::zeroed(elements.len(), elements[0].len(), executor)?;
+        let indices = executor.execute(&indices_circuit(&oram.layout, oram.length), vec![])?;
+        for (index, element) in indices.iter().zip(elements) {
+            oram.access(index, element, write_update, executor)?;
+        }
+        Ok(oram)
+    }
+
+    /// Creates an ORAM of `length` elements of `value_bits` bits that are all zero. It does not
+    /// cost any access, which makes it the way to create very large ORAMs.
+    pub fn zeroed<E: Executor<Key = K>>(
+        length: usize,
+        value_bits: usize,
+        executor: &mut E,
+    ) -> Result<CircuitOram<K>, ProtocolError> {
+        assert!(length > 0, "an ORAM needs at least one element");
+        let layout = Layout::new(length, value_bits);
+        // Empty slots all share the keys of the same zero block, which is like a constant wire
+        // that fans out.
+        let circuit = zero_circuit(&[layout.block_bits(), 1 + layout.depth]);
+        let mut zero = executor.execute(&circuit, vec![])?;
+        let zero_entry = zero.pop().expect("the circuit has two outputs");
+        let zero_block = zero.pop().expect("the circuit has two outputs");
+
+        let position_map = if length > LINEAR_THRESHOLD {
+            let packed_length = (length - 1) / PACKING + 1;
+            let packed_bits = PACKING * (1 + layout.depth);
+            let oram = CircuitOram::zeroed(packed_length, packed_bits, executor)?;
+            PositionMap::Recursive(Box::new(oram))
+        } else {
+            PositionMap::Linear(vec![zero_entry; length])
+        };
+        Ok(CircuitOram {
+            length,
+            layout,
+            buckets: vec![vec![zero_block.clone(); BUCKET_SIZE]; (2 << layout.depth) - 1],
+            stash: vec![zero_block; STASH_SIZE],
+            evictions: 0,
+            position_map,
+        })
+    }
+
+    /// Reads an element, replaces its value with the new value computed by `update`, and returns
+    /// the result computed by `update`.
+    fn access<E: Executor<Key = K>>(
+        &mut self,
+        index: &[K],
+        arguments: Vec<K>,
+        update: Update,
+        executor: &mut E,
+    ) -> Result<Vec<K>, ProtocolError> {
+        let layout = self.layout;
+        let circuit = prepare_circuit(self.length, index.len(), layout.index_bits);
+        let mut outputs = executor.execute(&circuit, vec![index.to_vec()])?;
+        let logical_index = outputs.pop().expect("the circuit has two outputs");
+        let in_bounds = outputs.pop().expect("the circuit has two outputs");
+
+        let mut new_leaf = executor.random(2 * layout.depth)?;
+        let fallback = new_leaf.split_off(layout.depth);
+        let entry = self.swap_position(&logical_index, &new_leaf, executor)?;
+        let leaf = executor.execute(&leaf_circuit(layout.depth), vec![entry, fallback])?;
+        let leaf = u128::from_bits(&executor.reveal(&leaf[0])?) as usize;
+
+        let circuit = access_circuit(&layout, arguments.len(), update);
+        let mut inputs = vec![in_bounds, logical_index, new_leaf, arguments];
+        inputs.append(&mut self.take_path(leaf));
+        let mut outputs = executor.execute(&circuit, inputs)?;
+        let result = outputs.remove(0);
+        let mut overflow = outputs.remove(0);
+        self.put_path(leaf, outputs);
+
+        for _ in 0..EVICTIONS {
+            let leaf = reverse_bits(self.evictions, layout.depth);
+            self.evictions += 1;
+            let mut inputs = vec![overflow];
+            inputs.append(&mut self.take_path(leaf));
+            let mut outputs = executor.execute(&evict_circuit(&layout, leaf), inputs)?;
+            overflow = outputs.remove(0);
+            self.put_path(leaf, outputs);
+        }
+        // Revealing the bit only tells if the stash overflowed, which does not depend on the
+        // accessed index.
+        if executor.reveal(&overflow)?[0] {
+            return Err(ProtocolError::StashOverflow);
+        }
+        Ok(result)
+    }
+
+    /// Maps a logical index to a new leaf and returns its old entry in the position map.
+    fn swap_position<E: Executor<Key = K>>(
+        &mut self,
+        logical_index: &[K],
+        new_leaf: &[K],
+        executor: &mut E,
+    ) -> Result<Vec<K>, ProtocolError> {
+        match &mut self.position_map {
+            PositionMap::Linear(entries) => {
+                let circuit = linear_swap_circuit(entries.len(), &self.layout);
+                let mut inputs = vec![logical_index.to_vec(), new_leaf.to_vec()];
+                inputs.append(entries);
+                let mut outputs = executor.execute(&circuit, inputs)?;
+                *entries = outputs.split_off(1);
+                Ok(outputs.remove(0))
+            }
+            PositionMap::Recursive(oram) => {
+                let (block, slot) = logical_index.split_at(self.layout.index_bits - PACKING_BITS);
+                let arguments = [slot, new_leaf].concat();
+                oram.access(block, arguments, swap_slot, executor)
+            }
+        }
+    }
+
+    /// Removes the stash and the buckets on the path to a leaf, in that order.
+    fn take_path(&mut self, leaf: usize) -> Vec<Vec<K>> {
+        let mut blocks = self.stash.split_off(0);
+        for bucket in self.layout.path(leaf) {
+            blocks.append(&mut self.buckets[bucket]);
+        }
+        blocks
+    }
+
+    /// Puts back the blocks that `take_path` removed.
+    fn put_path(&mut self, leaf: usize, mut blocks: Vec<Vec<K>>) {
+        let mut rest = blocks.split_off(STASH_SIZE);
+        self.stash = blocks;
+        for bucket in self.layout.path(leaf) {
+            let remaining = rest.split_off(BUCKET_SIZE);
+            self.buckets[bucket] = rest;
+            rest = remaining;
+        }
+    }
+}
+
+impl<E: Executor> ObliviousMemory<E> for CircuitOram<E::Key> {
+    fn len(&self) -> usize {
+        self.length
+    }
+
+    fn read(&mut self, index: &[E::Key], executor: &mut E) -> Result<Vec<E::Key>, ProtocolError> {
+        self.access(index, vec![], read_update, executor)
+    }
+
+    fn write(
+        &mut self,
+        index: &[E::Key],
+        value: &[E::Key],
+        executor: &mut E,
+    ) -> Result<(), ProtocolError> {
+        assert_eq!(
+            value.len(),
+            self.layout.value_bits,
+            "the value has the wrong width"
+        );
+        self.access(index, value.to_vec(), write_update, executor)?;
+        Ok(())
+    }
+}
+
+// ----------------------------------------------------------------------------------------------
+// -                                 Utility Functions                                          -
+// ----------------------------------------------------------------------------------------------
+
+/// Returns the number of AND gates of a read from an ORAM of `length` elements of `value_bits`
+/// bits, including the position maps and the evictions. It does not depend on the values or the
+/// index, so it is the cost of every access.
+pub fn access_and_count(length: usize, value_bits: usize) -> usize {
+    and_count(length, value_bits, 0, read_update)
+}
+
+fn and_count(length: usize, value_bits: usize, argument_bits: usize, update: Update) -> usize {
+    let layout = Layout::new(length, value_bits);
+    let position_map = if length > LINEAR_THRESHOLD {
+        let packed_length = (length - 1) / PACKING + 1;
+        let packed_bits = PACKING * (1 + layout.depth);
+        and_count(
+            packed_length,
+            packed_bits,
+            PACKING_BITS + layout.depth,
+            swap_slot,
+        )
+    } else {
+        linear_swap_circuit(length, &layout).and_count()
+    };
+    let circuits = [
+        prepare_circuit(length, layout.index_bits, layout.index_bits),
+        leaf_circuit(layout.depth),
+        access_circuit(&layout, argument_bits, update),
+    ];
+    let evictions = EVICTIONS * evict_circuit(&layout, 0).and_count();
+    circuits.iter().map(Circuit::and_count).sum::<usize>() + position_map + evictions
+}
+
+/// Returns the number of bits needed to write `value`, at least one.
+fn bits(value: usize) -> usize {
+    ((usize::BITS - value.leading_zeros()) as usize).max(1)
+}
+
+/// Returns the `width` least significant bits of `value` in reverse order. Evicting along the
+/// paths to the leaves in this order spreads the evictions evenly over the tree.
+fn reverse_bits(value: usize, width: usize) -> usize {
+    (0..width).fold(0, |reversed, bit| (reversed << 1) | ((value >> bit) & 1))
+}
+
+fn read_update(_: &mut CircuitBuilder, old: &[WireId], _: &[WireId]) -> (Vec<WireId>, Vec<WireId>) {
+    (old.to_vec(), old.to_vec())
+}
+
+fn write_update(
+    _: &mut CircuitBuilder,
+    old: &[WireId],
+    value: &[WireId],
+) -> (Vec<WireId>, Vec<WireId>) {
+    (value.to_vec(), old.to_vec())
+}
+
+/// Replaces an entry of a packed element of a recursive position map and returns the old entry.
+/// The arguments are the slot of the entry and the new leaf.
+fn swap_slot(
+    builder: &mut CircuitBuilder,
+    old: &[WireId],
+    arguments: &[WireId],
+) -> (Vec<WireId>, Vec<WireId>) {
+    let (slot, new_leaf) = arguments.split_at(PACKING_BITS);
+    let one = builder.constant(true);
+    let new_entry = [&[one], new_leaf].concat();
+    let entries = old
+        .chunks(new_entry.len())
+        .map(|entry| entry.to_vec())
+        .collect::<Vec<Vec<WireId>>>();
+    let old_entry = builder.array_read(&entries, slot);
+    let new = builder.array_write(&entries, slot, &new_entry).concat();
+    (new, old_entry)
+}
+
+/// Outputs zero values of the given widths.
+fn zero_circuit(widths: &[usize]) -> Circuit {
+    let mut builder = CircuitBuilder::new();
+    for width in widths {
+        let zero = builder.constant_word(0, *width);
+        builder.output(&zero);
+    }
+    builder.build()
+}
+
+/// Outputs the indices of all elements.
+fn indices_circuit(layout: &Layout, length: usize) -> Circuit {
+    let mut builder = CircuitBuilder::new();
+    for index in 0..length {
+        let index = builder.constant_word(index as u128, layout.index_bits);
+        builder.output(&index);
+    }
+    builder.build()
+}
+
+/// Checks the bounds of an index of any width. The input group is the index. The output groups
+/// are a bit that is set if the index is in bounds, and the logical index, which is zero if the
+/// index is out of bounds.
+fn prepare_circuit(length: usize, width: usize, index_bits: usize) -> Circuit {
+    let mut builder = CircuitBuilder::new();
+    let index = builder.input(0, width as u32);
+    let compare_width = width.max(bits(length));
+    let extended = builder.extend(&index, compare_width, false);
+    let bound = builder.constant_word(length as u128, compare_width);
+    let in_bounds = builder.less_than(&extended, &bound, false);
+    let logical_index = builder.bitwise_and(
+        &extended[compare_width - index_bits..],
+        &vec![in_bounds; index_bits],
+    );
+    builder.output(&[in_bounds]);
+    builder.output(&logical_index);
+    builder.build()
+}
+
+/// Replaces the entry of a logical index in a linear position map. The input groups are the
+/// logical index, the new leaf, and the entries. The output groups are the old entry and the
+/// entries.
+fn linear_swap_circuit(length: usize, layout: &Layout) -> Circuit {
+    let mut builder = CircuitBuilder::new();
+    let logical_index = builder.input(0, layout.index_bits as u32);
+    let new_leaf = builder.input(0, layout.depth as u32);
+    let entries = (0..length)
+        .map(|_| builder.input(0, 1 + layout.depth as u32))
+        .collect::<Vec<Vec<WireId>>>();
+    let one = builder.constant(true);
+    let new_entry = [&[one], new_leaf.as_slice()].concat();
+    let old_entry = builder.array_read(&entries, &logical_index);
+    builder.output(&old_entry);
+    for entry in builder.array_write(&entries, &logical_index, &new_entry) {
+        builder.output(&entry);
+    }
+    builder.build()
+}
+
+/// Picks the leaf of an element: the leaf of its entry in the position map, or a fresh random
+/// leaf if the element was never accessed. The input groups are the entry and the random leaf.
+fn leaf_circuit(depth: usize) -> Circuit {
+    let mut builder = CircuitBuilder::new();
+    let entry = builder.input(0, 1 + depth as u32);
+    let fallback = builder.input(0, depth as u32);
+    let leaf = builder.mux(entry[0], &entry[1..], &fallback);
+    builder.output(&leaf);
+    builder.build()
+}
+
+/// Removes an element from the stash and a path, updates it, and adds it back to the stash with
+/// its new leaf. The input groups are the bit that is set if the index is in bounds, the logical
+/// index, the new leaf, the arguments of the update, and the elements of the stash and the path.
+/// The output groups are the result of the update, a bit that is set if the stash overflowed, and
+/// the elements of the stash and the path.
+fn access_circuit(layout: &Layout, argument_bits: usize, update: Update) -> Circuit {
+    let mut builder = CircuitBuilder::new();
+    let in_bounds = builder.input(0, 1)[0];
+    let logical_index = builder.input(0, layout.index_bits as u32);
+    let new_leaf = builder.input(0, layout.depth as u32);
+    let arguments = builder.input(0, argument_bits as u32);
+    let mut blocks = (0..STASH_SIZE + (layout.depth + 1) * BUCKET_SIZE)
+        .map(|_| builder.input(0, layout.block_bits() as u32))
+        .collect::<Vec<Vec<WireId>>>();
+
+    let mut old = builder.constant_word(0, layout.value_bits);
+    for block in blocks.iter_mut() {
+        let equal = builder.equal(layout.index(block), &logical_index);
+        let hit = builder.and(block[0], equal);
+        // Every logical index is at most once in the stash and the path.
+        let masked = builder.bitwise_and(layout.value(block), &vec![hit; layout.value_bits]);
+        old = builder.bitwise_xor(&old, &masked);
+        let missing = builder.inv(equal);
+        block[0] = builder.and(block[0], missing);
+    }
+
+    let (new, result) = update(&mut builder, &old, &arguments);
+    let new = builder.mux(in_bounds, &new, &old);
+    let result = builder.bitwise_and(&result, &vec![in_bounds; result.len()]);
+    let one = builder.constant(true);
+    let new_block = [&[one], logical_index.as_slice(), &new_leaf, &new].concat();
+    let overflow = insert(&mut builder, &mut blocks[..STASH_SIZE], &new_block);
+
+    builder.output(&result);
+    builder.output(&[overflow]);
+    for block in blocks.iter() {
+        builder.output(block);
+    }
+    builder.build()
+}
+
+/// Evicts elements along the path to a public leaf with a single pass over the path. The input
+/// groups are a bit that is set if the stash overflowed, and the elements of the stash and the
+/// path. The output groups are the same bit, also set if an element did not fit in its bucket,
+/// and the elements after the eviction.
+///
+/// The positions on the path are the stash and then the buckets from the root to the leaf. The
+/// depth that an element can reach on the path is a thermometer code over the positions: the
+/// `i`-th wire is set if the element can be in the `i`-th position. It makes comparing depths and
+/// taking their maximum cheap. Every other position on the path is one-hot encoded, and none is
+/// encoded as all zeros.
+fn evict_circuit(layout: &Layout, leaf: usize) -> Circuit {
+    let mut builder = CircuitBuilder::new();
+    let positions = layout.depth + 2;
+    let mut overflow = builder.input(0, 1)[0];
+    let mut path = vec![(0..STASH_SIZE)
+        .map(|_| builder.input(0, layout.block_bits() as u32))
+        .collect::<Vec<Vec<WireId>>>()];
+    for _ in 0..=layout.depth {
+        let bucket = (0..BUCKET_SIZE)
+            .map(|_| builder.input(0, layout.block_bits() as u32))
+            .collect::<Vec<Vec<WireId>>>();
+        path.push(bucket);
+    }
+
+    // Finds the deepest element of every position and how deep it can go.
+    let mut reach = vec![];
+    let mut deepest = vec![];
+    for bucket in path.iter() {
+        let mut best = builder.constant_word(0, positions);
+        let mut selected: Vec<WireId> = vec![];
+        for block in bucket.iter() {
+            let mut depth = vec![block[0]; 2];
+            let mut prefix = block[0];
+            for (level, bit) in layout.leaf(block).iter().enumerate() {
+                let on_path = if (leaf >> (layout.depth - 1 - level)) & 1 == 1 {
+                    *bit
+                } else {
+                    builder.inv(*bit)
+                };
+                prefix = builder.and(prefix, on_path);
+                depth.push(prefix);
+            }
+            let deeper = greater(&mut builder, &depth, &best);
+            let not_deeper = builder.inv(deeper);
+            for select in selected.iter_mut() {
+                *select = builder.and(*select, not_deeper);
+            }
+            selected.push(deeper);
+            best = builder.bitwise_or(&best, &depth);
+        }
+        reach.push(best);
+        deepest.push(selected);
+    }
+
+    // PrepareDeepest: the position of the deepest element above every position that can go to
+    // that position.
+    let zero = builder.constant(false);
+    let mut source = vec![zero; positions];
+    source[0] = reach[0][0];
+    let mut goal = reach[0].clone();
+    let mut sources = vec![vec![zero; positions]];
+    for position in 1..positions {
+        let masked = builder.bitwise_and(&source, &vec![goal[position]; positions]);
+        sources.push(masked);
+        let deeper = greater(&mut builder, &reach[position], &goal);
+        let not_deeper = builder.inv(deeper);
+        source = builder.bitwise_and(&source, &vec![not_deeper; positions]);
+        source[position] = deeper;
+        goal = builder.bitwise_or(&goal, &reach[position]);
+    }
+
+    // PrepareTarget: the position that the deepest element of every position moves to, from the
+    // leaf to the stash.
+    let mut destination = vec![zero; positions];
+    let mut source = vec![zero; positions];
+    let mut targets = vec![vec![]; positions];
+    for position in (0..positions).rev() {
+        let arrived = source[position];
+        let not_arrived = builder.inv(arrived);
+        targets[position] = builder.bitwise_and(&destination, &vec![arrived; positions]);
+        destination = builder.bitwise_and(&destination, &vec![not_arrived; positions]);
+        source = builder.bitwise_and(&source, &vec![not_arrived; positions]);
+
+        let empty = path[position]
+            .iter()
+            .map(|block| builder.inv(block[0]))
+            .collect::<Vec<WireId>>();
+        let has_empty = builder.any(&empty);
+        let has_destination = builder.any(&destination);
+        let no_destination = builder.inv(has_destination);
+        let free = builder.and(no_destination, has_empty);
+        let has_target = builder.any(&targets[position]);
+        let has_source = builder.any(&sources[position]);
+        let free_or_target = builder.or(free, has_target);
+        let start = builder.and(free_or_target, has_source);
+        source = builder.mux(start, &sources[position], &source);
+        let here = builder.constant_word(1 << (positions - 1 - position), positions);
+        destination = builder.mux(start, &here, &destination);
+    }
+
+    // EvictOnceFast: carries the element to move down the path and drops it at its target.
+    let mut hold = builder.constant_word(0, layout.block_bits());
+    let mut destination = vec![zero; positions];
+    for position in 0..positions {
+        let drop = destination[position];
+        let not_drop = builder.inv(drop);
+        let mut dropped = hold.clone();
+        dropped[0] = builder.and(hold[0], drop);
+        hold[0] = builder.and(hold[0], not_drop);
+        destination = builder.bitwise_and(&destination, &vec![not_drop; positions]);
+
+        let take = builder.any(&targets[position]);
+        let mut taken = builder.constant_word(0, layout.block_bits());
+        for (block, select) in path[position].iter_mut().zip(&deepest[position]) {
+            let moved = builder.and(take, *select);
+            let masked = builder.bitwise_and(block, &vec![moved; layout.block_bits()]);
+            taken = builder.bitwise_xor(&taken, &masked);
+            let stays = builder.inv(moved);
+            block[0] = builder.and(block[0], stays);
+        }
+        hold = builder.mux(take, &taken, &hold);
+        destination = builder.mux(take, &targets[position], &destination);
+
+        // Nothing is ever dropped in the stash.
+        if position > 0 {
+            let lost = insert(&mut builder, &mut path[position], &dropped);
+            overflow = builder.or(overflow, lost);
+        }
+    }
+
+    builder.output(&[overflow]);
+    for bucket in path.iter() {
+        for block in bucket.iter() {
+            builder.output(block);
+        }
+    }
+    builder.build()
+}
+
+/// Returns a wire that is set if the first depth is greater than the second, both encoded as
+/// thermometer codes.
+fn greater(builder: &mut CircuitBuilder, depth_1: &[WireId], depth_2: &[WireId]) -> WireId {
+    let deeper = depth_1
+        .iter()
+        .zip(depth_2)
+        .map(|(bit_1, bit_2)| {
+            let not_bit_2 = builder.inv(*bit_2);
+            builder.and(*bit_1, not_bit_2)
+        })
+        .collect::<Vec<WireId>>();
+    builder.any(&deeper)
+}
+
+/// Puts an element in the first empty slot, if its first bit is set. Returns a wire that is set if
+/// the element is set but every slot is full.
+fn insert(builder: &mut CircuitBuilder, slots: &mut [Vec<WireId>], block: &[WireId]) -> WireId {
+    let mut pending = block[0];
+    for slot in slots.iter_mut() {
+        let empty = builder.inv(slot[0]);
+        let put = builder.and(pending, empty);
+        *slot = builder.mux(put, block, slot);
+        let not_put = builder.inv(put);
+        pending = builder.and(pending, not_put);
+    }
+    pending
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::obliv_array::testing::check_memory;
+
+    #[test]
+    fn test_circuit_oram() {
+        // The position map recurses once.
+        check_memory(
+            70,
+            40,
+            |elements, garbler| CircuitOram::new(elements, garbler).unwrap(),
+            |elements, evaluator| CircuitOram::new(elements, evaluator).unwrap(),
+        );
+    }
+
+    #[test]
+    fn test_full_stash_overflows() {
+        let layout = Layout::new(4, 8);
+        let circuit = access_circuit(&layout, 0, read_update);
+        let path_blocks = (layout.depth + 1) * BUCKET_SIZE;
+        for &free_slots in [0, 1].iter() {
+            // The stash holds element 0, and the accessed element 3 is not in the stash or on the
+            // path, so it has to be added to the stash.
+            let empty = vec![false; layout.block_bits()];
+            let mut used = empty.clone();
+            used[0] = true;
+            let mut inputs = vec![
+                vec![true],
+                vec![true; layout.index_bits],
+                vec![false; layout.depth],
+                vec![],
+            ];
+            inputs.extend(vec![empty.clone(); free_slots]);
+            inputs.extend(vec![used; STASH_SIZE - free_slots]);
+            inputs.extend(vec![empty; path_blocks]);
+            let outputs = circuit.compute(&inputs);
+            assert_eq!(outputs[1], vec![free_slots == 0]);
+        }
+    }
+
+    #[test]
+    fn test_access_cost_is_polylogarithmic() {
+        let costs = (10..=20)
+            .step_by(2)
+            .map(|log_length| access_and_count(1 << log_length, 32))
+            .collect::<Vec<usize>>();
+        for pair in costs.windows(2) {
+            assert!(pair[0] < pair[1] && pair[1] < 2 * pair[0], "{:?}", costs);
+        }
+    }
+}
@@ -0,0 +1,150 @@
+//! Implements the comparison gadgets on top of `CircuitBuilder`. Every gadget takes groups of
+//! wires with the most significant bit first and returns a single wire.
+
+use crate::circuit::{CircuitBuilder, WireId};
+
+impl CircuitBuilder {
+    /// Returns a wire that is set if the first value is less than the second one. Signed values
+    /// are compared in two's complement. It costs one AND gate per bit.
+    pub fn less_than(&mut self, input_1: &[WireId], input_2: &[WireId], signed: bool) -> WireId {
+        assert_eq!(
+            input_1.len(),
+            input_2.len(),
+            "operands must have the same width"
+        );
+        // `input_1 < input_2` if and only if `input_1 - input_2` borrows, i.e., if computing
+        // `input_1 + !input_2 + 1` does not carry out of the most significant bit.
+        let mut carry = self.constant(true);
+        for (index, (bit_1, bit_2)) in input_1.iter().zip(input_2).enumerate().rev() {
+            let mut bit_1 = *bit_1;
+            let mut bit_2 = self.inv(*bit_2);
+            if signed && index == 0 {
+                // Flipping the sign bits maps two's complement to the unsigned order.
+                bit_1 = self.inv(bit_1);
+                bit_2 = self.inv(bit_2);
+            }
+            let bit_1_carry = self.xor(bit_1, carry);
+            let bit_2_carry = self.xor(bit_2, carry);
+            let both = self.and(bit_1_carry, bit_2_carry);
+            carry = self.xor(both, carry);
+        }
+        self.inv(carry)
+    }
+
+    /// Returns a wire that is set if the first value is less than or equal to the second one.
+    pub fn less_equal(&mut self, input_1: &[WireId], input_2: &[WireId], signed: bool) -> WireId {
+        let greater = self.less_than(input_2, input_1, signed);
+        self.inv(greater)
+    }
+
+    /// Returns a wire that is set if the first value is greater than the second one.
+    pub fn greater_than(&mut self, input_1: &[WireId], input_2: &[WireId], signed: bool) -> WireId {
+        self.less_than(input_2, input_1, signed)
+    }
+
+    /// Returns a wire that is set if the first value is greater than or equal to the second one.
+    pub fn greater_equal(
+        &mut self,
+        input_1: &[WireId],
+        input_2: &[WireId],
+        signed: bool,
+    ) -> WireId {
+        let less = self.less_than(input_1, input_2, signed);
+        self.inv(less)
+    }
+
+    /// Returns a wire that is set if the two values are equal. It costs one AND gate per bit but
+    /// one.
+    pub fn equal(&mut self, input_1: &[WireId], input_2: &[WireId]) -> WireId {
+        let different = self.not_equal(input_1, input_2);
+        self.inv(different)
+    }
+
+    /// Returns a wire that is set if the two values are different.
+    pub fn not_equal(&mut self, input_1: &[WireId], input_2: &[WireId]) -> WireId {
+        assert_eq!(
+            input_1.len(),
+            input_2.len(),
+            "operands must have the same width"
+        );
+        let differences = input_1
+            .iter()
+            .zip(input_2)
+            .map(|(bit_1, bit_2)| self.xor(*bit_1, *bit_2))
+            .collect::<Vec<WireId>>();
+        self.any(&differences)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{circuit::CircuitBuilder, mpc_core::WireValue};
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+    const SEED: [u8; 32] = [42u8; 32];
+
+    #[test]
+    fn test_comparisons() {
+        let mut rng = StdRng::from_seed(SEED);
+        let mut builder = CircuitBuilder::new();
+        let a = builder.input(1, 32);
+        let b = builder.input(2, 32);
+        let mut outputs = Vec::new();
+        for signed in &[false, true] {
+            outputs.push(builder.less_than(&a, &b, *signed));
+            outputs.push(builder.less_equal(&a, &b, *signed));
+            outputs.push(builder.greater_than(&a, &b, *signed));
+            outputs.push(builder.greater_equal(&a, &b, *signed));
+        }
+        outputs.push(builder.equal(&a, &b));
+        outputs.push(builder.not_equal(&a, &b));
+        builder.output(&outputs);
+        let circuit = builder.build();
+
+        for _ in 0..100 {
+            let x: u32 = rng.gen();
+            let y: u32 = if rng.gen() { rng.gen() } else { x };
+            let got = circuit.compute(&[x.to_bits(), y.to_bits()]);
+            let (i, j) = (x as i32, y as i32);
+            let want = vec![
+                x < y,
+                x <= y,
+                x > y,
+                x >= y,
+                i < j,
+                i <= j,
+                i > j,
+                i >= j,
+                x == y,
+                x != y,
+            ];
+            assert_eq!(got[0], want, "{} {}", x, y);
+        }
+    }
+
+    #[test]
+    fn test_less_than() {
+        let mut rng = StdRng::from_seed(SEED);
+        let mut builder = CircuitBuilder::new();
+        let a = builder.input(1, 8);
+        let b = builder.input(2, 8);
+        let unsigned = builder.less_than(&a, &b, false);
+        let signed = builder.less_than(&a, &b, true);
+        builder.output(&[unsigned, signed]);
+        let circuit = builder.build();
+        assert_eq!(circuit.and_count(), 2 * 8);
+
+        for _ in 0..100 {
+            let x: u8 = rng.gen();
+            let y: u8 = if rng.gen() { rng.gen() } else { x };
+            let got = circuit.compute(&[x.to_bits(), y.to_bits()]);
+            assert_eq!(got[0][0], x < y, "{} < {}", x, y);
+            assert_eq!(
+                got[0][1],
+                (x as i8) < (y as i8),
+                "{} < {}",
+                x as i8,
+                y as i8
+            );
+        }
+    }
+}
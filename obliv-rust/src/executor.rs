@@ -0,0 +1,484 @@
+//! Runs garbled circuits interactively. The garbler garbles every circuit and sends the garbled
+//! tables over the channel, and the evaluator receives and evaluates them. Gadgets that need more
+//! than one circuit, e.g., ORAMs that reveal where they read, are written once against the
+//! `Executor` trait and run unchanged on both sides.
+
+use crate::{
+    circuit::{Circuit, CircuitBuilder},
+    mpc_core::{
+        evaluate_circuit, garble_circuit, GarbledTables, GarblingSession, GateGarbling, RevealTo,
+    },
+    permutation::{random_waksman_switches, waksman_switch_count},
+};
+use ocelot::ot::{Receiver as OtReceiver, Sender as OtSender};
+use rand_core::{CryptoRng, RngCore};
+use scuttlebutt::{AbstractChannel, Block};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{fmt, io, marker::PhantomData};
+
+// ----------------------------------------------------------------------------------------------
+// -                                  Type Definitions                                          -
+// ----------------------------------------------------------------------------------------------
+
+/// The errors that can happen while the parties talk to each other.
+#[derive(Debug)]
+pub enum ProtocolError {
+    Io(io::Error),
+    Ot(ocelot::Error),
+    /// A message could not be serialized or deserialized.
+    Serialization(bincode::Error),
+    /// The other party sent a key that is not one of the keys of the revealed wire.
+    InvalidKey,
+    /// A value was revealed to, or input from, a party that is not part of the protocol.
+    UnknownParty(usize),
+    /// The other party sent a different number of keys or garbled tables than expected.
+    WrongCount {
+        expected: usize,
+        received: usize,
+    },
+    /// The stash of an ORAM was full, so an element could not be stored.
+    StashOverflow,
+}
+
+/// Runs the garbler's or the evaluator's side of a computation.
+pub trait Executor {
+    /// The key of a 1-bit wire: the pair of keys for the garbler and the active key for the
+    /// evaluator.
+    type Key: Clone;
+
+    /// Garbles or evaluates a circuit on the keys of its input groups and returns the keys of its
+    /// output groups.
+    fn execute(
+        &mut self,
+        circuit: &Circuit,
+        inputs: Vec<Vec<Self::Key>>,
+    ) -> Result<Vec<Vec<Self::Key>>, ProtocolError>;
+
+    /// Reveals a value to both parties.
+    fn reveal(&mut self, value: &[Self::Key]) -> Result<Vec<bool>, ProtocolError> {
+        let bits = self.reveal_to(value, RevealTo::Both)?;
+        Ok(bits.expect("both parties learn the value"))
+    }
+
+    /// Reveals a value to the chosen parties and returns it if this party learns it. The garbler
+    /// sends the decoding bits if the evaluator learns the value. The evaluator sends its active
+    /// keys back if the garbler learns it, and the garbler checks that they belong to the value.
+    fn reveal_to(
+        &mut self,
+        value: &[Self::Key],
+        to: RevealTo,
+    ) -> Result<Option<Vec<bool>>, ProtocolError>;
+
+    /// Inputs a private value of this party. The garbler encodes it and sends the active keys,
+    /// and the evaluator gets its keys with OT. The other party calls `peer_input` at the same
+    /// time.
+    fn input(&mut self, value: &[bool]) -> Result<Vec<Self::Key>, ProtocolError>;
+
+    /// Inputs a private value of `bits` bits of the other party, which calls `input` at the same
+    /// time.
+    fn peer_input(&mut self, bits: usize) -> Result<Vec<Self::Key>, ProtocolError>;
+
+    /// Returns a secret random value of `bits` bits that neither party knows. It is the XOR of a
+    /// random value of each party; the evaluator's value is input via OT.
+    fn random(&mut self, bits: usize) -> Result<Vec<Self::Key>, ProtocolError>;
+
+    /// Inputs the switches of a Waksman network of `count` items for a random permutation of each
+    /// party, the garbler's switches first. Applying both networks in turn shuffles the items with
+    /// a permutation that neither party knows.
+    fn random_switches(&mut self, count: usize) -> Result<Vec<Vec<Self::Key>>, ProtocolError>;
+}
+
+/// The garbler's side of a computation.
+pub struct Garbler<'a, M, C, OT, R> {
+    pub channel: &'a mut C,
+    pub ot: &'a mut OT,
+    pub session: &'a mut GarblingSession,
+    pub rng: &'a mut R,
+    mode: PhantomData<M>,
+}
+
+/// The evaluator's side of a computation.
+pub struct Evaluator<'a, M, C, OT, R> {
+    pub channel: &'a mut C,
+    pub ot: &'a mut OT,
+    pub rng: &'a mut R,
+    mode: PhantomData<M>,
+}
+
+// ----------------------------------------------------------------------------------------------
+// -                                    Impl blocks                                             -
+// ----------------------------------------------------------------------------------------------
+
+impl fmt::Display for ProtocolError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ProtocolError::Io(error) => write!(f, "channel error: {}", error),
+            ProtocolError::Ot(error) => write!(f, "OT error: {}", error),
+            ProtocolError::Serialization(error) => write!(f, "malformed message: {}", error),
+            ProtocolError::InvalidKey => {
+                write!(f, "received a key that does not belong to the wire")
+            }
+            ProtocolError::UnknownParty(id) => write!(f, "unknown party {}", id),
+            ProtocolError::WrongCount { expected, received } => {
+                write!(f, "expected {} items, received {}", expected, received)
+            }
+            ProtocolError::StashOverflow => write!(f, "the stash of the ORAM overflowed"),
+        }
+    }
+}
+
+impl std::error::Error for ProtocolError {}
+
+impl From<io::Error> for ProtocolError {
+    fn from(error: io::Error) -> Self {
+        ProtocolError::Io(error)
+    }
+}
+
+impl From<ocelot::Error> for ProtocolError {
+    fn from(error: ocelot::Error) -> Self {
+        ProtocolError::Ot(error)
+    }
+}
+
+impl From<bincode::Error> for ProtocolError {
+    fn from(error: bincode::Error) -> Self {
+        ProtocolError::Serialization(error)
+    }
+}
+
+impl<'a, M, C, OT, R> Garbler<'a, M, C, OT, R> {
+    pub fn new(
+        channel: &'a mut C,
+        ot: &'a mut OT,
+        session: &'a mut GarblingSession,
+        rng: &'a mut R,
+    ) -> Self {
+        Garbler {
+            channel,
+            ot,
+            session,
+            rng,
+            mode: PhantomData,
+        }
+    }
+}
+
+impl<'a, M, C, OT, R> Evaluator<'a, M, C, OT, R> {
+    pub fn new(channel: &'a mut C, ot: &'a mut OT, rng: &'a mut R) -> Self {
+        Evaluator {
+            channel,
+            ot,
+            rng,
+            mode: PhantomData,
+        }
+    }
+}
+
+impl<'a, M, C, OT, R> Executor for Garbler<'a, M, C, OT, R>
+where
+    M: GateGarbling,
+    C: AbstractChannel,
+    OT: OtSender<Msg = Block>,
+    R: RngCore + CryptoRng,
+{
+    type Key = (M, M);
+
+    fn execute(
+        &mut self,
+        circuit: &Circuit,
+        inputs: Vec<Vec<(M, M)>>,
+    ) -> Result<Vec<Vec<(M, M)>>, ProtocolError> {
+        let (outputs, id, tables) = garble_circuit(circuit, inputs, self.session, self.rng);
+        send(self.channel, &(id, tables))?;
+        Ok(outputs)
+    }
+
+    fn reveal_to(
+        &mut self,
+        value: &[(M, M)],
+        to: RevealTo,
+    ) -> Result<Option<Vec<bool>>, ProtocolError> {
+        if to != RevealTo::Garbler {
+            let decoding = value.iter().map(M::decoding_bit).collect::<Vec<bool>>();
+            send(self.channel, &decoding)?;
+        }
+        if to == RevealTo::Evaluator {
+            return Ok(None);
+        }
+        let active: Vec<Block> = receive(self.channel)?;
+        if active.len() != value.len() {
+            return Err(ProtocolError::InvalidKey);
+        }
+        let bits = value
+            .iter()
+            .zip(active)
+            .map(|((zero, one), key)| {
+                if key == zero.to_block() {
+                    Ok(false)
+                } else if key == one.to_block() {
+                    Ok(true)
+                } else {
+                    Err(ProtocolError::InvalidKey)
+                }
+            })
+            .collect::<Result<Vec<bool>, ProtocolError>>()?;
+        Ok(Some(bits))
+    }
+
+    fn input(&mut self, value: &[bool]) -> Result<Vec<(M, M)>, ProtocolError> {
+        let pairs = (0..value.len())
+            .map(|_| M::pair_with_delta(self.rng, &self.session.delta))
+            .collect::<Vec<(M, M)>>();
+        let active = pairs
+            .iter()
+            .zip(value)
+            .map(|((zero, one), bit)| {
+                if *bit {
+                    one.to_block()
+                } else {
+                    zero.to_block()
+                }
+            })
+            .collect::<Vec<Block>>();
+        send(self.channel, &active)?;
+        Ok(pairs)
+    }
+
+    fn peer_input(&mut self, bits: usize) -> Result<Vec<(M, M)>, ProtocolError> {
+        let pairs = (0..bits)
+            .map(|_| M::pair_with_delta(self.rng, &self.session.delta))
+            .collect::<Vec<(M, M)>>();
+        let blocks = pairs
+            .iter()
+            .map(|(zero, one)| (zero.to_block(), one.to_block()))
+            .collect::<Vec<(Block, Block)>>();
+        self.ot.send(self.channel, &blocks, self.rng)?;
+        Ok(pairs)
+    }
+
+    fn random(&mut self, bits: usize) -> Result<Vec<(M, M)>, ProtocolError> {
+        let value = (0..bits)
+            .map(|_| self.rng.next_u32() & 1 == 1)
+            .collect::<Vec<bool>>();
+        let mine = self.input(&value)?;
+        let theirs = self.peer_input(bits)?;
+        let mut outputs = self.execute(&xor_circuit(bits), vec![mine, theirs])?;
+        Ok(outputs.remove(0))
+    }
+
+    fn random_switches(&mut self, count: usize) -> Result<Vec<Vec<(M, M)>>, ProtocolError> {
+        let switches = random_waksman_switches(count, self.rng);
+        let mine = self.input(&switches)?;
+        let theirs = self.peer_input(waksman_switch_count(count))?;
+        Ok(vec![mine, theirs])
+    }
+}
+
+impl<'a, M, C, OT, R> Executor for Evaluator<'a, M, C, OT, R>
+where
+    M: GateGarbling,
+    C: AbstractChannel,
+    OT: OtReceiver<Msg = Block>,
+    R: RngCore + CryptoRng,
+{
+    type Key = M;
+
+    fn execute(
+        &mut self,
+        circuit: &Circuit,
+        inputs: Vec<Vec<M>>,
+    ) -> Result<Vec<Vec<M>>, ProtocolError> {
+        let (id, tables): (u64, GarbledTables) = receive(self.channel)?;
+        check_count(circuit.table_count(), tables.len())?;
+        Ok(evaluate_circuit(circuit, inputs, id, &tables))
+    }
+
+    fn reveal_to(&mut self, value: &[M], to: RevealTo) -> Result<Option<Vec<bool>>, ProtocolError> {
+        let bits = if to != RevealTo::Garbler {
+            let decoding: Vec<bool> = receive(self.channel)?;
+            let bits = value
+                .iter()
+                .zip(decoding)
+                .map(|(key, decoding_bit)| key.decode_bit(decoding_bit))
+                .collect::<Vec<bool>>();
+            Some(bits)
+        } else {
+            None
+        };
+        if to != RevealTo::Evaluator {
+            let active = value.iter().map(M::to_block).collect::<Vec<Block>>();
+            send(self.channel, &active)?;
+        }
+        Ok(bits)
+    }
+
+    fn input(&mut self, value: &[bool]) -> Result<Vec<M>, ProtocolError> {
+        let keys = self.ot.receive(self.channel, value, self.rng)?;
+        Ok(keys.into_iter().map(M::from_block).collect())
+    }
+
+    fn peer_input(&mut self, bits: usize) -> Result<Vec<M>, ProtocolError> {
+        let active: Vec<Block> = receive(self.channel)?;
+        check_count(bits, active.len())?;
+        Ok(active.into_iter().map(M::from_block).collect())
+    }
+
+    fn random(&mut self, bits: usize) -> Result<Vec<M>, ProtocolError> {
+        let theirs = self.peer_input(bits)?;
+        let value = (0..bits)
+            .map(|_| self.rng.next_u32() & 1 == 1)
+            .collect::<Vec<bool>>();
+        let mine = self.input(&value)?;
+        let mut outputs = self.execute(&xor_circuit(bits), vec![theirs, mine])?;
+        Ok(outputs.remove(0))
+    }
+
+    fn random_switches(&mut self, count: usize) -> Result<Vec<Vec<M>>, ProtocolError> {
+        let theirs = self.peer_input(waksman_switch_count(count))?;
+        let switches = random_waksman_switches(count, self.rng);
+        let mine = self.input(&switches)?;
+        Ok(vec![theirs, mine])
+    }
+}
+
+// ----------------------------------------------------------------------------------------------
+// -                                 Utility Functions                                          -
+// ----------------------------------------------------------------------------------------------
+
+/// Serializes a message and sends it to the other party.
+pub fn send<C: AbstractChannel, T: Serialize>(
+    channel: &mut C,
+    message: &T,
+) -> Result<(), ProtocolError> {
+    let bytes = bincode::serialize(message)?;
+    channel.write_usize(bytes.len())?;
+    channel.write_bytes(&bytes)?;
+    channel.flush()?;
+    Ok(())
+}
+
+/// Receives a message sent with `send` and deserializes it.
+pub fn receive<C: AbstractChannel, T: DeserializeOwned>(
+    channel: &mut C,
+) -> Result<T, ProtocolError> {
+    let size = channel.read_usize()?;
+    let bytes = channel.read_vec(size)?;
+    Ok(bincode::deserialize(&bytes)?)
+}
+
+fn check_count(expected: usize, received: usize) -> Result<(), ProtocolError> {
+    if expected == received {
+        Ok(())
+    } else {
+        Err(ProtocolError::WrongCount { expected, received })
+    }
+}
+
+fn xor_circuit(bits: usize) -> Circuit {
+    let mut builder = CircuitBuilder::new();
+    let input_1 = builder.input(1, bits as u32);
+    let input_2 = builder.input(2, bits as u32);
+    let output = builder.bitwise_xor(&input_1, &input_2);
+    builder.output(&output);
+    builder.build()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{free_xor_garbling::FreeXorBit, mpc_core::WireValue};
+    use ocelot::ot::{ChouOrlandiReceiver, ChouOrlandiSender};
+    use scuttlebutt::{AesRng, TrackChannel};
+    use std::{
+        io::{BufReader, BufWriter},
+        os::unix::net::UnixStream,
+    };
+
+    fn adder(bits: u32) -> Circuit {
+        let mut builder = CircuitBuilder::new();
+        let input_1 = builder.input(1, bits);
+        let input_2 = builder.input(2, bits);
+        let output = builder.add(&input_1, &input_2);
+        builder.output(&output);
+        builder.build()
+    }
+
+    #[test]
+    fn test_execute_reveal_and_random() {
+        let (sender, receiver) = UnixStream::pair().unwrap();
+        let handle = std::thread::spawn(move || {
+            let mut rng = AesRng::new();
+            let reader = BufReader::new(sender.try_clone().unwrap());
+            let writer = BufWriter::new(sender);
+            let mut channel = TrackChannel::new(reader, writer);
+            let mut session = GarblingSession::new(&mut rng);
+            let mut ot = ChouOrlandiSender::init(&mut channel, &mut rng).unwrap();
+            let mut garbler =
+                Garbler::<FreeXorBit, _, _, _>::new(&mut channel, &mut ot, &mut session, &mut rng);
+
+            let random = garbler.random(64).unwrap();
+            let sum = garbler
+                .execute(&adder(64), vec![random.clone(), random.clone()])
+                .unwrap();
+            let revealed = garbler.reveal(&random).unwrap();
+            let doubled = garbler.reveal(&sum[0]).unwrap();
+            assert_eq!(u64::from_bits(&doubled), u64::from_bits(&revealed) << 1);
+            revealed
+        });
+
+        let mut rng = AesRng::new();
+        let reader = BufReader::new(receiver.try_clone().unwrap());
+        let writer = BufWriter::new(receiver);
+        let mut channel = TrackChannel::new(reader, writer);
+        let mut ot = ChouOrlandiReceiver::init(&mut channel, &mut rng).unwrap();
+        let mut evaluator = Evaluator::<FreeXorBit, _, _, _>::new(&mut channel, &mut ot, &mut rng);
+
+        let random = evaluator.random(64).unwrap();
+        let sum = evaluator
+            .execute(&adder(64), vec![random.clone(), random.clone()])
+            .unwrap();
+        let revealed = evaluator.reveal(&random).unwrap();
+        let doubled = evaluator.reveal(&sum[0]).unwrap();
+        assert_eq!(u64::from_bits(&doubled), u64::from_bits(&revealed) << 1);
+        assert_eq!(handle.join().unwrap(), revealed);
+    }
+
+    #[test]
+    fn test_wrong_counts_are_errors() {
+        let (sender, receiver) = UnixStream::pair().unwrap();
+        let handle = std::thread::spawn(move || {
+            let mut rng = AesRng::new();
+            let reader = BufReader::new(sender.try_clone().unwrap());
+            let writer = BufWriter::new(sender);
+            let mut channel = TrackChannel::new(reader, writer);
+            ChouOrlandiSender::init(&mut channel, &mut rng).unwrap();
+            let tables: GarbledTables = vec![vec![Block::default()]];
+            send(&mut channel, &(0u64, tables)).unwrap();
+            send(&mut channel, &vec![Block::default(); 7]).unwrap();
+        });
+
+        let mut rng = AesRng::new();
+        let reader = BufReader::new(receiver.try_clone().unwrap());
+        let writer = BufWriter::new(receiver);
+        let mut channel = TrackChannel::new(reader, writer);
+        let mut ot = ChouOrlandiReceiver::init(&mut channel, &mut rng).unwrap();
+        let mut evaluator = Evaluator::<FreeXorBit, _, _, _>::new(&mut channel, &mut ot, &mut rng);
+
+        let circuit = adder(8);
+        let keys = vec![FreeXorBit(Block::default()); 8];
+        match evaluator.execute(&circuit, vec![keys.clone(), keys]) {
+            Err(ProtocolError::WrongCount { expected, received }) => {
+                assert_eq!((expected, received), (circuit.table_count(), 1))
+            }
+            other => panic!("unexpected result {:?}", other.map(|_| ())),
+        }
+        match evaluator.peer_input(8) {
+            Err(ProtocolError::WrongCount { expected, received }) => {
+                assert_eq!((expected, received), (8, 7))
+            }
+            other => panic!("unexpected result {:?}", other.map(|_| ())),
+        }
+        handle.join().unwrap();
+    }
+}
@@ -0,0 +1,278 @@
+//! Implements fixed-point numbers on top of the integer wires. A fixed-point number with
+//! `FRACTION` fractional bits is stored as the integer `value * 2^FRACTION`, so addition,
+//! subtraction, and comparison are the integer gadgets, and only multiplication and division need
+//! gadgets of their own.
+
+use crate::{
+    circuit::{Circuit, CircuitBuilder, WireId},
+    mpc_core::{Operation, Wire, WireValue},
+};
+use serde::{Deserialize, Serialize};
+use std::marker::PhantomData;
+
+// ----------------------------------------------------------------------------------------------
+// -                                  Type Definitions                                          -
+// ----------------------------------------------------------------------------------------------
+
+/// The integer types that can store the raw value of a fixed-point number.
+pub trait FixedPointRaw: WireValue + Copy {
+    /// Converts a float to the integer, rounding toward zero and saturating at the bounds.
+    fn from_f64(value: f64) -> Self;
+    fn to_f64(self) -> f64;
+}
+
+/// A fixed-point number with `FRACTION` fractional bits, stored as the raw integer
+/// `value * 2^FRACTION`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct FixedPoint<I, const FRACTION: u32>(pub I);
+
+/// A wire that represents a fixed-point number with `FRACTION` fractional bits, stored in the
+/// integer wire `W`. The number is signed if `W` is signed.
+#[derive(Clone, Debug)]
+pub struct WireFixedPoint<W: Wire, const FRACTION: u32> {
+    wire_info: PhantomData<W>,
+}
+
+// ----------------------------------------------------------------------------------------------
+// -                                    Impl blocks                                             -
+// ----------------------------------------------------------------------------------------------
+
+macro_rules! fixed_point_raw {
+    ($($type:ty),*) => {
+        $(
+            impl FixedPointRaw for $type {
+                fn from_f64(value: f64) -> Self {
+                    value as $type
+                }
+
+                fn to_f64(self) -> f64 {
+                    self as f64
+                }
+            }
+        )*
+    };
+}
+
+fixed_point_raw!(u8, u16, u32, u64, u128, i8, i16, i32, i64, i128);
+
+impl<I: FixedPointRaw, const FRACTION: u32> FixedPoint<I, FRACTION> {
+    /// Converts a float to the nearest fixed-point number. Values out of range saturate.
+    pub fn from_f64(value: f64) -> Self {
+        FixedPoint(I::from_f64((value * 2f64.powi(FRACTION as i32)).round()))
+    }
+
+    pub fn to_f64(self) -> f64 {
+        self.0.to_f64() / 2f64.powi(FRACTION as i32)
+    }
+
+    /// Converts a float to the nearest fixed-point number. Values out of range saturate.
+    pub fn from_f32(value: f32) -> Self {
+        Self::from_f64(value as f64)
+    }
+
+    pub fn to_f32(self) -> f32 {
+        self.to_f64() as f32
+    }
+}
+
+impl<I: WireValue, const FRACTION: u32> WireValue for FixedPoint<I, FRACTION> {
+    fn to_bits(&self) -> Vec<bool> {
+        self.0.to_bits()
+    }
+
+    fn from_bits(bits: &[bool]) -> Self {
+        FixedPoint(I::from_bits(bits))
+    }
+}
+
+impl<W: Wire, const FRACTION: u32> Wire for WireFixedPoint<W, FRACTION>
+where
+    W::ValueType: FixedPointRaw,
+{
+    type ValueType = FixedPoint<W::ValueType, FRACTION>;
+
+    fn bits() -> u32 {
+        W::bits()
+    }
+
+    fn is_signed() -> bool {
+        W::is_signed()
+    }
+
+    /// Compiles multiplication and division with the fixed-point gadgets. The other operations
+    /// are the integer ones.
+    fn operation_circuit(operation: Operation) -> Circuit {
+        let mut builder = CircuitBuilder::new();
+        let input_1 = builder.input(0, W::bits());
+        let input_2 = builder.input(0, W::bits());
+        let output = match operation {
+            Operation::Mul => builder.mul_fixed(&input_1, &input_2, FRACTION, W::is_signed()),
+            Operation::Div => builder.div_fixed(&input_1, &input_2, FRACTION, W::is_signed()),
+            _ => return operation.circuit(W::bits(), W::is_signed()),
+        };
+        builder.output(&output);
+        builder.build()
+    }
+}
+
+impl CircuitBuilder {
+    /// Multiplies two fixed-point numbers with `fraction` fractional bits. The full product is
+    /// computed at twice the width and then shifted right by `fraction` bits, i.e., the result is
+    /// rounded toward negative infinity and wraps around if it does not fit the width.
+    pub fn mul_fixed(
+        &mut self,
+        input_1: &[WireId],
+        input_2: &[WireId],
+        fraction: u32,
+        signed: bool,
+    ) -> Vec<WireId> {
+        let width = input_1.len();
+        let fraction = fraction as usize;
+        assert!(fraction <= width, "more fractional bits than bits");
+        let product = self.mul_full(input_1, input_2, signed);
+        product[width - fraction..2 * width - fraction].to_vec()
+    }
+
+    /// Divides two fixed-point numbers with `fraction` fractional bits. The dividend is shifted
+    /// left by `fraction` bits before an integer division at `width + fraction` bits, i.e., the
+    /// result is rounded toward zero and wraps around if it does not fit the width. Dividing by
+    /// zero returns a value with every bit set.
+    pub fn div_fixed(
+        &mut self,
+        input_1: &[WireId],
+        input_2: &[WireId],
+        fraction: u32,
+        signed: bool,
+    ) -> Vec<WireId> {
+        let width = input_1.len();
+        let fraction = fraction as usize;
+        assert!(fraction <= width, "more fractional bits than bits");
+        let mut dividend = input_1.to_vec();
+        dividend.extend(self.constant_word(0, fraction));
+        let divisor = self.extend(input_2, width + fraction, signed);
+        let quotient = self.div(&dividend, &divisor, signed);
+        quotient[fraction..].to_vec()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        free_xor_garbling::FreeXorBit,
+        mpc_core::{
+            evaluate_circuit, evaluate_gate, garble_circuit, garble_gate, EvaluatingWire,
+            GarblingSession, GarblingWire, Operation,
+        },
+        wires::{WireBool, WireSigned16Bit},
+    };
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+    const SEED: [u8; 32] = [42u8; 32];
+
+    type Fixed = FixedPoint<i16, 8>;
+    type WireFixed = WireFixedPoint<WireSigned16Bit, 8>;
+
+    #[test]
+    fn test_fixed_point_conversions() {
+        assert_eq!(Fixed::from_f64(1.5).0, 384);
+        assert_eq!(Fixed::from_f64(-2.25).0, -576);
+        assert_eq!(Fixed::from_f32(0.001).0, 0);
+        assert_eq!(Fixed::from_f64(1000.0).0, i16::MAX);
+        assert_eq!(Fixed::from_f64(-3.140625).to_f64(), -3.140625);
+        assert_eq!(FixedPoint::<i16, 8>(-1).to_f32(), -0.00390625);
+        assert_eq!(
+            FixedPoint::<u32, 16>::from_f64(12.75).to_bits(),
+            (12 * 65536 + 49152u32).to_bits()
+        );
+    }
+
+    #[test]
+    fn test_garbled_fixed_point() {
+        let mut rng = StdRng::from_seed(SEED);
+        let mut builder = CircuitBuilder::new();
+        let a = builder.input(1, WireFixed::bits());
+        let b = builder.input(2, WireFixed::bits());
+        let sum = builder.add(&a, &b);
+        let difference = builder.sub(&a, &b);
+        let product = builder.mul_fixed(&a, &b, 8, WireFixed::is_signed());
+        let less = builder.less_than(&a, &b, WireFixed::is_signed());
+        builder.output(&sum);
+        builder.output(&difference);
+        builder.output(&product);
+        builder.output(&[less]);
+        let circuit = builder.build();
+
+        let mut session = GarblingSession::new(&mut rng);
+        for _ in 0..20 {
+            let x = Fixed::from_f64(rng.gen_range(-10.0, 10.0));
+            let y = Fixed::from_f64(rng.gen_range(-10.0, 10.0));
+            let wire_1 =
+                GarblingWire::<FreeXorBit, WireFixed>::with_delta(&mut rng, &session.delta);
+            let wire_2 =
+                GarblingWire::<FreeXorBit, WireFixed>::with_delta(&mut rng, &session.delta);
+            let garbled_value_1 = wire_1.clone().encode(x);
+            let garbled_value_2 = wire_2.clone().encode(y);
+
+            let (outputs, id, tables) = garble_circuit(
+                &circuit,
+                vec![wire_1.bits, wire_2.bits],
+                &mut session,
+                &mut rng,
+            );
+            let results = evaluate_circuit(
+                &circuit,
+                vec![garbled_value_1.bits, garbled_value_2.bits],
+                id,
+                &tables,
+            );
+            let mut outputs = outputs.into_iter().zip(results);
+            let mut next = || {
+                let (output, result) = outputs.next().unwrap();
+                GarblingWire::<FreeXorBit, WireFixed>::from_bits(output)
+                    .decoding_info()
+                    .decode(&EvaluatingWire { bits: result })
+            };
+            assert_eq!(next().to_f64(), x.to_f64() + y.to_f64());
+            assert_eq!(next().to_f64(), x.to_f64() - y.to_f64());
+            let want = ((x.0 as i32 * y.0 as i32) >> 8) as i16;
+            assert_eq!(next(), FixedPoint(want), "{:?} * {:?}", x, y);
+
+            let (output, result) = outputs.next().unwrap();
+            let less = GarblingWire::<FreeXorBit, WireBool>::from_bits(output)
+                .decoding_info()
+                .decode(&EvaluatingWire { bits: result });
+            assert_eq!(less, x < y, "{:?} < {:?}", x, y);
+        }
+    }
+
+    #[test]
+    fn test_fixed_point_operations() {
+        let mut rng = StdRng::from_seed(SEED);
+        let mut session = GarblingSession::new(&mut rng);
+        for _ in 0..10 {
+            let x = Fixed::from_f64(rng.gen_range(-10.0, 10.0));
+            let y = Fixed::from_f64(rng.gen_range(-10.0, 10.0));
+            let product = ((x.0 as i32 * y.0 as i32) >> 8) as i16;
+            let quotient = (((x.0 as i32) << 8) / y.0 as i32) as i16;
+            for &(operation, want) in [
+                (Operation::Add, FixedPoint(x.0.wrapping_add(y.0))),
+                (Operation::Mul, FixedPoint(product)),
+                (Operation::Div, FixedPoint(quotient)),
+            ]
+            .iter()
+            {
+                let wire_1 =
+                    GarblingWire::<FreeXorBit, WireFixed>::with_delta(&mut rng, &session.delta);
+                let wire_2 =
+                    GarblingWire::<FreeXorBit, WireFixed>::with_delta(&mut rng, &session.delta);
+                let garbled_value_1 = wire_1.clone().encode(x);
+                let garbled_value_2 = wire_2.clone().encode(y);
+                let (output, gates) =
+                    garble_gate(wire_1, wire_2, operation, &mut session, &mut rng);
+                let result = evaluate_gate(garbled_value_1, garbled_value_2, operation, gates);
+                let got = output.decoding_info().decode(&result);
+                assert_eq!(got, want, "{:?} {:?} {:?}", x, operation, y);
+            }
+        }
+    }
+}
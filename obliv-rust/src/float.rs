@@ -0,0 +1,743 @@
+//! Implements IEEE-754 single-precision floats on top of `CircuitBuilder`. A float is a group of
+//! 32 wires: the sign, the 8 exponent bits, and the 23 fraction bits, most significant bit first.
+//!
+//! Results are rounded to nearest with ties to even, subnormal numbers are supported, and the
+//! special cases follow IEEE-754, so every result matches native Rust `f32`. The only exception
+//! is the payload of NaNs: every NaN result is the quiet NaN `0x7FC00000`.
+//!
+//! The arithmetic gadgets follow the structure of Berkeley SoftFloat: the operands are unpacked,
+//! the exact result is computed on wider integers where the bits that do not fit are collapsed
+//! into a sticky bit, and the result is rounded and packed once at the end.
+
+use crate::circuit::{CircuitBuilder, WireId};
+
+/// The width of the signed exponents used while computing. It fits every intermediate exponent,
+/// including the ones of integers of up to 128 bits.
+const EXPONENT_WIDTH: usize = 12;
+
+/// The quiet NaN that is returned whenever the result is not a number.
+const NAN: u128 = 0x7FC0_0000;
+
+/// An unpacked float.
+struct Unpacked {
+    sign: WireId,
+    /// The biased exponent, where subnormal numbers have the exponent 1. It has `EXPONENT_WIDTH`
+    /// bits.
+    exponent: Vec<WireId>,
+    /// The 24-bit significand, including the implicit bit.
+    significand: Vec<WireId>,
+    is_nan: WireId,
+    is_inf: WireId,
+    is_zero: WireId,
+}
+
+impl CircuitBuilder {
+    /// Adds two floats.
+    pub fn add_f32(&mut self, input_1: &[WireId], input_2: &[WireId]) -> Vec<WireId> {
+        check_width(input_1, input_2);
+        // Order the operands such that the magnitude of `x` is at least the magnitude of `y`.
+        let swap = self.less_than(&input_1[1..], &input_2[1..], false);
+        let big = self.mux(swap, input_2, input_1);
+        let small = self.mux(swap, input_1, input_2);
+        let x = self.unpack(&big);
+        let y = self.unpack(&small);
+        let subtract = self.xor(x.sign, y.sign);
+
+        // Align the significands with six extra bits below them. The implicit bit is bit 29, so
+        // the sum fits in 31 bits.
+        let difference = self.sub(&x.exponent, &y.exponent);
+        let significand_x = self.widen(&x.significand, 2, 6);
+        let significand_y = self.widen(&y.significand, 2, 6);
+        let significand_y = self.shift_right_jamming(&significand_y, &difference);
+        let significand_y = significand_y
+            .iter()
+            .map(|bit| self.xor(*bit, subtract))
+            .collect::<Vec<WireId>>();
+        let sum = self.ripple_carry(&significand_x, &significand_y, subtract);
+
+        let (normalized, shift) = self.normalize(&sum[1..]);
+        let shift = self.extend(&shift, EXPONENT_WIDTH, false);
+        let exponent = self.sub(&x.exponent, &shift);
+        let mut significand = vec![self.constant(false)];
+        significand.extend(normalized);
+        let mut output = self.round_pack(x.sign, &exponent, &significand);
+
+        // An exact zero is positive, unless both operands are negative zeros.
+        let is_zero = self.any(&sum);
+        let is_zero = self.inv(is_zero);
+        let zero_sign = self.and(x.sign, y.sign);
+        let zero = self.signed_word(zero_sign, 0);
+        output = self.mux(is_zero, &zero, &output);
+        // The magnitude of `x` is the largest one, so `x` is infinite if any operand is.
+        output = self.mux(x.is_inf, &big, &output);
+        let both_inf = self.and(x.is_inf, y.is_inf);
+        let inf_minus_inf = self.and(both_inf, subtract);
+        let is_nan = self.or(x.is_nan, y.is_nan);
+        let is_nan = self.or(is_nan, inf_minus_inf);
+        self.select_constant(is_nan, NAN, &output)
+    }
+
+    /// Subtracts the second float from the first one.
+    pub fn sub_f32(&mut self, input_1: &[WireId], input_2: &[WireId]) -> Vec<WireId> {
+        check_width(input_1, input_2);
+        let mut negated = input_2.to_vec();
+        negated[0] = self.inv(input_2[0]);
+        self.add_f32(input_1, &negated)
+    }
+
+    /// Multiplies two floats.
+    pub fn mul_f32(&mut self, input_1: &[WireId], input_2: &[WireId]) -> Vec<WireId> {
+        check_width(input_1, input_2);
+        let x = self.unpack(input_1);
+        let y = self.unpack(input_2);
+        let sign = self.xor(x.sign, y.sign);
+
+        let product = self.mul_full(&x.significand, &y.significand, false);
+        let (normalized, shift) = self.normalize(&product);
+        // Keep the 31 most significant bits and collapse the others into the sticky bit.
+        let mut significand = vec![self.constant(false)];
+        significand.extend_from_slice(&normalized[..31]);
+        let sticky = self.any(&normalized[31..]);
+        significand[31] = self.or(significand[31], sticky);
+
+        // exponent = exponent_x + exponent_y - 127 - shift
+        let exponent = self.add(&x.exponent, &y.exponent);
+        let bias = self.constant_word(127, EXPONENT_WIDTH);
+        let exponent = self.sub(&exponent, &bias);
+        let shift = self.extend(&shift, EXPONENT_WIDTH, false);
+        let exponent = self.sub(&exponent, &shift);
+        let mut output = self.round_pack(sign, &exponent, &significand);
+
+        let is_zero = self.or(x.is_zero, y.is_zero);
+        let zero = self.signed_word(sign, 0);
+        output = self.mux(is_zero, &zero, &output);
+        let is_inf = self.or(x.is_inf, y.is_inf);
+        let inf = self.signed_word(sign, 0x7F80_0000);
+        output = self.mux(is_inf, &inf, &output);
+        let is_nan = self.or(x.is_nan, y.is_nan);
+        let inf_times_zero = self.and(is_inf, is_zero);
+        let is_nan = self.or(is_nan, inf_times_zero);
+        self.select_constant(is_nan, NAN, &output)
+    }
+
+    /// Divides the first float by the second one.
+    pub fn div_f32(&mut self, input_1: &[WireId], input_2: &[WireId]) -> Vec<WireId> {
+        check_width(input_1, input_2);
+        let x = self.unpack(input_1);
+        let y = self.unpack(input_2);
+        let sign = self.xor(x.sign, y.sign);
+
+        // Normalize the significands, so the quotient of the significands is in (0.5, 2).
+        let (significand_x, shift_x) = self.normalize(&x.significand);
+        let (significand_y, shift_y) = self.normalize(&y.significand);
+        let shift_x = self.extend(&shift_x, EXPONENT_WIDTH, false);
+        let shift_y = self.extend(&shift_y, EXPONENT_WIDTH, false);
+        let exponent_x = self.sub(&x.exponent, &shift_x);
+        let exponent_y = self.sub(&y.exponent, &shift_y);
+
+        // Long division of `significand_x * 2^31` by `significand_y`. The remainder is always less
+        // than the divisor, so it fits in 25 bits after shifting it to the left.
+        let divisor = self.extend(&significand_y, 26, false);
+        let mut remainder = self.extend(&significand_x, 25, false);
+        let mut quotient = Vec::with_capacity(32);
+        for step in 0..32 {
+            if step > 0 {
+                remainder.remove(0);
+                remainder.push(self.constant(false));
+            }
+            let extended = self.extend(&remainder, 26, false);
+            let difference = self.sub(&extended, &divisor);
+            let bit = self.inv(difference[0]);
+            remainder = self.mux(bit, &difference[1..], &remainder);
+            quotient.push(bit);
+        }
+        let sticky = self.any(&remainder);
+
+        // The quotient either has its leading one at bit 31 or at bit 30.
+        let high = quotient[0];
+        let mut shifted = vec![self.constant(false)];
+        shifted.extend_from_slice(&quotient[..31]);
+        shifted[31] = self.or(shifted[31], quotient[31]);
+        let mut significand = self.mux(high, &shifted, &quotient);
+        significand[31] = self.or(significand[31], sticky);
+
+        // exponent = exponent_x - exponent_y + 125 + high
+        let exponent = self.sub(&exponent_x, &exponent_y);
+        let bias = self.constant_word(125, EXPONENT_WIDTH);
+        let exponent = self.add(&exponent, &bias);
+        let high = self.extend(&[high], EXPONENT_WIDTH, false);
+        let exponent = self.add(&exponent, &high);
+        let mut output = self.round_pack(sign, &exponent, &significand);
+
+        let is_zero = self.or(x.is_zero, y.is_inf);
+        let zero = self.signed_word(sign, 0);
+        output = self.mux(is_zero, &zero, &output);
+        let is_inf = self.or(x.is_inf, y.is_zero);
+        let inf = self.signed_word(sign, 0x7F80_0000);
+        output = self.mux(is_inf, &inf, &output);
+        let is_nan = self.or(x.is_nan, y.is_nan);
+        let both_zero = self.and(x.is_zero, y.is_zero);
+        let both_inf = self.and(x.is_inf, y.is_inf);
+        let is_nan = self.or(is_nan, both_zero);
+        let is_nan = self.or(is_nan, both_inf);
+        self.select_constant(is_nan, NAN, &output)
+    }
+
+    /// Returns a wire that is set if the first float is less than the second one. Swap the
+    /// operands to compute greater than.
+    pub fn lt_f32(&mut self, input_1: &[WireId], input_2: &[WireId]) -> WireId {
+        let (less, _, is_nan) = self.compare_f32(input_1, input_2);
+        let is_number = self.inv(is_nan);
+        self.and(less, is_number)
+    }
+
+    /// Returns a wire that is set if the first float is less than or equal to the second one.
+    /// Swap the operands to compute greater than or equal.
+    pub fn le_f32(&mut self, input_1: &[WireId], input_2: &[WireId]) -> WireId {
+        let (less, equal, is_nan) = self.compare_f32(input_1, input_2);
+        let less_equal = self.or(less, equal);
+        let is_number = self.inv(is_nan);
+        self.and(less_equal, is_number)
+    }
+
+    /// Returns a wire that is set if the floats are equal. Zeros of both signs are equal, and NaN
+    /// is not equal to anything.
+    pub fn eq_f32(&mut self, input_1: &[WireId], input_2: &[WireId]) -> WireId {
+        let (_, equal, is_nan) = self.compare_f32(input_1, input_2);
+        let is_number = self.inv(is_nan);
+        self.and(equal, is_number)
+    }
+
+    /// Converts an integer to the nearest float, like `value as f32`.
+    pub fn int_to_f32(&mut self, input: &[WireId], signed: bool) -> Vec<WireId> {
+        let width = input.len();
+        assert!(width > 0 && width <= 128, "unsupported integer width");
+        let sign = if signed {
+            input[0]
+        } else {
+            self.constant(false)
+        };
+        // The magnitude is `(input ^ sign) + sign`.
+        let flipped = input
+            .iter()
+            .map(|bit| self.xor(*bit, sign))
+            .collect::<Vec<WireId>>();
+        let zeros = self.constant_word(0, width);
+        let magnitude = self.ripple_carry(&flipped, &zeros, sign);
+
+        let (normalized, shift) = self.normalize(&magnitude);
+        let mut significand = vec![self.constant(false)];
+        if width >= 31 {
+            significand.extend_from_slice(&normalized[..31]);
+            let sticky = self.any(&normalized[31..]);
+            significand[31] = self.or(significand[31], sticky);
+        } else {
+            significand.extend_from_slice(&normalized);
+            significand.extend(self.constant_word(0, 31 - width));
+        }
+        // exponent = width + 125 - shift
+        let exponent = self.constant_word(width as u128 + 125, EXPONENT_WIDTH);
+        let shift = self.extend(&shift, EXPONENT_WIDTH, false);
+        let exponent = self.sub(&exponent, &shift);
+        let output = self.round_pack(sign, &exponent, &significand);
+
+        let is_zero = self.any(&magnitude);
+        let is_zero = self.inv(is_zero);
+        self.select_constant(is_zero, 0, &output)
+    }
+
+    /// Converts a float to an integer of `width` bits, like `value as u32` or `value as i32`. The
+    /// value is rounded toward zero, out of range values saturate, and NaN is converted to zero.
+    pub fn f32_to_int(&mut self, input: &[WireId], width: usize, signed: bool) -> Vec<WireId> {
+        assert_eq!(input.len(), 32, "a float has 32 bits");
+        assert!(width > 0 && width <= 128, "unsupported integer width");
+        let x = self.unpack(input);
+        let bias = self.constant_word(127, EXPONENT_WIDTH);
+        let too_small = self.less_than(&x.exponent, &bias, true);
+        let limit = self.constant_word(127 + width as u128, EXPONENT_WIDTH);
+        let too_big = self.less_than(&x.exponent, &limit, true);
+        let too_big = self.inv(too_big);
+
+        // The significand is placed such that its leading bit is at bit `width + 22`, then it is
+        // shifted right by `width + 126 - exponent`, which is in `[0, width)` when the value is in
+        // range. The 23 least significant bits are the fraction that is dropped.
+        let shifted = self.widen(&x.significand, 0, width - 1);
+        let distance = self.constant_word(width as u128 + 126, EXPONENT_WIDTH);
+        let distance = self.sub(&distance, &x.exponent);
+        let distance_bits = (usize::BITS - (width - 1).leading_zeros()) as usize;
+        let shifted =
+            self.shift_right_secret(&shifted, &distance[EXPONENT_WIDTH - distance_bits..], false);
+        let magnitude = shifted[..width].to_vec();
+
+        let mut output = if signed {
+            let flipped = magnitude
+                .iter()
+                .map(|bit| self.xor(*bit, x.sign))
+                .collect::<Vec<WireId>>();
+            let zeros = self.constant_word(0, width);
+            let value = self.ripple_carry(&flipped, &zeros, x.sign);
+            // Saturate to `MIN` or `MAX`, which are the sign followed by its inverse.
+            let overflow = self.or(too_big, magnitude[0]);
+            let not_sign = self.inv(x.sign);
+            let mut saturated = vec![not_sign; width];
+            saturated[0] = x.sign;
+            self.mux(overflow, &saturated, &value)
+        } else {
+            let ones = self.constant_word(u128::MAX, width);
+            let value = self.mux(too_big, &ones, &magnitude);
+            self.select_constant(x.sign, 0, &value)
+        };
+        output = self.select_constant(too_small, 0, &output);
+        self.select_constant(x.is_nan, 0, &output)
+    }
+
+    // ------------------------------------------------------------------------------------------
+    //                                 Internal Functions                                       -
+    // ------------------------------------------------------------------------------------------
+
+    fn unpack(&mut self, input: &[WireId]) -> Unpacked {
+        assert_eq!(input.len(), 32, "a float has 32 bits");
+        let exponent = &input[1..9];
+        let fraction = &input[9..];
+        let implicit = self.any(exponent);
+        let is_max = self.all(exponent);
+        let fraction_nonzero = self.any(fraction);
+        let fraction_zero = self.inv(fraction_nonzero);
+        let is_subnormal = self.inv(implicit);
+
+        // Subnormal numbers have the same exponent as the smallest normal numbers.
+        let mut effective = self.extend(exponent, EXPONENT_WIDTH, false);
+        effective[EXPONENT_WIDTH - 1] = self.or(exponent[7], is_subnormal);
+        let mut significand = vec![implicit];
+        significand.extend_from_slice(fraction);
+
+        Unpacked {
+            sign: input[0],
+            exponent: effective,
+            significand,
+            is_nan: self.and(is_max, fraction_nonzero),
+            is_inf: self.and(is_max, fraction_zero),
+            is_zero: self.and(is_subnormal, fraction_zero),
+        }
+    }
+
+    /// Rounds and packs a float, following `softfloat_roundPackToF32`. The significand has 32
+    /// bits and is either normalized with its leading one at bit 30, or the exponent is at most
+    /// 0. The value of the float is `significand * 2^(exponent - 156)`.
+    fn round_pack(
+        &mut self,
+        sign: WireId,
+        exponent: &[WireId],
+        significand: &[WireId],
+    ) -> Vec<WireId> {
+        // Tiny values are denormalized by shifting the significand to the right.
+        let negative = exponent[0];
+        let zero_exponent = self.constant_word(0, EXPONENT_WIDTH);
+        let distance = self.sub(&zero_exponent, exponent);
+        let denormalized = self.shift_right_jamming(significand, &distance);
+        let significand = self.mux(negative, &denormalized, significand);
+        let exponent = self.mux(negative, &zero_exponent, exponent);
+
+        // Round to nearest: add half of the last place, and clear the last bit on ties.
+        let half = self.constant_word(0x40, 32);
+        let rounded = self.add(&significand, &half);
+        let round_bits = self.any(&significand[26..]);
+        let not_round_bits = self.inv(round_bits);
+        let tie = self.and(significand[25], not_round_bits);
+        let mut mantissa = rounded[..25].to_vec();
+        let not_tie = self.inv(tie);
+        mantissa[24] = self.and(mantissa[24], not_tie);
+
+        let max_exponent = self.constant_word(0xFD, EXPONENT_WIDTH);
+        let too_big = self.less_than(&max_exponent, &exponent, true);
+        let at_max = self.equal(&exponent, &max_exponent);
+        let rounds_over = self.and(at_max, rounded[0]);
+        let overflow = self.or(too_big, rounds_over);
+
+        // The implicit bit of the mantissa is added to the exponent.
+        let mut shifted_exponent = exponent[EXPONENT_WIDTH - 8..].to_vec();
+        shifted_exponent.extend(self.constant_word(0, 23));
+        let mantissa = self.extend(&mantissa, 31, false);
+        let packed = self.add(&shifted_exponent, &mantissa);
+        let mantissa_nonzero = self.any(&mantissa);
+        let mantissa_zero = self.inv(mantissa_nonzero);
+        let packed = self.select_constant(mantissa_zero, 0, &packed);
+        let packed = self.select_constant(overflow, 0x7F80_0000, &packed);
+
+        let mut output = vec![sign];
+        output.extend(packed);
+        output
+    }
+
+    /// Returns whether the first float is less than the second one and whether they are equal,
+    /// ignoring NaNs, and whether any of them is a NaN.
+    fn compare_f32(&mut self, input_1: &[WireId], input_2: &[WireId]) -> (WireId, WireId, WireId) {
+        check_width(input_1, input_2);
+        let x = self.unpack(input_1);
+        let y = self.unpack(input_2);
+        let both_zero = self.and(x.is_zero, y.is_zero);
+        let not_both_zero = self.inv(both_zero);
+        let magnitude_less = self.less_than(&input_1[1..], &input_2[1..], false);
+        let magnitude_greater = self.less_than(&input_2[1..], &input_1[1..], false);
+
+        // With equal signs, the magnitudes are compared, in reverse order for negative floats.
+        let same_sign_less = self.mux(x.sign, &[magnitude_greater], &[magnitude_less])[0];
+        let different_signs = self.xor(x.sign, y.sign);
+        let different_signs_less = self.and(x.sign, not_both_zero);
+        let less = self.mux(different_signs, &[different_signs_less], &[same_sign_less])[0];
+
+        let same = self.equal(input_1, input_2);
+        let equal = self.or(same, both_zero);
+        let is_nan = self.or(x.is_nan, y.is_nan);
+        (less, equal, is_nan)
+    }
+
+    /// Returns the constant `value` if `condition` is set, otherwise `if_false`.
+    fn select_constant(
+        &mut self,
+        condition: WireId,
+        value: u128,
+        if_false: &[WireId],
+    ) -> Vec<WireId> {
+        let if_true = self.constant_word(value, if_false.len());
+        self.mux(condition, &if_true, if_false)
+    }
+
+    /// Returns the 31 least significant bits of `value` after the `sign`.
+    fn signed_word(&mut self, sign: WireId, value: u128) -> Vec<WireId> {
+        let mut output = vec![sign];
+        output.extend(self.constant_word(value, 31));
+        output
+    }
+
+    /// Adds `high` zeros before the value and `low` zeros after it.
+    fn widen(&mut self, input: &[WireId], high: usize, low: usize) -> Vec<WireId> {
+        let mut output = self.constant_word(0, high);
+        output.extend_from_slice(input);
+        output.extend(self.constant_word(0, low));
+        output
+    }
+
+    /// Returns a wire that is set if any of the wires is set.
+    pub(crate) fn any(&mut self, input: &[WireId]) -> WireId {
+        let zero = self.constant(false);
+        input.iter().fold(zero, |acc, bit| self.or(acc, *bit))
+    }
+
+    /// Returns a wire that is set if all of the wires are set.
+    fn all(&mut self, input: &[WireId]) -> WireId {
+        let one = self.constant(true);
+        input.iter().fold(one, |acc, bit| self.and(acc, *bit))
+    }
+
+    /// Shifts a value to the right by a secret `distance`, most significant bit first. The least
+    /// significant bit of the result is set when any set bit is shifted out.
+    fn shift_right_jamming(&mut self, input: &[WireId], distance: &[WireId]) -> Vec<WireId> {
+        let width = input.len();
+        let mut output = input.to_vec();
+        for (power, bit) in distance.iter().rev().enumerate() {
+            let shift = if power < 16 {
+                (1 << power).min(width)
+            } else {
+                width
+            };
+            let mut shifted = self.constant_word(0, shift);
+            shifted.extend_from_slice(&output[..width - shift]);
+            let lost = self.any(&output[width - shift..]);
+            shifted[width - 1] = self.or(shifted[width - 1], lost);
+            output = self.mux(*bit, &shifted, &output);
+        }
+        output
+    }
+
+    /// Shifts a non-zero value to the left until its most significant bit is set. Returns the
+    /// shifted value and the distance, most significant bit first.
+    fn normalize(&mut self, input: &[WireId]) -> (Vec<WireId>, Vec<WireId>) {
+        let width = input.len();
+        let mut output = input.to_vec();
+        let mut distance = vec![];
+        let stages = (usize::BITS - width.saturating_sub(1).leading_zeros()) as usize;
+        for power in (0..stages).rev() {
+            let shift = 1 << power;
+            let top = self.any(&output[..shift]);
+            let top_zero = self.inv(top);
+            let mut shifted = output[shift..].to_vec();
+            shifted.extend(self.constant_word(0, shift));
+            output = self.mux(top_zero, &shifted, &output);
+            distance.push(top_zero);
+        }
+        (output, distance)
+    }
+}
+
+fn check_width(input_1: &[WireId], input_2: &[WireId]) {
+    assert_eq!(input_1.len(), 32, "a float has 32 bits");
+    assert_eq!(input_2.len(), 32, "a float has 32 bits");
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        circuit::{Circuit, CircuitBuilder},
+        free_xor_garbling::FreeXorBit,
+        mpc_core::{
+            evaluate_circuit, evaluate_comparison, evaluate_gate, garble_circuit,
+            garble_comparison, garble_gate, Comparison, EvaluatingWire, GarblingSession,
+            GarblingWire, Operation, WireValue,
+        },
+        wires::WireF32,
+    };
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+    const SEED: [u8; 32] = [42u8; 32];
+
+    const SPECIAL: [u32; 16] = [
+        0x0000_0000, // 0
+        0x8000_0000, // -0
+        0x3F80_0000, // 1
+        0xBF80_0000, // -1
+        0x7F80_0000, // inf
+        0xFF80_0000, // -inf
+        0x7FC0_0000, // NaN
+        0x7F80_0001, // signaling NaN
+        0x0000_0001, // smallest subnormal
+        0x807F_FFFF, // largest negative subnormal
+        0x0080_0000, // smallest normal
+        0x7F7F_FFFF, // largest normal
+        0xFF7F_FFFF, // most negative normal
+        0x3F80_0001, // 1 + epsilon
+        0x4B80_0000, // 2^24
+        0x3EFF_FFFF, // just below 0.5
+    ];
+
+    /// Returns a mix of special values, random values, and values that are close to each other,
+    /// which exercise the rounding and the cancellation paths.
+    fn samples(rng: &mut StdRng) -> Vec<(f32, f32)> {
+        let mut samples = vec![];
+        for x in SPECIAL.iter() {
+            for y in SPECIAL.iter() {
+                samples.push((f32::from_bits(*x), f32::from_bits(*y)));
+            }
+        }
+        for _ in 0..600 {
+            let x: u32 = rng.gen();
+            let y: u32 = match rng.gen_range(0, 4) {
+                0 => rng.gen(),
+                1 => x ^ rng.gen_range(0, 1 << 8),
+                2 => x.wrapping_add(rng.gen_range(0, 30) << 23) ^ rng.gen_range(0, 1 << 24),
+                _ => rng.gen_range(0, 1 << 24) | (x & 0x8000_0000),
+            };
+            samples.push((f32::from_bits(x), f32::from_bits(y)));
+        }
+        samples
+    }
+
+    fn same(got: f32, want: f32) -> bool {
+        (got.is_nan() && want.is_nan()) || got.to_bits() == want.to_bits()
+    }
+
+    fn binary_circuit<F: Fn(&mut CircuitBuilder, &[usize], &[usize]) -> Vec<usize>>(
+        gadget: F,
+    ) -> Circuit {
+        let mut builder = CircuitBuilder::new();
+        let a = builder.input(1, 32);
+        let b = builder.input(2, 32);
+        let output = gadget(&mut builder, &a, &b);
+        builder.output(&output);
+        builder.build()
+    }
+
+    fn check_arithmetic<F, G>(gadget: F, native: G)
+    where
+        F: Fn(&mut CircuitBuilder, &[usize], &[usize]) -> Vec<usize>,
+        G: Fn(f32, f32) -> f32,
+    {
+        let mut rng = StdRng::from_seed(SEED);
+        let circuit = binary_circuit(gadget);
+        for (x, y) in samples(&mut rng) {
+            let got = circuit.compute(&[WireValue::to_bits(&x), WireValue::to_bits(&y)]);
+            let got = f32::from_bits(u32::from_bits(&got[0]));
+            let want = native(x, y);
+            assert!(same(got, want), "{:e} {:e}: {:e} != {:e}", x, y, got, want);
+        }
+    }
+
+    #[test]
+    fn test_add_f32() {
+        check_arithmetic(|builder, a, b| builder.add_f32(a, b), |x, y| x + y);
+    }
+
+    #[test]
+    fn test_sub_f32() {
+        check_arithmetic(|builder, a, b| builder.sub_f32(a, b), |x, y| x - y);
+    }
+
+    #[test]
+    fn test_mul_f32() {
+        check_arithmetic(|builder, a, b| builder.mul_f32(a, b), |x, y| x * y);
+    }
+
+    #[test]
+    fn test_div_f32() {
+        check_arithmetic(|builder, a, b| builder.div_f32(a, b), |x, y| x / y);
+    }
+
+    #[test]
+    fn test_compare_f32() {
+        let mut rng = StdRng::from_seed(SEED);
+        let circuit = binary_circuit(|builder, a, b| {
+            vec![
+                builder.lt_f32(a, b),
+                builder.le_f32(a, b),
+                builder.eq_f32(a, b),
+                builder.lt_f32(b, a),
+            ]
+        });
+        for (x, y) in samples(&mut rng) {
+            let got = circuit.compute(&[WireValue::to_bits(&x), WireValue::to_bits(&y)]);
+            assert_eq!(
+                got[0],
+                vec![x < y, x <= y, x == y, x > y],
+                "{:e} {:e}",
+                x,
+                y
+            );
+        }
+    }
+
+    #[test]
+    fn test_int_conversions() {
+        let mut rng = StdRng::from_seed(SEED);
+        let mut builder = CircuitBuilder::new();
+        let signed = builder.input(1, 32);
+        let unsigned = builder.input(1, 64);
+        let float = builder.input(1, 32);
+        let outputs = [
+            builder.int_to_f32(&signed, true),
+            builder.int_to_f32(&unsigned, false),
+            builder.f32_to_int(&float, 32, true),
+            builder.f32_to_int(&float, 64, false),
+            builder.f32_to_int(&float, 8, true),
+        ];
+        for output in outputs.iter() {
+            builder.output(output);
+        }
+        let circuit = builder.build();
+
+        let mut floats = SPECIAL
+            .iter()
+            .map(|x| f32::from_bits(*x))
+            .collect::<Vec<_>>();
+        floats.extend(&[
+            0.5,
+            -0.5,
+            2.5,
+            -127.9,
+            -128.0,
+            -128.5,
+            255.9,
+            2147483520.0,
+            -2.1e9,
+        ]);
+        for index in 0..500 {
+            let x: i32 = rng.gen::<i32>() >> rng.gen_range(0, 32);
+            let y: u64 = rng.gen::<u64>() >> rng.gen_range(0, 64);
+            let z = if index < floats.len() {
+                floats[index]
+            } else {
+                f32::from_bits(rng.gen::<u32>() & 0xCFFF_FFFF)
+            };
+            let got = circuit.compute(&[x.to_bits(), y.to_bits(), WireValue::to_bits(&z)]);
+            let as_f32 = |bits: &[bool]| f32::from_bits(u32::from_bits(bits));
+            assert_eq!(as_f32(&got[0]).to_bits(), (x as f32).to_bits(), "{}", x);
+            assert_eq!(as_f32(&got[1]).to_bits(), (y as f32).to_bits(), "{}", y);
+            assert_eq!(i32::from_bits(&got[2]), z as i32, "{:e}", z);
+            assert_eq!(u64::from_bits(&got[3]), z as u64, "{:e}", z);
+            assert_eq!(i8::from_bits(&got[4]), z as i8, "{:e}", z);
+        }
+    }
+
+    #[test]
+    fn test_garbled_f32() {
+        let mut rng = StdRng::from_seed(SEED);
+        let circuit = binary_circuit(|builder, a, b| {
+            let sum = builder.add_f32(a, b);
+            builder.mul_f32(&sum, b)
+        });
+        let mut session = GarblingSession::new(&mut rng);
+        let wire_1 = GarblingWire::<FreeXorBit, WireF32>::with_delta(&mut rng, &session.delta);
+        let wire_2 = GarblingWire::<FreeXorBit, WireF32>::with_delta(&mut rng, &session.delta);
+        let garbled_value_1 = wire_1.clone().encode(1.25);
+        let garbled_value_2 = wire_2.clone().encode(-0.1);
+
+        let (mut outputs, id, tables) = garble_circuit(
+            &circuit,
+            vec![wire_1.bits, wire_2.bits],
+            &mut session,
+            &mut rng,
+        );
+        let mut results = evaluate_circuit(
+            &circuit,
+            vec![garbled_value_1.bits, garbled_value_2.bits],
+            id,
+            &tables,
+        );
+        let got = GarblingWire::<FreeXorBit, WireF32>::from_bits(outputs.remove(0))
+            .decoding_info()
+            .decode(&EvaluatingWire {
+                bits: results.remove(0),
+            });
+        assert_eq!(got, (1.25f32 + -0.1) * -0.1);
+    }
+
+    #[test]
+    fn test_f32_operations_and_comparisons() {
+        let mut rng = StdRng::from_seed(SEED);
+        let mut session = GarblingSession::new(&mut rng);
+        let values = [1.25f32, -0.1, 3.0e-39, -0.0, f32::INFINITY, f32::NAN];
+        for (x, y) in values.iter().zip(values.iter().rev()) {
+            for &(operation, want) in [
+                (Operation::Add, x + y),
+                (Operation::Sub, x - y),
+                (Operation::Mul, x * y),
+                (Operation::Div, x / y),
+            ]
+            .iter()
+            {
+                let wire_1 =
+                    GarblingWire::<FreeXorBit, WireF32>::with_delta(&mut rng, &session.delta);
+                let wire_2 =
+                    GarblingWire::<FreeXorBit, WireF32>::with_delta(&mut rng, &session.delta);
+                let garbled_value_1 = wire_1.clone().encode(*x);
+                let garbled_value_2 = wire_2.clone().encode(*y);
+                let (output, gates) =
+                    garble_gate(wire_1, wire_2, operation, &mut session, &mut rng);
+                let result = evaluate_gate(garbled_value_1, garbled_value_2, operation, gates);
+                let got = output.decoding_info().decode(&result);
+                assert!(same(got, want), "{:e} {:?} {:e}", x, operation, y);
+            }
+            for &(comparison, want) in [
+                (Comparison::Lt, x < y),
+                (Comparison::Le, x <= y),
+                (Comparison::Gt, x > y),
+                (Comparison::Ge, x >= y),
+                (Comparison::Eq, x == y),
+                (Comparison::Ne, x != y),
+            ]
+            .iter()
+            {
+                let wire_1 =
+                    GarblingWire::<FreeXorBit, WireF32>::with_delta(&mut rng, &session.delta);
+                let wire_2 =
+                    GarblingWire::<FreeXorBit, WireF32>::with_delta(&mut rng, &session.delta);
+                let garbled_value_1 = wire_1.clone().encode(*x);
+                let garbled_value_2 = wire_2.clone().encode(*y);
+                let (output, gates) =
+                    garble_comparison(wire_1, wire_2, comparison, &mut session, &mut rng);
+                let result =
+                    evaluate_comparison(garbled_value_1, garbled_value_2, comparison, gates);
+                let got = output.decoding_info().decode(&result);
+                assert_eq!(got, want, "{:e} {:?} {:e}", x, comparison, y);
+            }
+        }
+    }
+}
@@ -0,0 +1,152 @@
+//! Implements the Free-XOR garbling mode. Every one-key is its zero-key XORed with a session-wide
+//! secret `Delta`, so XOR and NOT gates can be garbled and evaluated without any table or
+//! communication.
+
+use crate::mpc_core::{Delta, EvaluatingWire, GarblingMode, GarblingWire, Wire};
+use rand_core::{CryptoRng, RngCore};
+use scuttlebutt::Block;
+use serde::{Deserialize, Serialize};
+
+// ----------------------------------------------------------------------------------------------
+//                              Type Definion and Impl Blocks                                   -
+// ----------------------------------------------------------------------------------------------
+
+/// A garbled bit where the one-key is the zero-key XORed with the global delta.
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
+pub struct FreeXorBit(pub Block);
+
+impl GarblingMode for FreeXorBit {
+    /// Panics, because a pair with its own offset cannot be combined with any other wire by the
+    /// free XOR gates. Every pair must be generated from the session delta with `pair_with_delta`.
+    fn pair<R: RngCore + CryptoRng>(_rng: &mut R) -> (Self, Self) {
+        panic!("Free-XOR keys need the session delta, use pair_with_delta instead")
+    }
+
+    fn pair_with_delta<R: RngCore + CryptoRng>(rng: &mut R, delta: &Delta) -> (Self, Self) {
+        let mut buffer: [u8; 16] = [0; 16];
+        rng.fill_bytes(&mut buffer);
+        let zero = Block::from(buffer);
+        (Self(zero), Self(zero ^ delta.0))
+    }
+
+    fn to_block(&self) -> Block {
+        self.0
+    }
+
+    fn from_block(block: Block) -> Self {
+        Self(block)
+    }
+}
+
+// ----------------------------------------------------------------------------------------------
+//                              Garble and Evaluate Operations                                  -
+// ----------------------------------------------------------------------------------------------
+
+/// Garbles a bitwise XOR of two wires. Both wires must have been generated with the same delta.
+pub fn garble_xor<W: Wire>(
+    input_1: GarblingWire<FreeXorBit, W>,
+    input_2: GarblingWire<FreeXorBit, W>,
+) -> GarblingWire<FreeXorBit, W> {
+    GarblingWire::from_bits(
+        input_1
+            .bits
+            .into_iter()
+            .zip(input_2.bits)
+            .map(|((zero_1, one_1), (zero_2, _))| {
+                (
+                    FreeXorBit(zero_1.0 ^ zero_2.0),
+                    FreeXorBit(one_1.0 ^ zero_2.0),
+                )
+            })
+            .collect(),
+    )
+}
+
+/// Evaluates a bitwise XOR of two wires by XORing their keys.
+pub fn evaluate_xor(
+    input_1: EvaluatingWire<FreeXorBit>,
+    input_2: EvaluatingWire<FreeXorBit>,
+) -> EvaluatingWire<FreeXorBit> {
+    EvaluatingWire {
+        bits: input_1
+            .bits
+            .into_iter()
+            .zip(input_2.bits)
+            .map(|(bit_1, bit_2)| FreeXorBit(bit_1.0 ^ bit_2.0))
+            .collect(),
+    }
+}
+
+/// Garbles a bitwise NOT of a wire by swapping the meaning of its keys.
+pub fn garble_not<W: Wire>(input: GarblingWire<FreeXorBit, W>) -> GarblingWire<FreeXorBit, W> {
+    GarblingWire::from_bits(
+        input
+            .bits
+            .into_iter()
+            .map(|(zero, one)| (one, zero))
+            .collect(),
+    )
+}
+
+/// Evaluates a bitwise NOT of a wire. The keys do not change, only their meaning does.
+pub fn evaluate_not(input: EvaluatingWire<FreeXorBit>) -> EvaluatingWire<FreeXorBit> {
+    input
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wires::Wire8Bit;
+    use rand::{rngs::StdRng, SeedableRng};
+    const SEED: [u8; 32] = [42u8; 32];
+
+    #[test]
+    fn test_free_xor_pairs_share_delta() {
+        let mut rng = StdRng::from_seed(SEED);
+        let delta = Delta::new(&mut rng);
+
+        let garbled_wires_1 = GarblingWire::<FreeXorBit, Wire8Bit>::with_delta(&mut rng, &delta);
+        let garbled_wires_2 = GarblingWire::<FreeXorBit, Wire8Bit>::with_delta(&mut rng, &delta);
+        assert!(garbled_wires_1
+            .bits
+            .into_iter()
+            .chain(garbled_wires_2.bits)
+            .all(|(zero, one)| zero.0 ^ one.0 == delta.0));
+        assert!(delta.0.lsb());
+    }
+
+    #[test]
+    #[should_panic(expected = "use pair_with_delta")]
+    fn test_free_xor_wires_need_the_session_delta() {
+        let mut rng = StdRng::from_seed(SEED);
+        GarblingWire::<FreeXorBit, Wire8Bit>::new(&mut rng);
+    }
+
+    #[test]
+    fn test_free_xor_u8() {
+        let mut rng = StdRng::from_seed(SEED);
+        let delta = Delta::new(&mut rng);
+
+        let garbled_wires_1 = GarblingWire::<FreeXorBit, Wire8Bit>::with_delta(&mut rng, &delta);
+        let garbled_value_1 = garbled_wires_1.clone().encode(6);
+        let garbled_wires_2 = GarblingWire::<FreeXorBit, Wire8Bit>::with_delta(&mut rng, &delta);
+        let garbled_value_2 = garbled_wires_2.clone().encode(12);
+
+        let output = garble_xor(garbled_wires_1, garbled_wires_2);
+        let result = evaluate_xor(garbled_value_1, garbled_value_2);
+        assert_eq!(output.encode(6 ^ 12).bits, result.bits);
+    }
+
+    #[test]
+    fn test_free_not_u8() {
+        let mut rng = StdRng::from_seed(SEED);
+        let delta = Delta::new(&mut rng);
+
+        let garbled_wires = GarblingWire::<FreeXorBit, Wire8Bit>::with_delta(&mut rng, &delta);
+        let garbled_value = garbled_wires.clone().encode(6);
+
+        let output = garble_not(garbled_wires);
+        let result = evaluate_not(garbled_value);
+        assert_eq!(output.encode(!6).bits, result.bits);
+    }
+}
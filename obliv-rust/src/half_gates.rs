@@ -0,0 +1,219 @@
+//! Implements the half-gates garbling scheme of Zahur, Rosulek, and Evans on top of the Free-XOR
+//! garbling mode. An AND gate is split into a garbler half-gate and an evaluator half-gate, and
+//! costs two ciphertexts. XOR and NOT gates remain free.
+
+use crate::{
+    free_xor_garbling::FreeXorBit,
+    mpc_core::{Delta, EvaluatingWire, GarblingSession, GarblingWire, Gate, GateGarbling, Wire},
+};
+use rand_core::{CryptoRng, RngCore};
+use scuttlebutt::{Block, AES_HASH};
+
+// ----------------------------------------------------------------------------------------------
+//                              Type Definion and Impl Blocks                                   -
+// ----------------------------------------------------------------------------------------------
+
+impl GateGarbling for FreeXorBit {
+    fn garble_and<R: RngCore + CryptoRng>(
+        input_1: &(Self, Self),
+        input_2: &(Self, Self),
+        id: u64,
+        delta: &Delta,
+        _rng: &mut R,
+    ) -> ((Self, Self), Vec<Block>) {
+        let ((a_zero, a_one), (b_zero, b_one)) = (input_1, input_2);
+        let (garbler_id, evaluator_id) = (2 * id, 2 * id + 1);
+        let permute_a = a_zero.0.lsb();
+        let permute_b = b_zero.0.lsb();
+
+        // Garbler half-gate: the garbler knows the permute bit of the second input.
+        let hash_a_zero = hash(a_zero.0, garbler_id);
+        let mut garbler_table = hash_a_zero ^ hash(a_one.0, garbler_id);
+        if permute_b {
+            garbler_table ^= delta.0;
+        }
+        let mut garbler_zero = hash_a_zero;
+        if permute_a {
+            garbler_zero ^= garbler_table;
+        }
+
+        // Evaluator half-gate: the evaluator sees the point-and-permute bit of the second input.
+        let hash_b_zero = hash(b_zero.0, evaluator_id);
+        let evaluator_table = hash_b_zero ^ hash(b_one.0, evaluator_id) ^ a_zero.0;
+        let mut evaluator_zero = hash_b_zero;
+        if permute_b {
+            evaluator_zero ^= evaluator_table ^ a_zero.0;
+        }
+
+        let zero = garbler_zero ^ evaluator_zero;
+        (
+            (FreeXorBit(zero), FreeXorBit(zero ^ delta.0)),
+            vec![garbler_table, evaluator_table],
+        )
+    }
+
+    fn evaluate_and(input_1: &Self, input_2: &Self, id: u64, table: &[Block]) -> Self {
+        let (garbler_id, evaluator_id) = (2 * id, 2 * id + 1);
+
+        let mut garbler_half = hash(input_1.0, garbler_id);
+        if input_1.0.lsb() {
+            garbler_half ^= table[0];
+        }
+        let mut evaluator_half = hash(input_2.0, evaluator_id);
+        if input_2.0.lsb() {
+            evaluator_half ^= table[1] ^ input_1.0;
+        }
+
+        FreeXorBit(garbler_half ^ evaluator_half)
+    }
+
+    fn garble_xor<R: RngCore + CryptoRng>(
+        input_1: &(Self, Self),
+        input_2: &(Self, Self),
+        _id: u64,
+        _delta: &Delta,
+        _rng: &mut R,
+    ) -> ((Self, Self), Vec<Block>) {
+        (
+            (
+                FreeXorBit(input_1.0 .0 ^ input_2.0 .0),
+                FreeXorBit(input_1.1 .0 ^ input_2.0 .0),
+            ),
+            vec![],
+        )
+    }
+
+    fn evaluate_xor(input_1: &Self, input_2: &Self, _id: u64, _table: &[Block]) -> Self {
+        FreeXorBit(input_1.0 ^ input_2.0)
+    }
+}
+
+// ----------------------------------------------------------------------------------------------
+//                              Garble and Evaluate Operations                                  -
+// ----------------------------------------------------------------------------------------------
+
+/// Garbles a bitwise AND of two wires. Both wires must have been generated with the delta of the
+/// session.
+pub fn garble_and<W: Wire, R: RngCore + CryptoRng>(
+    input_1: GarblingWire<FreeXorBit, W>,
+    input_2: GarblingWire<FreeXorBit, W>,
+    session: &mut GarblingSession,
+    rng: &mut R,
+) -> (GarblingWire<FreeXorBit, W>, Gate<FreeXorBit, W>) {
+    let id = session.reserve(W::bits() as u64);
+    let (bits, tables): (Vec<(FreeXorBit, FreeXorBit)>, Vec<Vec<Block>>) = input_1
+        .bits
+        .iter()
+        .zip(input_2.bits.iter())
+        .enumerate()
+        .map(|(index, (bit_1, bit_2))| {
+            FreeXorBit::garble_and(bit_1, bit_2, id + index as u64, &session.delta, rng)
+        })
+        .unzip();
+    (GarblingWire::from_bits(bits), Gate::new(id, tables))
+}
+
+/// Evaluates a bitwise AND of two wires using the tables of the garbled gate.
+pub fn evaluate_and<W: Wire>(
+    input_1: EvaluatingWire<FreeXorBit>,
+    input_2: EvaluatingWire<FreeXorBit>,
+    gate: &Gate<FreeXorBit, W>,
+) -> EvaluatingWire<FreeXorBit> {
+    EvaluatingWire {
+        bits: input_1
+            .bits
+            .iter()
+            .zip(input_2.bits.iter())
+            .zip(gate.tables.iter())
+            .enumerate()
+            .map(|(index, ((bit_1, bit_2), table))| {
+                FreeXorBit::evaluate_and(bit_1, bit_2, gate.id + index as u64, table)
+            })
+            .collect(),
+    }
+}
+
+// ----------------------------------------------------------------------------------------------
+//                                   Internal Functions                                         -
+// ----------------------------------------------------------------------------------------------
+
+/// The tweakable circular correlation robust hash used by both half-gates.
+fn hash(key: Block, tweak: u64) -> Block {
+    AES_HASH.tccr_hash(Block::from(tweak as u128), key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{mpc_core::GarblingMode, wires::Wire8Bit};
+    use rand::{rngs::StdRng, SeedableRng};
+    const SEED: [u8; 32] = [42u8; 32];
+
+    #[test]
+    fn test_half_gates_and_truth_table() {
+        let mut rng = StdRng::from_seed(SEED);
+        let mut session = GarblingSession::new(&mut rng);
+
+        let input_1 = FreeXorBit::pair_with_delta(&mut rng, &session.delta);
+        let input_2 = FreeXorBit::pair_with_delta(&mut rng, &session.delta);
+        let id = session.reserve(1);
+        let (output, table) =
+            FreeXorBit::garble_and(&input_1, &input_2, id, &session.delta, &mut rng);
+        assert_eq!(table.len(), 2);
+        assert_eq!(output.0 .0 ^ output.1 .0, session.delta.0);
+
+        for (x, y) in [(false, false), (false, true), (true, false), (true, true)].iter() {
+            let key_1 = if *x { &input_1.1 } else { &input_1.0 };
+            let key_2 = if *y { &input_2.1 } else { &input_2.0 };
+            let want = if *x && *y { &output.1 } else { &output.0 };
+            assert_eq!(
+                want,
+                &FreeXorBit::evaluate_and(key_1, key_2, id, &table),
+                "{} AND {}",
+                x,
+                y
+            );
+        }
+    }
+
+    #[test]
+    fn test_half_gates_and_u8() {
+        let mut rng = StdRng::from_seed(SEED);
+        let mut session = GarblingSession::new(&mut rng);
+
+        let garbled_wires_1 =
+            GarblingWire::<FreeXorBit, Wire8Bit>::with_delta(&mut rng, &session.delta);
+        let garbled_value_1 = garbled_wires_1.clone().encode(0b1010_1100);
+        let garbled_wires_2 =
+            GarblingWire::<FreeXorBit, Wire8Bit>::with_delta(&mut rng, &session.delta);
+        let garbled_value_2 = garbled_wires_2.clone().encode(0b0110_0101);
+
+        let (output, gate) = garble_and(garbled_wires_1, garbled_wires_2, &mut session, &mut rng);
+        assert_eq!(session.gate_count, 8);
+
+        let serialized_gate = bincode::serialize(&gate).unwrap();
+        let gate: Gate<FreeXorBit, Wire8Bit> = bincode::deserialize(&serialized_gate).unwrap();
+        let result = evaluate_and(garbled_value_1, garbled_value_2, &gate);
+        assert_eq!(output.encode(0b0010_0100).bits, result.bits);
+    }
+
+    #[test]
+    fn test_half_gates_unique_tweaks() {
+        let mut rng = StdRng::from_seed(SEED);
+        let mut session = GarblingSession::new(&mut rng);
+
+        let garbled_wires_1 =
+            GarblingWire::<FreeXorBit, Wire8Bit>::with_delta(&mut rng, &session.delta);
+        let garbled_wires_2 =
+            GarblingWire::<FreeXorBit, Wire8Bit>::with_delta(&mut rng, &session.delta);
+        let (_, gate_1) = garble_and(
+            garbled_wires_1.clone(),
+            garbled_wires_2.clone(),
+            &mut session,
+            &mut rng,
+        );
+        let (_, gate_2) = garble_and(garbled_wires_1, garbled_wires_2, &mut session, &mut rng);
+        assert_eq!(gate_2.id, 8);
+        assert_ne!(gate_1.tables, gate_2.tables);
+    }
+}
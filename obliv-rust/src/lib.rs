@@ -1,5 +1,22 @@
+pub mod arithmetic;
+pub mod bitwise;
+pub mod bristol;
+pub mod circuit;
+pub mod circuit_oram;
+pub mod comparison;
+pub mod executor;
+pub mod fixed_point;
+pub mod float;
+pub mod free_xor_garbling;
+pub mod half_gates;
 pub mod mpc_core;
+pub mod mux;
+pub mod obliv_array;
 pub mod obliv_macro;
+pub mod ot;
+pub mod permutation;
 pub mod plain_garbling;
 pub mod simple_garbling;
+pub mod sort;
+pub mod sqrt_oram;
 pub mod wires;
@@ -1,6 +1,12 @@
 //! Provides the API for a 2PC protocol. The user of this crate is not expected to call these
 //! directly. Instead, the user will call the macros and the macros call these APIs.
 
+use crate::{
+    circuit::{BooleanGate, Circuit, CircuitBuilder, WireId},
+    executor::{Evaluator, Executor, Garbler, ProtocolError},
+    ot::{AnyOtReceiver, AnyOtSender, OtKind},
+    wires::WireBool,
+};
 use rand_core::{CryptoRng, RngCore};
 use scuttlebutt::{AbstractChannel, Block};
 use serde::{Deserialize, Serialize};
@@ -24,40 +30,163 @@ pub enum Role {
     Evaluator,
 }
 
-/// Stores metadata about the protocol. Specifically, the current party.
+/// Stores metadata about the protocol. Specifically, the current party and the OT that it uses to
+/// input the evaluator's values.
 pub struct Protocol<C: AbstractChannel, R: RngCore + CryptoRng> {
     pub parties: Vec<Party>,
     pub me: Party,
     pub role: Role,
     pub channel: C,
     pub rng: R,
+    pub ot: PartyOt<AnyOtSender, AnyOtReceiver>,
+    /// The global delta of the garbler's keys and the ids of its garbled tables. The evaluator
+    /// does not use it.
+    pub session: GarblingSession,
+}
+
+/// The parties that learn a revealed value.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RevealTo {
+    /// Only the garbler learns the value. The evaluator sends the active keys back, which the
+    /// garbler matches against the keys of the wire.
+    Garbler,
+    /// Only the evaluator learns the value. The garbler sends the decoding information.
+    Evaluator,
+    /// Both parties learn the value.
+    Both,
+}
+
+/// The OT of a party. The garbler is the OT sender of the keys of the evaluator's inputs and the
+/// evaluator is the OT receiver. With an OT extension such as KOS, the setup runs the 128 base OTs
+/// once per session and every later batch of OTs only costs symmetric-key operations.
+pub enum PartyOt<S, R> {
+    Sender(S),
+    Receiver(R),
 }
 
 /// The operations that are supported by the protocol. Insead of focusing on 1-bit logic gates, the
 /// intention is to create higher level constructs that are used in writing typical programs.
+/// Every operation takes two values of the same wire type and returns a value of that type. The
+/// variants describe integer wires; wire types such as floats compile them with their own gadgets
+/// in `Wire::operation_circuit`.
+#[derive(Clone, Copy, Debug)]
 pub enum Operation {
-    AddU8,
-    MulU8,
+    /// Adds two integers. The result wraps around.
+    Add,
+    /// Subtracts the second integer from the first one. The result wraps around.
+    Sub,
+    /// Multiplies two integers. The result is truncated to the width of the operands.
+    Mul,
+    /// Divides the first integer by the second one, rounding toward zero. Dividing by zero returns
+    /// a value with every bit set.
+    Div,
+    /// Computes the bitwise AND of two integers.
+    And,
+    /// Computes the bitwise OR of two integers.
+    Or,
+    /// Computes the bitwise XOR of two integers.
+    Xor,
+    /// Shifts the first integer to the left by the second one, which is read as unsigned.
+    /// Shifting by the width or more returns zero.
+    Shl,
+    /// Shifts the first integer to the right by the second one, which is read as unsigned. Signed
+    /// integers are shifted arithmetically.
+    Shr,
+    /// Rotates the first integer to the left by the second one, which is read as unsigned.
+    RotateLeft,
+    /// Rotates the first integer to the right by the second one, which is read as unsigned.
+    RotateRight,
+}
+
+/// The comparisons that are supported by the protocol. Every comparison takes two values of the
+/// same wire type and returns a secret `WireBool`. Signed wires are compared as signed integers.
+#[derive(Clone, Copy, Debug)]
+pub enum Comparison {
+    /// Checks if the first value is less than the second one.
+    Lt,
+    /// Checks if the first value is less than or equal to the second one.
+    Le,
+    /// Checks if the first value is greater than the second one.
+    Gt,
+    /// Checks if the first value is greater than or equal to the second one.
+    Ge,
+    /// Checks if the two values are equal.
+    Eq,
+    /// Checks if the two values are different.
+    Ne,
 }
 
+/// The session-wide secret offset between the zero-key and the one-key of every wire in the
+/// garbling modes that support Free-XOR. Its least significant bit is always set so that the
+/// zero-key and one-key of a wire always have different point-and-permute bits.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Delta(pub Block);
+
 /// The main trait that distinguishes various garbling modes.
 pub trait GarblingMode {
     /// Generates a pair of keys for the zero and one value of a 1-bit wire.
     fn pair<R: RngCore + CryptoRng>(rng: &mut R) -> (Self, Self)
     where
         Self: Sized;
+    /// Generates a pair of keys where the one-key is the zero-key XORed with the session `delta`.
+    /// Garbling modes that do not support Free-XOR ignore `delta` and fall back to `pair`.
+    fn pair_with_delta<R: RngCore + CryptoRng>(rng: &mut R, _delta: &Delta) -> (Self, Self)
+    where
+        Self: Sized,
+    {
+        Self::pair(rng)
+    }
     /// Converts the key of a wire to a `Block` so that it can be used with `swanky` library.
     fn to_block(&self) -> Block;
+    /// Converts a `Block` received from the `swanky` library (e.g., via OT) back to a key.
+    fn from_block(block: Block) -> Self
+    where
+        Self: Sized;
+    /// Returns the bit that the evaluator needs to decode the keys of a pair. By default, it is the
+    /// point-and-permute bit of the zero-key.
+    fn decoding_bit(pair: &(Self, Self)) -> bool
+    where
+        Self: Sized,
+    {
+        pair.0.to_block().lsb()
+    }
+    /// Decodes a key given the decoding bit of its pair.
+    fn decode_bit(&self, decoding_bit: bool) -> bool {
+        self.to_block().lsb() != decoding_bit
+    }
 }
 
 /// The main trait for a wire. The typical usecasse is to represent a value that can be seen in a
 /// Rust program. Therefore, the wire is rarely single bit.
 pub trait Wire {
     /// The Rust data primitive datatype that can be passed to functions implementing this trait.
-    type ValueType;
+    type ValueType: WireValue;
 
     /// Returins the number of bits represented in this wire.
     fn bits() -> u32;
+
+    /// Returns true if the bits of the wire are a signed integer in two's complement.
+    fn is_signed() -> bool {
+        false
+    }
+
+    /// Compiles an operation on two values of this wire down to a circuit. By default, the bits
+    /// are treated as an integer.
+    fn operation_circuit(operation: Operation) -> Circuit {
+        operation.circuit(Self::bits(), Self::is_signed())
+    }
+
+    /// Compiles a comparison of two values of this wire down to a circuit. By default, the bits
+    /// are treated as an integer.
+    fn comparison_circuit(comparison: Comparison) -> Circuit {
+        comparison.circuit(Self::bits(), Self::is_signed())
+    }
+}
+
+/// Converts a Rust value to and from the bits of a wire, most significant bit first.
+pub trait WireValue: Sized {
+    fn to_bits(&self) -> Vec<bool>;
+    fn from_bits(bits: &[bool]) -> Self;
 }
 
 /// Represents a group of garbled wires defined by the garbling mode and the wire specificacion.
@@ -68,46 +197,272 @@ pub struct GarblingWire<M: GarblingMode, W: Wire> {
 }
 
 /// Represents a group of garbled values.
-#[derive(Clone, Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct EvaluatingWire<M: GarblingMode> {
     pub bits: Vec<M>,
 }
 
-/// Represents a garbled gate.
-#[derive(Clone, Serialize, Deserialize)]
+/// A secret value as held by one party: the garbler knows both keys of every bit and the evaluator
+/// only knows the active ones.
+#[derive(Clone, Debug)]
+pub enum SecretWire<M: GarblingMode, W: Wire> {
+    Garbling(GarblingWire<M, W>),
+    Evaluating(EvaluatingWire<M>),
+}
+
+/// The information that the evaluator needs to decode a garbled value of a wire, i.e., the decoding
+/// bit of every 1-bit wire.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct DecodingInfo<W: Wire> {
+    pub bits: Vec<bool>,
+    wire_info: PhantomData<W>,
+}
+
+/// The garbled tables of the 1-bit gates of a circuit, in the order they are evaluated.
+pub type GarbledTables = Vec<Vec<Block>>;
+
+/// Represents a garbled gate. It only holds what the evaluator needs: the garbled tables of the
+/// 1-bit gates that make up the operation, in the order they are evaluated.
+#[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct Gate<M: GarblingMode, W: Wire> {
-    pub output: GarblingWire<M, W>,
+    /// The index of the first 1-bit gate. The `i`-th table is garbled with the index `id + i`.
+    pub id: u64,
+    /// The garbled tables. Gates that are free to evaluate have empty tables.
+    pub tables: GarbledTables,
+    wire_info: PhantomData<(M, W)>,
+}
+
+/// Stores the garbler's state that has to live for the whole session: the global delta and the
+/// number of 1-bit gates garbled so far. The latter is used as a unique tweak for every gate.
+pub struct GarblingSession {
+    pub delta: Delta,
+    pub gate_count: u64,
+}
+
+/// A garbling mode that can garble and evaluate 1-bit gates. Each garbled gate produces the keys
+/// of its output wire and a garbled table that has to be sent to the evaluator.
+pub trait GateGarbling: GarblingMode + Clone {
+    /// Garbles a 1-bit AND gate with the unique index `id`.
+    fn garble_and<R: RngCore + CryptoRng>(
+        input_1: &(Self, Self),
+        input_2: &(Self, Self),
+        id: u64,
+        delta: &Delta,
+        rng: &mut R,
+    ) -> ((Self, Self), Vec<Block>);
+
+    /// Evaluates a 1-bit AND gate with the unique index `id`.
+    fn evaluate_and(input_1: &Self, input_2: &Self, id: u64, table: &[Block]) -> Self;
+
+    /// Garbles a 1-bit XOR gate with the unique index `id`.
+    fn garble_xor<R: RngCore + CryptoRng>(
+        input_1: &(Self, Self),
+        input_2: &(Self, Self),
+        id: u64,
+        delta: &Delta,
+        rng: &mut R,
+    ) -> ((Self, Self), Vec<Block>);
+
+    /// Evaluates a 1-bit XOR gate with the unique index `id`.
+    fn evaluate_xor(input_1: &Self, input_2: &Self, id: u64, table: &[Block]) -> Self;
+
+    /// Garbles a 1-bit NOT gate. By default, the meaning of the input keys is swapped.
+    fn garble_not(input: &(Self, Self)) -> (Self, Self) {
+        (input.1.clone(), input.0.clone())
+    }
+
+    /// Evaluates a 1-bit NOT gate. By default, the key is left unchanged.
+    fn evaluate_not(input: &Self) -> Self {
+        input.clone()
+    }
+}
+
+/// The 1-bit gates that circuits are built from. It is implemented once for the garbler and once
+/// for the evaluator, so that both sides walk through the exact same sequence of gates.
+trait BitGates {
+    type Bit: Clone;
+    fn and(&mut self, input_1: &Self::Bit, input_2: &Self::Bit) -> Self::Bit;
+    fn xor(&mut self, input_1: &Self::Bit, input_2: &Self::Bit) -> Self::Bit;
+    fn not(&mut self, input: &Self::Bit) -> Self::Bit;
+    /// Returns a bit that always carries `value`. Its key is sent to the evaluator as a table.
+    fn constant(&mut self, value: bool) -> Self::Bit;
+}
+
+/// Garbles 1-bit gates one after the other. The `i`-th garbled table is garbled with the index
+/// `id + i`.
+struct TableGarbler<'a, M, R> {
+    id: u64,
+    delta: Delta,
+    rng: &'a mut R,
+    tables: GarbledTables,
+    mode: PhantomData<M>,
+}
+
+/// Evaluates 1-bit gates in the order they were garbled.
+struct TableEvaluator<'a, M> {
+    id: u64,
+    tables: std::slice::Iter<'a, Vec<Block>>,
+    mode: PhantomData<M>,
 }
 
 // ----------------------------------------------------------------------------------------------
 // -                                    Impl blocks                                             -
 // ----------------------------------------------------------------------------------------------
 
+impl Delta {
+    /// Generates a random delta with its least significant bit set.
+    pub fn new<R: RngCore + CryptoRng>(rng: &mut R) -> Delta {
+        let mut buffer: [u8; 16] = [0; 16];
+        rng.fill_bytes(&mut buffer);
+        Delta(Block::from(buffer).set_lsb())
+    }
+}
+
+impl Operation {
+    /// Compiles the operation on values of `bits` bits down to a boolean circuit with two input
+    /// groups and one output group. The operands are intermediate values, so they are not owned by
+    /// any party.
+    pub fn circuit(&self, bits: u32, signed: bool) -> Circuit {
+        let mut builder = CircuitBuilder::new();
+        let input_1 = builder.input(0, bits);
+        let input_2 = builder.input(0, bits);
+        let output = match self {
+            Operation::Add => builder.add(&input_1, &input_2),
+            Operation::Sub => builder.sub(&input_1, &input_2),
+            Operation::Mul => builder.mul(&input_1, &input_2),
+            Operation::Div => builder.div(&input_1, &input_2, signed),
+            Operation::And => builder.bitwise_and(&input_1, &input_2),
+            Operation::Or => builder.bitwise_or(&input_1, &input_2),
+            Operation::Xor => builder.bitwise_xor(&input_1, &input_2),
+            Operation::Shl => builder.shift_left_secret(&input_1, &input_2),
+            Operation::Shr => builder.shift_right_secret(&input_1, &input_2, signed),
+            Operation::RotateLeft => builder.rotate_left_secret(&input_1, &input_2),
+            Operation::RotateRight => builder.rotate_right_secret(&input_1, &input_2),
+        };
+        builder.output(&output);
+        builder.build()
+    }
+}
+
+impl Comparison {
+    /// Compiles the comparison on values of `bits` bits down to a boolean circuit with two input
+    /// groups and a 1-bit output group.
+    pub fn circuit(&self, bits: u32, signed: bool) -> Circuit {
+        let mut builder = CircuitBuilder::new();
+        let input_1 = builder.input(0, bits);
+        let input_2 = builder.input(0, bits);
+        let output = match self {
+            Comparison::Lt => builder.less_than(&input_1, &input_2, signed),
+            Comparison::Le => builder.less_equal(&input_1, &input_2, signed),
+            Comparison::Gt => builder.greater_than(&input_1, &input_2, signed),
+            Comparison::Ge => builder.greater_equal(&input_1, &input_2, signed),
+            Comparison::Eq => builder.equal(&input_1, &input_2),
+            Comparison::Ne => builder.not_equal(&input_1, &input_2),
+        };
+        builder.output(&[output]);
+        builder.build()
+    }
+}
+
+impl GarblingSession {
+    /// Starts a new session with a fresh random delta.
+    pub fn new<R: RngCore + CryptoRng>(rng: &mut R) -> GarblingSession {
+        GarblingSession {
+            delta: Delta::new(rng),
+            gate_count: 0,
+        }
+    }
+
+    /// Reserves `count` consecutive gate indices and returns the first one.
+    pub fn reserve(&mut self, count: u64) -> u64 {
+        let id = self.gate_count;
+        self.gate_count += count;
+        id
+    }
+}
+
+impl<M: GarblingMode, W: Wire> Gate<M, W> {
+    /// Creates a garbled gate from its garbled tables.
+    pub fn new(id: u64, tables: GarbledTables) -> Gate<M, W> {
+        Gate {
+            id,
+            tables,
+            wire_info: PhantomData,
+        }
+    }
+}
+
+impl<W: Wire> DecodingInfo<W> {
+    pub fn new(bits: Vec<bool>) -> DecodingInfo<W> {
+        DecodingInfo {
+            bits,
+            wire_info: PhantomData,
+        }
+    }
+
+    /// Decodes a garbled value of the wire.
+    pub fn decode<M: GarblingMode>(&self, value: &EvaluatingWire<M>) -> W::ValueType {
+        assert_eq!(value.bits.len(), self.bits.len(), "wrong number of bits");
+        let bits = value
+            .bits
+            .iter()
+            .zip(self.bits.iter())
+            .map(|(bit, decoding_bit)| bit.decode_bit(*decoding_bit))
+            .collect::<Vec<bool>>();
+        W::ValueType::from_bits(&bits)
+    }
+}
+
 impl<M: GarblingMode, W: Wire> GarblingWire<M, W> {
-    /// Generates garbled keys for all the wires and returns the garbled wires.
+    /// Generates garbled keys for all the wires and returns the garbled wires. Modes with Free-XOR
+    /// panic here, because their keys need the session delta of `with_delta`.
     pub fn new<R: RngCore + CryptoRng>(rng: &mut R) -> GarblingWire<M, W> {
         GarblingWire {
             wire_info: PhantomData,
             bits: (0..W::bits())
-                .into_iter()
                 .map(|_| M::pair(rng))
                 .collect::<Vec<(M, M)>>(),
         }
     }
 
-    /// TODO: change the type of value W::ValueType
+    /// Generates garbled keys for all the wires such that every one-key is `delta` away from its
+    /// zero-key.
+    pub fn with_delta<R: RngCore + CryptoRng>(rng: &mut R, delta: &Delta) -> GarblingWire<M, W> {
+        GarblingWire {
+            wire_info: PhantomData,
+            bits: (0..W::bits())
+                .map(|_| M::pair_with_delta(rng, delta))
+                .collect::<Vec<(M, M)>>(),
+        }
+    }
+
+    /// Wraps already generated pairs of keys into a garbled wire.
+    pub fn from_bits(bits: Vec<(M, M)>) -> GarblingWire<M, W> {
+        GarblingWire {
+            wire_info: PhantomData,
+            bits,
+        }
+    }
+
     /// Encodes a value to to garbled value.
-    pub fn encode(self, value: u8) -> EvaluatingWire<M> {
+    pub fn encode(self, value: W::ValueType) -> EvaluatingWire<M> {
+        let bits = value.to_bits();
+        assert_eq!(bits.len(), self.bits.len(), "value does not fit the wire");
         EvaluatingWire {
             bits: self
                 .bits
                 .into_iter()
-                .zip(to_bit_arr(value, W::bits()))
+                .zip(bits)
                 .map(|((zero, one), choice)| if choice { one } else { zero })
                 .collect::<Vec<M>>(),
         }
     }
 
+    /// Returns the information that the evaluator needs to decode a garbled value of this wire.
+    pub fn decoding_info(&self) -> DecodingInfo<W> {
+        DecodingInfo::new(self.bits.iter().map(M::decoding_bit).collect())
+    }
+
     /// Returns the corresponding garbled keys as `Block`s to be used with the `swanky` library.
     pub fn to_blocks(self) -> Vec<(Block, Block)> {
         self.bits
@@ -117,14 +472,774 @@ impl<M: GarblingMode, W: Wire> GarblingWire<M, W> {
     }
 }
 
+impl<M: GarblingMode, W: Wire> SecretWire<M, W> {
+    /// Returns the keys if this party is the garbler.
+    pub fn garbling(self) -> Option<GarblingWire<M, W>> {
+        match self {
+            SecretWire::Garbling(wire) => Some(wire),
+            SecretWire::Evaluating(_) => None,
+        }
+    }
+
+    /// Returns the active keys if this party is the evaluator.
+    pub fn evaluating(self) -> Option<EvaluatingWire<M>> {
+        match self {
+            SecretWire::Garbling(_) => None,
+            SecretWire::Evaluating(wire) => Some(wire),
+        }
+    }
+}
+
+impl<S, R> PartyOt<S, R> {
+    /// Returns the OT sender. Panics if this party is the OT receiver.
+    pub fn sender(&mut self) -> &mut S {
+        match self {
+            PartyOt::Sender(sender) => sender,
+            PartyOt::Receiver(_) => panic!("the evaluator is not an OT sender"),
+        }
+    }
+
+    /// Returns the OT receiver. Panics if this party is the OT sender.
+    pub fn receiver(&mut self) -> &mut R {
+        match self {
+            PartyOt::Sender(_) => panic!("the garbler is not an OT receiver"),
+            PartyOt::Receiver(receiver) => receiver,
+        }
+    }
+}
+
+impl<C: AbstractChannel, R: RngCore + CryptoRng> Protocol<C, R> {
+    /// Starts the protocol for this party and runs the setup of the chosen OT. The other party
+    /// calls it at the same time with the same `ot`.
+    pub fn new(
+        parties: Vec<Party>,
+        me: Party,
+        role: Role,
+        mut channel: C,
+        mut rng: R,
+        ot: OtKind,
+    ) -> Result<Self, ProtocolError> {
+        let ot = PartyOt::new(ot, &role, &mut channel, &mut rng)?;
+        let session = GarblingSession::new(&mut rng);
+        Ok(Protocol {
+            parties,
+            me,
+            role,
+            channel,
+            rng,
+            ot,
+            session,
+        })
+    }
+
+    /// Returns the garbler's executor, which runs gadgets such as ORAMs and permutations on the
+    /// keys of the wires that `assign!` returns. The inputs and reveals of the protocol go through
+    /// it as well.
+    pub fn garbler<M>(&mut self) -> Garbler<'_, M, C, AnyOtSender, R> {
+        Garbler::new(
+            &mut self.channel,
+            self.ot.sender(),
+            &mut self.session,
+            &mut self.rng,
+        )
+    }
+
+    /// Returns the evaluator's executor, the counterpart of `garbler`.
+    pub fn evaluator<M>(&mut self) -> Evaluator<'_, M, C, AnyOtReceiver, R> {
+        Evaluator::new(&mut self.channel, self.ot.receiver(), &mut self.rng)
+    }
+
+    /// Called by the garbler to input its own value. It generates the keys of a new wire and sends
+    /// the active keys of the value. The evaluator calls `evaluator_peer_input` at the same time.
+    pub fn garbler_input<M: GateGarbling, W: Wire>(
+        &mut self,
+        value: W::ValueType,
+    ) -> Result<GarblingWire<M, W>, ProtocolError> {
+        Ok(self.garbler_inputs(&[value])?.remove(0))
+    }
+
+    /// Called by the garbler to input a value of the evaluator. It generates the keys of a new wire
+    /// and sends them with OT. The evaluator calls `evaluator_input` at the same time.
+    pub fn garbler_peer_input<M: GateGarbling, W: Wire>(
+        &mut self,
+    ) -> Result<GarblingWire<M, W>, ProtocolError> {
+        Ok(self.garbler_peer_inputs(1)?.remove(0))
+    }
+
+    /// Called by the evaluator to input its own value. It receives the active keys of the value
+    /// with OT, so the garbler does not learn the value.
+    pub fn evaluator_input<M: GateGarbling, W: Wire>(
+        &mut self,
+        value: W::ValueType,
+    ) -> Result<EvaluatingWire<M>, ProtocolError> {
+        Ok(self.evaluator_inputs::<M, W>(&[value])?.remove(0))
+    }
+
+    /// Called by the evaluator to input a value of the garbler. It receives the active keys that
+    /// the garbler sends in `garbler_input`.
+    pub fn evaluator_peer_input<M: GateGarbling, W: Wire>(
+        &mut self,
+    ) -> Result<EvaluatingWire<M>, ProtocolError> {
+        Ok(self.evaluator_peer_inputs::<M, W>(1)?.remove(0))
+    }
+
+    /// Like `garbler_input`, but inputs several values in a single message.
+    pub fn garbler_inputs<M: GateGarbling, W: Wire>(
+        &mut self,
+        values: &[W::ValueType],
+    ) -> Result<Vec<GarblingWire<M, W>>, ProtocolError> {
+        let bits = value_bits::<W>(values);
+        let keys = self.garbler::<M>().input(&bits)?;
+        Ok(keys
+            .chunks(W::bits() as usize)
+            .map(|bits| GarblingWire::from_bits(bits.to_vec()))
+            .collect())
+    }
+
+    /// Like `garbler_peer_input`, but inputs `count` values of the evaluator with a single batch
+    /// of OTs. With an OT extension, this is much cheaper than one batch per value.
+    pub fn garbler_peer_inputs<M: GateGarbling, W: Wire>(
+        &mut self,
+        count: usize,
+    ) -> Result<Vec<GarblingWire<M, W>>, ProtocolError> {
+        let keys = self.garbler::<M>().peer_input(count * W::bits() as usize)?;
+        Ok(keys
+            .chunks(W::bits() as usize)
+            .map(|bits| GarblingWire::from_bits(bits.to_vec()))
+            .collect())
+    }
+
+    /// Like `evaluator_input`, but inputs several values with a single batch of OTs.
+    pub fn evaluator_inputs<M: GateGarbling, W: Wire>(
+        &mut self,
+        values: &[W::ValueType],
+    ) -> Result<Vec<EvaluatingWire<M>>, ProtocolError> {
+        let bits = value_bits::<W>(values);
+        let keys = self.evaluator::<M>().input(&bits)?;
+        Ok(keys
+            .chunks(W::bits() as usize)
+            .map(|bits| EvaluatingWire {
+                bits: bits.to_vec(),
+            })
+            .collect())
+    }
+
+    /// Like `evaluator_peer_input`, but receives the `count` values sent in one `garbler_inputs`.
+    pub fn evaluator_peer_inputs<M: GateGarbling, W: Wire>(
+        &mut self,
+        count: usize,
+    ) -> Result<Vec<EvaluatingWire<M>>, ProtocolError> {
+        let keys = self
+            .evaluator::<M>()
+            .peer_input(count * W::bits() as usize)?;
+        Ok(keys
+            .chunks(W::bits() as usize)
+            .map(|bits| EvaluatingWire {
+                bits: bits.to_vec(),
+            })
+            .collect())
+    }
+
+    /// Checks that the party `id` is the other party of the protocol, i.e., a party whose inputs
+    /// this party can receive.
+    pub fn input_from(&self, id: usize) -> Result<(), ProtocolError> {
+        if id == self.me.id || !self.parties.iter().any(|party| party.id == id) {
+            return Err(ProtocolError::UnknownParty(id));
+        }
+        Ok(())
+    }
+
+    /// Returns who learns a value that is revealed to the party `id`, which must be part of the
+    /// protocol.
+    pub fn reveal_to(&self, id: usize) -> Result<RevealTo, ProtocolError> {
+        if !self.parties.iter().any(|party| party.id == id) {
+            return Err(ProtocolError::UnknownParty(id));
+        }
+        Ok(match (&self.role, id == self.me.id) {
+            (Role::Garbler, true) | (Role::Evaluator, false) => RevealTo::Garbler,
+            (Role::Garbler, false) | (Role::Evaluator, true) => RevealTo::Evaluator,
+        })
+    }
+
+    /// Reveals a secret value to the chosen parties. Both parties call it at the same time with
+    /// their side of the value. Returns the value if this party learns it.
+    pub fn reveal<M, W>(
+        &mut self,
+        wire: &SecretWire<M, W>,
+        to: RevealTo,
+    ) -> Result<Option<W::ValueType>, ProtocolError>
+    where
+        M: GateGarbling,
+        W: Wire,
+    {
+        let bits = match wire {
+            SecretWire::Garbling(wire) => self.garbler::<M>().reveal_to(&wire.bits, to)?,
+            SecretWire::Evaluating(wire) => self.evaluator::<M>().reveal_to(&wire.bits, to)?,
+        };
+        Ok(bits.map(|bits| W::ValueType::from_bits(&bits)))
+    }
+}
+
+impl<'a, M: GateGarbling, R: RngCore + CryptoRng> TableGarbler<'a, M, R> {
+    fn new(id: u64, delta: Delta, rng: &'a mut R) -> TableGarbler<'a, M, R> {
+        TableGarbler {
+            id,
+            delta,
+            rng,
+            tables: vec![],
+            mode: PhantomData,
+        }
+    }
+
+    /// Returns the index of the next garbled table.
+    fn next_id(&self) -> u64 {
+        self.id + self.tables.len() as u64
+    }
+}
+
+impl<'a, M: GateGarbling, R: RngCore + CryptoRng> BitGates for TableGarbler<'a, M, R> {
+    type Bit = (M, M);
+
+    fn and(&mut self, input_1: &Self::Bit, input_2: &Self::Bit) -> Self::Bit {
+        let (output, table) =
+            M::garble_and(input_1, input_2, self.next_id(), &self.delta, self.rng);
+        self.tables.push(table);
+        output
+    }
+
+    fn xor(&mut self, input_1: &Self::Bit, input_2: &Self::Bit) -> Self::Bit {
+        let (output, table) =
+            M::garble_xor(input_1, input_2, self.next_id(), &self.delta, self.rng);
+        self.tables.push(table);
+        output
+    }
+
+    fn not(&mut self, input: &Self::Bit) -> Self::Bit {
+        M::garble_not(input)
+    }
+
+    fn constant(&mut self, value: bool) -> Self::Bit {
+        let pair = M::pair_with_delta(self.rng, &self.delta);
+        let active = if value { &pair.1 } else { &pair.0 };
+        self.tables.push(vec![active.to_block()]);
+        pair
+    }
+}
+
+impl<'a, M: GateGarbling> TableEvaluator<'a, M> {
+    fn new(id: u64, tables: &'a [Vec<Block>]) -> TableEvaluator<'a, M> {
+        TableEvaluator {
+            id,
+            tables: tables.iter(),
+            mode: PhantomData,
+        }
+    }
+
+    /// Returns the index and the garbled table of the next gate.
+    fn next_table(&mut self) -> (u64, &'a [Block]) {
+        let id = self.id;
+        self.id += 1;
+        (id, self.tables.next().expect("not enough garbled tables"))
+    }
+}
+
+impl<'a, M: GateGarbling> BitGates for TableEvaluator<'a, M> {
+    type Bit = M;
+
+    fn and(&mut self, input_1: &M, input_2: &M) -> M {
+        let (id, table) = self.next_table();
+        M::evaluate_and(input_1, input_2, id, table)
+    }
+
+    fn xor(&mut self, input_1: &M, input_2: &M) -> M {
+        let (id, table) = self.next_table();
+        M::evaluate_xor(input_1, input_2, id, table)
+    }
+
+    fn not(&mut self, input: &M) -> M {
+        M::evaluate_not(input)
+    }
+
+    fn constant(&mut self, _value: bool) -> M {
+        let (_, table) = self.next_table();
+        M::from_block(table[0])
+    }
+}
+
+// ----------------------------------------------------------------------------------------------
+// -                              Garble and Evaluate Circuits                                  -
+// ----------------------------------------------------------------------------------------------
+
+/// Garbles a circuit given the keys of its input groups. Returns the keys of the output groups,
+/// the index of the first garbled table, and the garbled tables that the evaluator needs.
+pub fn garble_circuit<M: GateGarbling, R: RngCore + CryptoRng>(
+    circuit: &Circuit,
+    inputs: Vec<Vec<(M, M)>>,
+    session: &mut GarblingSession,
+    rng: &mut R,
+) -> (Vec<Vec<(M, M)>>, u64, GarbledTables) {
+    let first_id = session.reserve(circuit.table_count() as u64);
+    let mut garbler = TableGarbler::new(first_id, session.delta, rng);
+    let outputs = run_circuit(&mut garbler, circuit, inputs);
+    (outputs, first_id, garbler.tables)
+}
+
+/// Evaluates a garbled circuit given the keys of its input groups and the garbled tables. Returns
+/// the keys of the output groups.
+pub fn evaluate_circuit<M: GateGarbling>(
+    circuit: &Circuit,
+    inputs: Vec<Vec<M>>,
+    first_id: u64,
+    tables: &[Vec<Block>],
+) -> Vec<Vec<M>> {
+    let mut evaluator = TableEvaluator::new(first_id, tables);
+    run_circuit(&mut evaluator, circuit, inputs)
+}
+
+/// Garbles an operation on two wires of any width.
+pub fn garble_gate<M: GateGarbling, W: Wire, R: RngCore + CryptoRng>(
+    input_1: GarblingWire<M, W>,
+    input_2: GarblingWire<M, W>,
+    operation: Operation,
+    session: &mut GarblingSession,
+    rng: &mut R,
+) -> (GarblingWire<M, W>, Vec<Gate<M, W>>) {
+    let (mut outputs, id, tables) = garble_circuit(
+        &W::operation_circuit(operation),
+        vec![input_1.bits, input_2.bits],
+        session,
+        rng,
+    );
+    (
+        GarblingWire::from_bits(outputs.remove(0)),
+        vec![Gate::new(id, tables)],
+    )
+}
+
+/// Evaluates an operation on two wires using the garbled gates sent by the garbler.
+pub fn evaluate_gate<M: GateGarbling, W: Wire>(
+    input_1: EvaluatingWire<M>,
+    input_2: EvaluatingWire<M>,
+    operation: Operation,
+    gates: Vec<Gate<M, W>>,
+) -> EvaluatingWire<M> {
+    let mut outputs = evaluate_circuit(
+        &W::operation_circuit(operation),
+        vec![input_1.bits, input_2.bits],
+        gates[0].id,
+        &gates[0].tables,
+    );
+    EvaluatingWire {
+        bits: outputs.remove(0),
+    }
+}
+
+/// Garbles a comparison of two wires of any width. The output is a 1-bit wire.
+pub fn garble_comparison<M: GateGarbling, W: Wire, R: RngCore + CryptoRng>(
+    input_1: GarblingWire<M, W>,
+    input_2: GarblingWire<M, W>,
+    comparison: Comparison,
+    session: &mut GarblingSession,
+    rng: &mut R,
+) -> (GarblingWire<M, WireBool>, Vec<Gate<M, W>>) {
+    let (mut outputs, id, tables) = garble_circuit(
+        &W::comparison_circuit(comparison),
+        vec![input_1.bits, input_2.bits],
+        session,
+        rng,
+    );
+    (
+        GarblingWire::from_bits(outputs.remove(0)),
+        vec![Gate::new(id, tables)],
+    )
+}
+
+/// Evaluates a comparison of two wires using the garbled gates sent by the garbler.
+pub fn evaluate_comparison<M: GateGarbling, W: Wire>(
+    input_1: EvaluatingWire<M>,
+    input_2: EvaluatingWire<M>,
+    comparison: Comparison,
+    gates: Vec<Gate<M, W>>,
+) -> EvaluatingWire<M> {
+    let mut outputs = evaluate_circuit(
+        &W::comparison_circuit(comparison),
+        vec![input_1.bits, input_2.bits],
+        gates[0].id,
+        &gates[0].tables,
+    );
+    EvaluatingWire {
+        bits: outputs.remove(0),
+    }
+}
+
+/// Garbles a mux that returns `if_true` if the secret `condition` is set, otherwise `if_false`.
+pub fn garble_mux<M: GateGarbling, W: Wire, R: RngCore + CryptoRng>(
+    condition: GarblingWire<M, WireBool>,
+    if_true: GarblingWire<M, W>,
+    if_false: GarblingWire<M, W>,
+    session: &mut GarblingSession,
+    rng: &mut R,
+) -> (GarblingWire<M, W>, Vec<Gate<M, W>>) {
+    let (mut outputs, id, tables) = garble_circuit(
+        &mux_circuit(W::bits()),
+        vec![condition.bits, if_true.bits, if_false.bits],
+        session,
+        rng,
+    );
+    (
+        GarblingWire::from_bits(outputs.remove(0)),
+        vec![Gate::new(id, tables)],
+    )
+}
+
+/// Evaluates a mux using the garbled gates sent by the garbler.
+pub fn evaluate_mux<M: GateGarbling, W: Wire>(
+    condition: EvaluatingWire<M>,
+    if_true: EvaluatingWire<M>,
+    if_false: EvaluatingWire<M>,
+    gates: Vec<Gate<M, W>>,
+) -> EvaluatingWire<M> {
+    let mut outputs = evaluate_circuit(
+        &mux_circuit(W::bits()),
+        vec![condition.bits, if_true.bits, if_false.bits],
+        gates[0].id,
+        &gates[0].tables,
+    );
+    EvaluatingWire {
+        bits: outputs.remove(0),
+    }
+}
+
 // ----------------------------------------------------------------------------------------------
 // -                                 Utility Functions                                          -
 // ----------------------------------------------------------------------------------------------
 
-pub(crate) fn to_bit_arr(value: u8, len: u32) -> Vec<bool> {
-    let mask = 2u8.pow(len - 1);
-    (0..len)
-        .into_iter()
-        .map(|index| (value & (mask >> index)) != 0)
-        .collect::<Vec<bool>>()
+/// Returns the bits of the values, which must all fit a wire of type `W`.
+fn value_bits<W: Wire>(values: &[W::ValueType]) -> Vec<bool> {
+    let bits = values
+        .iter()
+        .flat_map(WireValue::to_bits)
+        .collect::<Vec<bool>>();
+    assert_eq!(
+        bits.len(),
+        values.len() * W::bits() as usize,
+        "value does not fit the wire"
+    );
+    bits
+}
+
+/// Compiles a mux on values of `bits` bits. The first input group is the 1-bit condition.
+fn mux_circuit(bits: u32) -> Circuit {
+    let mut builder = CircuitBuilder::new();
+    let condition = builder.input(0, 1);
+    let if_true = builder.input(0, bits);
+    let if_false = builder.input(0, bits);
+    let output = builder.mux(condition[0], &if_true, &if_false);
+    builder.output(&output);
+    builder.build()
+}
+
+/// Walks through the gates of a circuit given the keys of its input groups, and returns the keys
+/// of its output groups.
+fn run_circuit<G: BitGates>(
+    gates: &mut G,
+    circuit: &Circuit,
+    inputs: Vec<Vec<G::Bit>>,
+) -> Vec<Vec<G::Bit>> {
+    assert_eq!(inputs.len(), circuit.inputs.len(), "wrong number of inputs");
+    let mut keys: Vec<Option<G::Bit>> = vec![None; circuit.wire_count];
+    for (input, bits) in circuit.inputs.iter().zip(inputs) {
+        assert_eq!(input.wires.len(), bits.len(), "wrong input length");
+        for (wire, bit) in input.wires.iter().zip(bits) {
+            keys[*wire] = Some(bit);
+        }
+    }
+
+    for gate in circuit.gates.iter() {
+        let key = |wire: WireId| keys[wire].as_ref().expect("gate reads an unset wire");
+        let output = match *gate {
+            BooleanGate::And(input_1, input_2, _) => gates.and(key(input_1), key(input_2)),
+            BooleanGate::Xor(input_1, input_2, _) => gates.xor(key(input_1), key(input_2)),
+            BooleanGate::Inv(input, _) => gates.not(key(input)),
+            BooleanGate::Const(value, _) => gates.constant(value),
+        };
+        keys[gate.output()] = Some(output);
+    }
+
+    circuit
+        .outputs
+        .iter()
+        .map(|output| {
+            output
+                .iter()
+                .map(|wire| keys[*wire].clone().expect("output is an unset wire"))
+                .collect()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        free_xor_garbling::FreeXorBit,
+        plain_garbling::PlainBit,
+        simple_garbling::GarbledBit,
+        wires::{Wire16Bit, Wire64Bit, Wire8Bit, WireSigned16Bit, WireSigned32Bit},
+    };
+    use rand::{
+        distributions::{Distribution, Standard},
+        rngs::StdRng,
+        Rng, SeedableRng,
+    };
+    use std::fmt::Debug;
+    const SEED: [u8; 32] = [42u8; 32];
+
+    /// Decodes a garbled value by comparing every key with the one-key.
+    fn decode<M: GarblingMode + PartialEq, W: Wire>(
+        wire: &GarblingWire<M, W>,
+        value: &EvaluatingWire<M>,
+    ) -> W::ValueType {
+        let bits = wire
+            .bits
+            .iter()
+            .zip(value.bits.iter())
+            .map(|((_, one), bit)| bit == one)
+            .collect::<Vec<bool>>();
+        W::ValueType::from_bits(&bits)
+    }
+
+    type NativeOperation<T> = fn(T, T) -> T;
+
+    fn check_operations<M: GateGarbling + PartialEq, W: Wire + Clone>(
+        add: NativeOperation<W::ValueType>,
+        sub: NativeOperation<W::ValueType>,
+        mul: NativeOperation<W::ValueType>,
+        div: NativeOperation<W::ValueType>,
+    ) where
+        W::ValueType: Copy + Debug + PartialEq,
+        Standard: Distribution<W::ValueType>,
+    {
+        let mut rng = StdRng::from_seed(SEED);
+        let mut session = GarblingSession::new(&mut rng);
+        for _ in 0..10 {
+            let value_1: W::ValueType = rng.gen();
+            let value_2: W::ValueType = rng.gen();
+            for &(operation, native) in [
+                (Operation::Add, add),
+                (Operation::Sub, sub),
+                (Operation::Mul, mul),
+                (Operation::Div, div),
+            ]
+            .iter()
+            {
+                let wires_1 = GarblingWire::<M, W>::with_delta(&mut rng, &session.delta);
+                let wires_2 = GarblingWire::<M, W>::with_delta(&mut rng, &session.delta);
+                let garbled_value_1 = wires_1.clone().encode(value_1);
+                let garbled_value_2 = wires_2.clone().encode(value_2);
+
+                let (output, gates) =
+                    garble_gate(wires_1, wires_2, operation, &mut session, &mut rng);
+                let result = evaluate_gate(garbled_value_1, garbled_value_2, operation, gates);
+                let want = native(value_1, value_2);
+                assert_eq!(decode(&output, &result), want, "{:?}", operation);
+                assert_eq!(output.decoding_info().decode(&result), want);
+            }
+        }
+    }
+
+    fn check_operations_on_every_width<M: GateGarbling + PartialEq>() {
+        // Dividing by zero sets every bit of the quotient.
+        check_operations::<M, Wire8Bit>(
+            u8::wrapping_add,
+            u8::wrapping_sub,
+            u8::wrapping_mul,
+            |a, b| a.checked_div(b).unwrap_or(u8::MAX),
+        );
+        check_operations::<M, Wire16Bit>(
+            u16::wrapping_add,
+            u16::wrapping_sub,
+            u16::wrapping_mul,
+            |a, b| a.checked_div(b).unwrap_or(u16::MAX),
+        );
+        check_operations::<M, WireSigned32Bit>(
+            i32::wrapping_add,
+            i32::wrapping_sub,
+            i32::wrapping_mul,
+            |a, b| if b == 0 { -1 } else { a.wrapping_div(b) },
+        );
+        check_operations::<M, Wire64Bit>(
+            u64::wrapping_add,
+            u64::wrapping_sub,
+            u64::wrapping_mul,
+            |a, b| a.checked_div(b).unwrap_or(u64::MAX),
+        );
+    }
+
+    #[test]
+    fn test_operations_in_every_mode() {
+        check_operations_on_every_width::<PlainBit>();
+        check_operations_on_every_width::<GarbledBit>();
+        check_operations_on_every_width::<FreeXorBit>();
+    }
+
+    fn check_comparisons<W: Wire + Clone>()
+    where
+        W::ValueType: Copy + Debug + PartialOrd,
+        Standard: Distribution<W::ValueType>,
+    {
+        let mut rng = StdRng::from_seed(SEED);
+        let mut session = GarblingSession::new(&mut rng);
+        for _ in 0..10 {
+            let value_1: W::ValueType = rng.gen();
+            let value_2: W::ValueType = if rng.gen::<bool>() {
+                rng.gen()
+            } else {
+                value_1
+            };
+            for &(comparison, want) in [
+                (Comparison::Lt, value_1 < value_2),
+                (Comparison::Le, value_1 <= value_2),
+                (Comparison::Gt, value_1 > value_2),
+                (Comparison::Ge, value_1 >= value_2),
+                (Comparison::Eq, value_1 == value_2),
+                (Comparison::Ne, value_1 != value_2),
+            ]
+            .iter()
+            {
+                let wires_1 = GarblingWire::<FreeXorBit, W>::with_delta(&mut rng, &session.delta);
+                let wires_2 = GarblingWire::<FreeXorBit, W>::with_delta(&mut rng, &session.delta);
+                let garbled_value_1 = wires_1.clone().encode(value_1);
+                let garbled_value_2 = wires_2.clone().encode(value_2);
+
+                let (output, gates) =
+                    garble_comparison(wires_1, wires_2, comparison, &mut session, &mut rng);
+                let result =
+                    evaluate_comparison(garbled_value_1, garbled_value_2, comparison, gates);
+                assert_eq!(
+                    output.decoding_info().decode(&result),
+                    want,
+                    "{:?}",
+                    comparison
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_bitwise_operations() {
+        let mut rng = StdRng::from_seed(SEED);
+        let mut session = GarblingSession::new(&mut rng);
+        let shift = |value: i16, amount: i16, native: fn(i16, u32) -> Option<i16>, fill: i16| {
+            native(value, amount as u32).unwrap_or(fill)
+        };
+        for _ in 0..10 {
+            let value_1: i16 = rng.gen();
+            let value_2: i16 = rng.gen_range(0, 20);
+            let sign = if value_1 < 0 { -1 } else { 0 };
+            for &(operation, want) in [
+                (Operation::And, value_1 & value_2),
+                (Operation::Or, value_1 | value_2),
+                (Operation::Xor, value_1 ^ value_2),
+                (Operation::Shl, shift(value_1, value_2, i16::checked_shl, 0)),
+                (
+                    Operation::Shr,
+                    shift(value_1, value_2, i16::checked_shr, sign),
+                ),
+                (Operation::RotateLeft, value_1.rotate_left(value_2 as u32)),
+                (Operation::RotateRight, value_1.rotate_right(value_2 as u32)),
+            ]
+            .iter()
+            {
+                let wires_1 = GarblingWire::<FreeXorBit, WireSigned16Bit>::with_delta(
+                    &mut rng,
+                    &session.delta,
+                );
+                let wires_2 = GarblingWire::<FreeXorBit, WireSigned16Bit>::with_delta(
+                    &mut rng,
+                    &session.delta,
+                );
+                let garbled_value_1 = wires_1.clone().encode(value_1);
+                let garbled_value_2 = wires_2.clone().encode(value_2);
+
+                let (output, gates) =
+                    garble_gate(wires_1, wires_2, operation, &mut session, &mut rng);
+                let result = evaluate_gate(garbled_value_1, garbled_value_2, operation, gates);
+                assert_eq!(
+                    output.decoding_info().decode(&result),
+                    want,
+                    "{:?}",
+                    operation
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_comparisons() {
+        check_comparisons::<Wire8Bit>();
+        check_comparisons::<WireSigned32Bit>();
+        check_comparisons::<Wire64Bit>();
+    }
+
+    #[test]
+    fn test_mux() {
+        let mut rng = StdRng::from_seed(SEED);
+        let mut session = GarblingSession::new(&mut rng);
+        for &pick in [false, true].iter() {
+            let condition =
+                GarblingWire::<FreeXorBit, WireBool>::with_delta(&mut rng, &session.delta);
+            let if_true =
+                GarblingWire::<FreeXorBit, Wire16Bit>::with_delta(&mut rng, &session.delta);
+            let if_false =
+                GarblingWire::<FreeXorBit, Wire16Bit>::with_delta(&mut rng, &session.delta);
+            let garbled_condition = condition.clone().encode(pick);
+            let garbled_true = if_true.clone().encode(1234);
+            let garbled_false = if_false.clone().encode(4321);
+
+            let (output, gates) = garble_mux(condition, if_true, if_false, &mut session, &mut rng);
+            let result = evaluate_mux(garbled_condition, garbled_true, garbled_false, gates);
+            let want = if pick { 1234 } else { 4321 };
+            assert_eq!(output.decoding_info().decode(&result), want);
+        }
+    }
+
+    #[test]
+    fn test_add_u8_is_garbled() {
+        let mut rng = StdRng::from_seed(SEED);
+        let mut session = GarblingSession::new(&mut rng);
+        let wires_1 = GarblingWire::<FreeXorBit, Wire8Bit>::with_delta(&mut rng, &session.delta);
+        let wires_2 = GarblingWire::<FreeXorBit, Wire8Bit>::with_delta(&mut rng, &session.delta);
+        let (_, gates) = garble_gate(wires_1, wires_2, Operation::Add, &mut session, &mut rng);
+        // The 7 carries of the ripple-carry adder are AND gates with two ciphertexts each.
+        assert_eq!(
+            gates[0]
+                .tables
+                .iter()
+                .filter(|table| table.len() == 2)
+                .count(),
+            7
+        );
+    }
+
+    #[test]
+    fn test_circuit_with_constants() {
+        let mut rng = StdRng::from_seed(SEED);
+        let mut session = GarblingSession::new(&mut rng);
+        let mut builder = CircuitBuilder::new();
+        let input = builder.input(1, 1)[0];
+        let one = builder.constant(true);
+        let zero = builder.constant(false);
+        let not_input = builder.inv(input);
+        builder.output(&[one, zero, not_input]);
+        let circuit = builder.build();
+
+        let pair = FreeXorBit::pair_with_delta(&mut rng, &session.delta);
+        let (outputs, id, tables) =
+            garble_circuit(&circuit, vec![vec![pair.clone()]], &mut session, &mut rng);
+        let result = evaluate_circuit(&circuit, vec![vec![pair.0]], id, &tables);
+        assert_eq!(result[0][0], outputs[0][0].1);
+        assert_eq!(result[0][1], outputs[0][1].0);
+        assert_eq!(result[0][2], outputs[0][2].1);
+    }
 }
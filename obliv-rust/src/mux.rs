@@ -0,0 +1,59 @@
+//! Implements the multiplexer gadget on top of `CircuitBuilder`. It is the building block of
+//! oblivious conditionals: both branches are computed and the mux keeps the result of the branch
+//! that the secret condition picks.
+
+use crate::circuit::{CircuitBuilder, WireId};
+
+impl CircuitBuilder {
+    /// Returns `if_true` if `condition` is set, otherwise `if_false`. It costs one AND gate per bit:
+    /// `if_false ^ (condition & (if_true ^ if_false))`.
+    pub fn mux(
+        &mut self,
+        condition: WireId,
+        if_true: &[WireId],
+        if_false: &[WireId],
+    ) -> Vec<WireId> {
+        assert_eq!(
+            if_true.len(),
+            if_false.len(),
+            "operands must have the same width"
+        );
+        if_true
+            .iter()
+            .zip(if_false)
+            .map(|(bit_true, bit_false)| {
+                let difference = self.xor(*bit_true, *bit_false);
+                let masked = self.and(condition, difference);
+                self.xor(*bit_false, masked)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{circuit::CircuitBuilder, mpc_core::WireValue};
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+    const SEED: [u8; 32] = [42u8; 32];
+
+    #[test]
+    fn test_mux() {
+        let mut rng = StdRng::from_seed(SEED);
+        let mut builder = CircuitBuilder::new();
+        let condition = builder.input(1, 1);
+        let a = builder.input(1, 32);
+        let b = builder.input(2, 32);
+        let output = builder.mux(condition[0], &a, &b);
+        builder.output(&output);
+        let circuit = builder.build();
+        assert_eq!(circuit.and_count(), 32);
+
+        for _ in 0..20 {
+            let pick: bool = rng.gen();
+            let x: u32 = rng.gen();
+            let y: u32 = rng.gen();
+            let got = circuit.compute(&[pick.to_bits(), x.to_bits(), y.to_bits()]);
+            assert_eq!(u32::from_bits(&got[0]), if pick { x } else { y });
+        }
+    }
+}
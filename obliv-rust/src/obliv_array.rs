@@ -0,0 +1,593 @@
+//! Implements oblivious arrays, i.e., arrays of secret elements with a public length that can be
+//! read and written at secret indices. Every access scans the whole array: each element goes
+//! through a mux, so neither party learns which element was accessed.
+
+use crate::{
+    circuit::{Circuit, CircuitBuilder, WireId},
+    executor::{Evaluator, Executor, Garbler, ProtocolError},
+    mpc_core::{
+        evaluate_circuit, garble_circuit, EvaluatingWire, GarblingMode, GarblingSession,
+        GarblingWire, Gate, GateGarbling, Wire,
+    },
+};
+use rand_core::{CryptoRng, RngCore};
+use serde::{Deserialize, Serialize};
+
+// ----------------------------------------------------------------------------------------------
+// -                                  Type Definitions                                          -
+// ----------------------------------------------------------------------------------------------
+
+/// An oblivious array on the garbler's side. An index that is out of bounds reads zero and writes
+/// nothing.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct GarblingArray<M: GarblingMode, W: Wire> {
+    pub elements: Vec<GarblingWire<M, W>>,
+}
+
+/// An oblivious array on the evaluator's side.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct EvaluatingArray<M: GarblingMode> {
+    pub elements: Vec<EvaluatingWire<M>>,
+}
+
+/// The interface shared by the oblivious memories: a public length, and reads and writes at secret
+/// indices that both parties run in lockstep through their `Executor`. Values and indices are the
+/// keys of their wires, and the index may have any width. An index that is out of bounds reads
+/// zero and writes nothing.
+pub trait ObliviousMemory<E: Executor> {
+    /// Returns the public length of the memory.
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Reads the element at a secret index.
+    fn read(&mut self, index: &[E::Key], executor: &mut E) -> Result<Vec<E::Key>, ProtocolError>;
+
+    /// Writes a value at a secret index.
+    fn write(
+        &mut self,
+        index: &[E::Key],
+        value: &[E::Key],
+        executor: &mut E,
+    ) -> Result<(), ProtocolError>;
+}
+
+// ----------------------------------------------------------------------------------------------
+// -                                     Gadgets                                                -
+// ----------------------------------------------------------------------------------------------
+
+impl CircuitBuilder {
+    /// Returns one wire per position of an array of `length` elements. The wire of a position is
+    /// set if the secret `index` equals the position. The positions are decoded one index bit at a
+    /// time, which costs about two AND gates per position.
+    pub fn decode_index(&mut self, index: &[WireId], length: usize) -> Vec<WireId> {
+        let mut decoded = vec![self.constant(true)];
+        for (level, bit) in index.iter().enumerate() {
+            // Only the prefixes of positions below `length` are needed.
+            let remaining = index.len() - level - 1;
+            let needed = if remaining < usize::BITS as usize {
+                (length + (1 << remaining) - 1) >> remaining
+            } else {
+                1
+            };
+            let not_bit = self.inv(*bit);
+            let mut next = Vec::with_capacity(2 * decoded.len());
+            for prefix in decoded.iter().take(needed) {
+                next.push(self.and(*prefix, not_bit));
+                next.push(self.and(*prefix, *bit));
+            }
+            next.truncate(needed);
+            decoded = next;
+        }
+        let zero = self.constant(false);
+        decoded.resize(length, zero);
+        decoded
+    }
+
+    /// Reads the element at a secret index. It returns zero if the index is out of bounds.
+    pub fn array_read(&mut self, elements: &[Vec<WireId>], index: &[WireId]) -> Vec<WireId> {
+        let selected = self.decode_index(index, elements.len());
+        let width = elements.first().map_or(0, |element| element.len());
+        let zero = self.constant(false);
+        let mut output = vec![zero; width];
+        for (element, select) in elements.iter().zip(selected) {
+            // At most one position is selected, so the masked elements can be XOR-ed together.
+            for (bit, output) in element.iter().zip(output.iter_mut()) {
+                let masked = self.and(*bit, select);
+                *output = self.xor(*output, masked);
+            }
+        }
+        output
+    }
+
+    /// Writes a value at a secret index and returns the updated elements. Nothing changes if the
+    /// index is out of bounds.
+    pub fn array_write(
+        &mut self,
+        elements: &[Vec<WireId>],
+        index: &[WireId],
+        value: &[WireId],
+    ) -> Vec<Vec<WireId>> {
+        let selected = self.decode_index(index, elements.len());
+        elements
+            .iter()
+            .zip(selected)
+            .map(|(element, select)| self.mux(select, value, element))
+            .collect()
+    }
+}
+
+// ----------------------------------------------------------------------------------------------
+// -                                    Impl blocks                                             -
+// ----------------------------------------------------------------------------------------------
+
+impl<M: GateGarbling, W: Wire> GarblingArray<M, W> {
+    pub fn new(elements: Vec<GarblingWire<M, W>>) -> GarblingArray<M, W> {
+        GarblingArray { elements }
+    }
+
+    /// Returns the public length of the array.
+    pub fn len(&self) -> usize {
+        self.elements.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.elements.is_empty()
+    }
+
+    /// Garbles a read at a secret index, which may be of any wire type.
+    pub fn garble_read<I: Wire, R: RngCore + CryptoRng>(
+        &self,
+        index: GarblingWire<M, I>,
+        session: &mut GarblingSession,
+        rng: &mut R,
+    ) -> (GarblingWire<M, W>, Vec<Gate<M, W>>) {
+        let circuit = read_circuit(self.len(), W::bits(), I::bits());
+        let mut inputs = vec![index.bits];
+        inputs.extend(self.elements.iter().map(|element| element.bits.clone()));
+        let (mut outputs, id, tables) = garble_circuit(&circuit, inputs, session, rng);
+        (
+            GarblingWire::from_bits(outputs.remove(0)),
+            vec![Gate::new(id, tables)],
+        )
+    }
+
+    /// Garbles a write of a value at a secret index, which may be of any wire type.
+    pub fn garble_write<I: Wire, R: RngCore + CryptoRng>(
+        &mut self,
+        index: GarblingWire<M, I>,
+        value: GarblingWire<M, W>,
+        session: &mut GarblingSession,
+        rng: &mut R,
+    ) -> Vec<Gate<M, W>> {
+        let circuit = write_circuit(self.len(), W::bits(), I::bits());
+        let mut inputs = vec![index.bits, value.bits];
+        inputs.extend(self.elements.drain(..).map(|element| element.bits));
+        let (outputs, id, tables) = garble_circuit(&circuit, inputs, session, rng);
+        self.elements = outputs.into_iter().map(GarblingWire::from_bits).collect();
+        vec![Gate::new(id, tables)]
+    }
+}
+
+impl<M: GateGarbling> EvaluatingArray<M> {
+    pub fn new(elements: Vec<EvaluatingWire<M>>) -> EvaluatingArray<M> {
+        EvaluatingArray { elements }
+    }
+
+    /// Returns the public length of the array.
+    pub fn len(&self) -> usize {
+        self.elements.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.elements.is_empty()
+    }
+
+    /// Evaluates a read at a secret index using the garbled gates sent by the garbler.
+    pub fn evaluate_read<W: Wire>(
+        &self,
+        index: EvaluatingWire<M>,
+        gates: Vec<Gate<M, W>>,
+    ) -> EvaluatingWire<M> {
+        let circuit = read_circuit(self.len(), W::bits(), index.bits.len() as u32);
+        let mut inputs = vec![index.bits];
+        inputs.extend(self.elements.iter().map(|element| element.bits.clone()));
+        let mut outputs = evaluate_circuit(&circuit, inputs, gates[0].id, &gates[0].tables);
+        EvaluatingWire {
+            bits: outputs.remove(0),
+        }
+    }
+
+    /// Evaluates a write at a secret index using the garbled gates sent by the garbler.
+    pub fn evaluate_write<W: Wire>(
+        &mut self,
+        index: EvaluatingWire<M>,
+        value: EvaluatingWire<M>,
+        gates: Vec<Gate<M, W>>,
+    ) {
+        let circuit = write_circuit(self.len(), W::bits(), index.bits.len() as u32);
+        let mut inputs = vec![index.bits, value.bits];
+        inputs.extend(self.elements.drain(..).map(|element| element.bits));
+        let outputs = evaluate_circuit(&circuit, inputs, gates[0].id, &gates[0].tables);
+        self.elements = outputs
+            .into_iter()
+            .map(|bits| EvaluatingWire { bits })
+            .collect();
+    }
+}
+
+impl<'a, M, W, C, OT, R> ObliviousMemory<Garbler<'a, M, C, OT, R>> for GarblingArray<M, W>
+where
+    M: GateGarbling,
+    W: Wire,
+    Garbler<'a, M, C, OT, R>: Executor<Key = (M, M)>,
+{
+    fn len(&self) -> usize {
+        self.elements.len()
+    }
+
+    fn read(
+        &mut self,
+        index: &[(M, M)],
+        garbler: &mut Garbler<'a, M, C, OT, R>,
+    ) -> Result<Vec<(M, M)>, ProtocolError> {
+        let elements = self.elements.iter().map(|element| element.bits.clone());
+        read_elements(elements.collect(), index, garbler)
+    }
+
+    fn write(
+        &mut self,
+        index: &[(M, M)],
+        value: &[(M, M)],
+        garbler: &mut Garbler<'a, M, C, OT, R>,
+    ) -> Result<(), ProtocolError> {
+        let elements = self.elements.drain(..).map(|element| element.bits);
+        let elements = write_elements(elements.collect(), index, value, garbler)?;
+        self.elements = elements.into_iter().map(GarblingWire::from_bits).collect();
+        Ok(())
+    }
+}
+
+impl<'a, M, C, OT, R> ObliviousMemory<Evaluator<'a, M, C, OT, R>> for EvaluatingArray<M>
+where
+    M: GateGarbling,
+    Evaluator<'a, M, C, OT, R>: Executor<Key = M>,
+{
+    fn len(&self) -> usize {
+        self.elements.len()
+    }
+
+    fn read(
+        &mut self,
+        index: &[M],
+        evaluator: &mut Evaluator<'a, M, C, OT, R>,
+    ) -> Result<Vec<M>, ProtocolError> {
+        let elements = self.elements.iter().map(|element| element.bits.clone());
+        read_elements(elements.collect(), index, evaluator)
+    }
+
+    fn write(
+        &mut self,
+        index: &[M],
+        value: &[M],
+        evaluator: &mut Evaluator<'a, M, C, OT, R>,
+    ) -> Result<(), ProtocolError> {
+        let elements = self.elements.drain(..).map(|element| element.bits);
+        let elements = write_elements(elements.collect(), index, value, evaluator)?;
+        self.elements = elements
+            .into_iter()
+            .map(|bits| EvaluatingWire { bits })
+            .collect();
+        Ok(())
+    }
+}
+
+// ----------------------------------------------------------------------------------------------
+// -                                 Utility Functions                                          -
+// ----------------------------------------------------------------------------------------------
+
+/// Reads the element at a secret index with a linear scan.
+pub(crate) fn read_elements<E: Executor>(
+    elements: Vec<Vec<E::Key>>,
+    index: &[E::Key],
+    executor: &mut E,
+) -> Result<Vec<E::Key>, ProtocolError> {
+    let bits = elements.first().map_or(0, |element| element.len());
+    let circuit = read_circuit(elements.len(), bits as u32, index.len() as u32);
+    let mut inputs = vec![index.to_vec()];
+    inputs.extend(elements);
+    Ok(executor.execute(&circuit, inputs)?.remove(0))
+}
+
+/// Writes a value at a secret index with a linear scan and returns the updated elements.
+pub(crate) fn write_elements<E: Executor>(
+    elements: Vec<Vec<E::Key>>,
+    index: &[E::Key],
+    value: &[E::Key],
+    executor: &mut E,
+) -> Result<Vec<Vec<E::Key>>, ProtocolError> {
+    let circuit = write_circuit(elements.len(), value.len() as u32, index.len() as u32);
+    let mut inputs = vec![index.to_vec(), value.to_vec()];
+    inputs.extend(elements);
+    executor.execute(&circuit, inputs)
+}
+
+/// Compiles a read. The input groups are the index and then the elements.
+fn read_circuit(length: usize, bits: u32, index_bits: u32) -> Circuit {
+    let mut builder = CircuitBuilder::new();
+    let index = builder.input(0, index_bits);
+    let elements = (0..length)
+        .map(|_| builder.input(0, bits))
+        .collect::<Vec<Vec<WireId>>>();
+    let output = builder.array_read(&elements, &index);
+    builder.output(&output);
+    builder.build()
+}
+
+/// Compiles a write. The input groups are the index, the value, and then the elements. The output
+/// groups are the updated elements.
+fn write_circuit(length: usize, bits: u32, index_bits: u32) -> Circuit {
+    let mut builder = CircuitBuilder::new();
+    let index = builder.input(0, index_bits);
+    let value = builder.input(0, bits);
+    let elements = (0..length)
+        .map(|_| builder.input(0, bits))
+        .collect::<Vec<Vec<WireId>>>();
+    for element in builder.array_write(&elements, &index, &value) {
+        builder.output(&element);
+    }
+    builder.build()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::testing::check_memory;
+    use super::*;
+    use crate::{
+        free_xor_garbling::FreeXorBit,
+        mpc_core::{evaluate_gate, garble_gate, Operation, WireValue},
+        wires::{Wire16Bit, Wire8Bit},
+    };
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+    const SEED: [u8; 32] = [42u8; 32];
+
+    #[test]
+    fn test_decode_index() {
+        let mut builder = CircuitBuilder::new();
+        let index = builder.input(1, 4);
+        let decoded = builder.decode_index(&index, 11);
+        builder.output(&decoded);
+        let circuit = builder.build();
+        assert!(circuit.and_count() <= 2 * 11 + 4);
+
+        for position in 0..16u8 {
+            let got = circuit.compute(&[position.to_bits()[4..].to_vec()]);
+            let want = (0..11).map(|i| i == position).collect::<Vec<bool>>();
+            assert_eq!(got[0], want, "{}", position);
+        }
+    }
+
+    #[test]
+    fn test_read_and_write() {
+        let mut rng = StdRng::from_seed(SEED);
+        let mut builder = CircuitBuilder::new();
+        let elements = (0..6).map(|_| builder.input(1, 8)).collect::<Vec<_>>();
+        let index = builder.input(2, 3);
+        let value = builder.input(2, 8);
+        let read = builder.array_read(&elements, &index);
+        let written = builder.array_write(&elements, &index, &value);
+        builder.output(&read);
+        builder.output(&written.concat());
+        let circuit = builder.build();
+
+        for _ in 0..20 {
+            let values = (0..6).map(|_| rng.gen()).collect::<Vec<u8>>();
+            let position: u8 = rng.gen_range(0, 8);
+            let new_value: u8 = rng.gen();
+            let mut inputs = values
+                .iter()
+                .map(|value| value.to_bits())
+                .collect::<Vec<_>>();
+            inputs.push(position.to_bits()[5..].to_vec());
+            inputs.push(new_value.to_bits());
+            let got = circuit.compute(&inputs);
+
+            let position = position as usize;
+            let want = values.get(position).cloned().unwrap_or(0);
+            assert_eq!(u8::from_bits(&got[0]), want, "{}", position);
+            let mut updated = values.clone();
+            if position < updated.len() {
+                updated[position] = new_value;
+            }
+            let got = got[1].chunks(8).map(u8::from_bits).collect::<Vec<u8>>();
+            assert_eq!(got, updated, "{}", position);
+        }
+    }
+
+    #[test]
+    fn test_garbled_histogram() {
+        let mut rng = StdRng::from_seed(SEED);
+        let mut session = GarblingSession::new(&mut rng);
+        let mut garbler = GarblingArray::<FreeXorBit, Wire16Bit>::new(
+            (0..10)
+                .map(|_| GarblingWire::with_delta(&mut rng, &session.delta))
+                .collect(),
+        );
+        let mut evaluator = EvaluatingArray::new(
+            garbler
+                .elements
+                .iter()
+                .map(|element| element.clone().encode(0))
+                .collect(),
+        );
+        let mut histogram = vec![0u16; 10];
+
+        for _ in 0..20 {
+            let bucket: u8 = rng.gen_range(0, 10);
+            histogram[bucket as usize] += 1;
+
+            // histogram[bucket] += 1
+            let index = GarblingWire::<FreeXorBit, Wire8Bit>::with_delta(&mut rng, &session.delta);
+            let garbled_index = index.clone().encode(bucket);
+            let (count, gates) = garbler.garble_read(index.clone(), &mut session, &mut rng);
+            let garbled_count = evaluator.evaluate_read(garbled_index.clone(), gates);
+            let one = GarblingWire::<FreeXorBit, Wire16Bit>::with_delta(&mut rng, &session.delta);
+            let garbled_one = one.clone().encode(1);
+            let (count, gates) = garble_gate(count, one, Operation::Add, &mut session, &mut rng);
+            let garbled_count = evaluate_gate(garbled_count, garbled_one, Operation::Add, gates);
+            let gates = garbler.garble_write(index, count, &mut session, &mut rng);
+            evaluator.evaluate_write(garbled_index, garbled_count, gates);
+        }
+
+        assert_eq!(evaluator.len(), histogram.len());
+        for ((element, value), want) in garbler
+            .elements
+            .iter()
+            .zip(evaluator.elements.iter())
+            .zip(histogram)
+        {
+            assert_eq!(element.decoding_info().decode(value), want);
+        }
+    }
+
+    #[test]
+    fn test_linear_scan_memory() {
+        check_memory(
+            20,
+            30,
+            |elements, _| {
+                let elements = elements.into_iter().map(GarblingWire::from_bits);
+                GarblingArray::<FreeXorBit, Wire16Bit>::new(elements.collect())
+            },
+            |elements, _| {
+                let elements = elements.into_iter().map(|bits| EvaluatingWire { bits });
+                EvaluatingArray::new(elements.collect())
+            },
+        );
+    }
+}
+
+/// A two-party harness that runs the same accesses on any `ObliviousMemory` and checks them
+/// against a plain array.
+#[cfg(test)]
+pub(crate) mod testing {
+    use super::ObliviousMemory;
+    use crate::{
+        executor::{Evaluator, Executor, Garbler},
+        free_xor_garbling::FreeXorBit,
+        mpc_core::{GarblingSession, WireValue},
+    };
+    use ocelot::ot::{KosReceiver, KosSender, Receiver, Sender};
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+    use scuttlebutt::{AesRng, TrackChannel};
+    use std::{
+        io::{BufReader, BufWriter},
+        os::unix::net::UnixStream,
+    };
+    const SEED: [u8; 32] = [42u8; 32];
+
+    type Channel = TrackChannel<BufReader<UnixStream>, BufWriter<UnixStream>>;
+    pub(crate) type TestGarbler<'a> = Garbler<'a, FreeXorBit, Channel, KosSender, AesRng>;
+    pub(crate) type TestEvaluator<'a> = Evaluator<'a, FreeXorBit, Channel, KosReceiver, AesRng>;
+
+    /// Returns a public list of accesses: the index and, for writes, the value.
+    fn accesses(length: usize, count: usize) -> Vec<(u16, Option<u16>)> {
+        let mut rng = StdRng::from_seed(SEED);
+        (0..count)
+            .map(|_| {
+                // Small indices repeat often, and some indices are out of bounds.
+                let index = match rng.gen_range(0, 4) {
+                    0 => rng.gen_range(0, 4),
+                    1 => rng.gen_range(length as u16, length as u16 + 40),
+                    _ => rng.gen_range(0, length as u16),
+                };
+                let value = if rng.gen() { Some(rng.gen()) } else { None };
+                (index, value)
+            })
+            .collect()
+    }
+
+    /// Creates a memory of `length` 16-bit elements on both sides with `new_garbling` and
+    /// `new_evaluating`, runs `count` random reads and writes, and checks the values that are read.
+    pub(crate) fn check_memory<G, E, MG, ME>(
+        length: usize,
+        count: usize,
+        new_garbling: G,
+        new_evaluating: E,
+    ) where
+        G: FnOnce(Vec<Vec<(FreeXorBit, FreeXorBit)>>, &mut TestGarbler) -> MG + Send + 'static,
+        E: FnOnce(Vec<Vec<FreeXorBit>>, &mut TestEvaluator) -> ME,
+        MG: for<'a> ObliviousMemory<TestGarbler<'a>>,
+        ME: for<'a> ObliviousMemory<TestEvaluator<'a>>,
+    {
+        let initial = (0..length)
+            .map(|index| index as u16 * 3)
+            .collect::<Vec<u16>>();
+        let accesses = accesses(length, count);
+        let mut want = vec![];
+        let mut model = initial.clone();
+        for (index, value) in accesses.iter() {
+            let index = *index as usize;
+            want.push(model.get(index).cloned().unwrap_or(0));
+            if let (Some(element), Some(value)) = (model.get_mut(index), value) {
+                *element = *value;
+            }
+        }
+
+        let (sender, receiver) = UnixStream::pair().unwrap();
+        let garbler_accesses = accesses.clone();
+        let handle = std::thread::spawn(move || {
+            let mut rng = AesRng::new();
+            let reader = BufReader::new(sender.try_clone().unwrap());
+            let writer = BufWriter::new(sender);
+            let mut channel = TrackChannel::new(reader, writer);
+            let mut session = GarblingSession::new(&mut rng);
+            let mut ot = KosSender::init(&mut channel, &mut rng).unwrap();
+            let mut garbler = Garbler::new(&mut channel, &mut ot, &mut session, &mut rng);
+
+            let elements = initial
+                .iter()
+                .map(|value| garbler.input(&value.to_bits()).unwrap())
+                .collect::<Vec<_>>();
+            let mut memory = new_garbling(elements, &mut garbler);
+            let mut got = vec![];
+            for (index, value) in garbler_accesses.iter() {
+                let index = garbler.input(&index.to_bits()).unwrap();
+                let read = memory.read(&index, &mut garbler).unwrap();
+                got.push(u16::from_bits(&garbler.reveal(&read).unwrap()));
+                if let Some(value) = value {
+                    let value = garbler.input(&value.to_bits()).unwrap();
+                    memory.write(&index, &value, &mut garbler).unwrap();
+                }
+            }
+            got
+        });
+
+        let mut rng = AesRng::new();
+        let reader = BufReader::new(receiver.try_clone().unwrap());
+        let writer = BufWriter::new(receiver);
+        let mut channel = TrackChannel::new(reader, writer);
+        let mut ot = KosReceiver::init(&mut channel, &mut rng).unwrap();
+        let mut evaluator = Evaluator::new(&mut channel, &mut ot, &mut rng);
+
+        let elements = (0..length)
+            .map(|_| evaluator.peer_input(16).unwrap())
+            .collect::<Vec<_>>();
+        let mut memory = new_evaluating(elements, &mut evaluator);
+        assert_eq!(memory.len(), length);
+        let mut got = vec![];
+        for (_, value) in accesses.iter() {
+            let index = evaluator.peer_input(16).unwrap();
+            let read = memory.read(&index, &mut evaluator).unwrap();
+            got.push(u16::from_bits(&evaluator.reveal(&read).unwrap()));
+            if value.is_some() {
+                let value = evaluator.peer_input(16).unwrap();
+                memory.write(&index, &value, &mut evaluator).unwrap();
+            }
+        }
+        assert_eq!(got, want);
+        assert_eq!(handle.join().unwrap(), want);
+    }
+}
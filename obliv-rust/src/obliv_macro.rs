@@ -1,5 +1,12 @@
 //! This module implements the macros that will expand the `obliv` keyword into an MPC protocol.
 
+/// Example:
+/// obliv!(builder, if (condition) { x = value; } else { y = other; });
+///
+/// The `obliv if` form works on a `CircuitBuilder`. The condition is a secret 1-bit wire and every
+/// assigned variable is a group of wires. Both branches are always computed, so the circuit does
+/// not depend on the condition. Instead, every assignment goes through a mux that only keeps the
+/// new value if its branch is taken. The branches contain assignments and nested `if`s.
 #[macro_export]
 macro_rules! obliv {
     ($a:ident + $b:ident) => {{
@@ -14,57 +21,168 @@ macro_rules! obliv {
             val
         }
     }};
+    ($builder:ident, if $($branch:tt)*) => {{
+        let auto_generated_guard = $builder.constant(true);
+        $crate::obliv!(@branch $builder, auto_generated_guard, if $($branch)*);
+    }};
+    (@branch $builder:ident, $guard:ident,) => {};
+    (@branch
+        $builder:ident,
+        $guard:ident,
+        if ($condition:expr) { $($then:tt)* } else { $($otherwise:tt)* }
+        $($rest:tt)*
+    ) => {
+        {
+            // A nested branch is taken if the enclosing branch is taken and its condition holds.
+            let auto_generated_condition: $crate::circuit::WireId = $condition;
+            let auto_generated_then = $builder.and($guard, auto_generated_condition);
+            $crate::obliv!(@branch $builder, auto_generated_then, $($then)*);
+            let auto_generated_not = $builder.inv(auto_generated_condition);
+            let auto_generated_otherwise = $builder.and($guard, auto_generated_not);
+            $crate::obliv!(@branch $builder, auto_generated_otherwise, $($otherwise)*);
+        }
+        $crate::obliv!(@branch $builder, $guard, $($rest)*);
+    };
+    (@branch $builder:ident, $guard:ident, if ($condition:expr) { $($then:tt)* } $($rest:tt)*) => {
+        {
+            let auto_generated_condition: $crate::circuit::WireId = $condition;
+            let auto_generated_then = $builder.and($guard, auto_generated_condition);
+            $crate::obliv!(@branch $builder, auto_generated_then, $($then)*);
+        }
+        $crate::obliv!(@branch $builder, $guard, $($rest)*);
+    };
+    (@branch $builder:ident, $guard:ident, $variable:ident = $value:expr; $($rest:tt)*) => {
+        // Statements after the assignment see the new value, which is only wrong if the branch is
+        // not taken, and then their results are discarded as well.
+        let auto_generated_value: Vec<$crate::circuit::WireId> = $value;
+        $variable = $builder.mux($guard, &auto_generated_value, &$variable);
+        $crate::obliv!(@branch $builder, $guard, $($rest)*);
+    };
 }
 
 /// Example:
-/// One side calls:       assign!(a, value 10);
-/// The other side calls: assign!(a);
+/// One side calls:       assign!(a, 10, protocol, PlainBit, Wire8Bit);
+/// The other side calls: assign!(a <- party 1, protocol, PlainBit, Wire8Bit);
 ///
-/// If the garbler calls the first statement, then it encodes and sends.
-/// If the evaluator calls the first statement, then they run OT.
+/// The first statement inputs a private value of the caller and the second one inputs the value of
+/// the other party. If the garbler inputs a value, then it encodes and sends the garbled value. If
+/// the evaluator inputs a value, then they run the OT of the protocol, where the garbler is the
+/// sender. Both forms return a `Result` with the `SecretWire` of the caller. Receiving the input
+/// of a party that is not the other party of the protocol, including the caller itself, fails with
+/// `ProtocolError::UnknownParty` before anything is sent.
+///
+/// Several values can be input at once with, e.g., `assign!([a = 10, b = 20], ...)` on one side
+/// and `assign!([a, b] <- party 1, ...)` on the other side. They are sent in a single message or a
+/// single batch of OTs, and the result is a `Vec` of `SecretWire`s.
 #[macro_export]
 macro_rules! assign {
+    ([$($a:ident = $c:expr),*], $p:expr, $g:ty, $w:ty) => {{
+        {
+            let auto_generated_values: Vec<<$w as $crate::mpc_core::Wire>::ValueType> =
+                vec![$($c),*];
+            match $p.role {
+                $crate::mpc_core::Role::Garbler => $p
+                    .garbler_inputs::<$g, $w>(&auto_generated_values)
+                    .map(|wires| {
+                        wires
+                            .into_iter()
+                            .map($crate::mpc_core::SecretWire::Garbling)
+                            .collect::<Vec<_>>()
+                    }),
+                $crate::mpc_core::Role::Evaluator => $p
+                    .evaluator_inputs::<$g, $w>(&auto_generated_values)
+                    .map(|wires| {
+                        wires
+                            .into_iter()
+                            .map($crate::mpc_core::SecretWire::<$g, $w>::Evaluating)
+                            .collect::<Vec<_>>()
+                    }),
+            }
+        }
+    }};
+    ([$($a:ident),*] <- party $b:expr, $p:expr, $g:ty, $w:ty) => {{
+        {
+            let auto_generated_count = [$(stringify!($a)),*].len();
+            $p.input_from($b).and_then(|()| match $p.role {
+                $crate::mpc_core::Role::Garbler => $p
+                    .garbler_peer_inputs::<$g, $w>(auto_generated_count)
+                    .map(|wires| {
+                        wires
+                            .into_iter()
+                            .map($crate::mpc_core::SecretWire::Garbling)
+                            .collect::<Vec<_>>()
+                    }),
+                $crate::mpc_core::Role::Evaluator => $p
+                    .evaluator_peer_inputs::<$g, $w>(auto_generated_count)
+                    .map(|wires| {
+                        wires
+                            .into_iter()
+                            .map($crate::mpc_core::SecretWire::<$g, $w>::Evaluating)
+                            .collect::<Vec<_>>()
+                    }),
+            })
+        }
+    }};
     ($a:ident, $c:expr, $p:expr, $g:ty, $w:ty) => {{
         {
-            // TODO: get proper Wire from type of $b.
-            // TODO: set the garbling mode in an external macro somehow.
-            // TODO: get the rng from outside.
-
-            // If called by the garbler, encode and send the garbled value
             match $p.role {
-                Role::Garbler => {
-                    let auto_generated_garbling_wire = GarblingWire::<$g, $w>::new(&mut $p.rng);
-                    let auto_generated_garbled_value =
-                        auto_generated_garbling_wire.clone().encode($c);
-                    let auto_generated_ser =
-                        bincode::serialize(&auto_generated_garbled_value).unwrap();
-                    $p.channel.write_usize(auto_generated_ser.len()).unwrap();
-                    $p.channel.write_bytes(&auto_generated_ser).unwrap();
-                    $p.channel.flush().unwrap();
-                    Some(auto_generated_garbling_wire)
-                }
-                Role::Evaluator => None,
+                $crate::mpc_core::Role::Garbler => $p
+                    .garbler_input::<$g, $w>($c)
+                    .map($crate::mpc_core::SecretWire::Garbling),
+                $crate::mpc_core::Role::Evaluator => $p
+                    .evaluator_input::<$g, $w>($c)
+                    .map($crate::mpc_core::SecretWire::Evaluating),
             }
-            // If called by the evaluator
         }
     }};
-    ($a:ident <- party $b:expr) => {{
+    ($a:ident <- party $b:expr, $p:expr, $g:ty, $w:ty) => {{
         {
-            // If called by the garbler
-            $c
-            // If called by the evaluator
+            $p.input_from($b).and_then(|()| match $p.role {
+                $crate::mpc_core::Role::Garbler => $p
+                    .garbler_peer_input::<$g, $w>()
+                    .map($crate::mpc_core::SecretWire::Garbling),
+                $crate::mpc_core::Role::Evaluator => $p
+                    .evaluator_peer_input::<$g, $w>()
+                    .map($crate::mpc_core::SecretWire::Evaluating),
+            })
         }
     }};
 }
 
+/// Example:
+/// Both sides call:       reveal!(a, protocol);
+/// Or to reveal to one:   reveal!(a -> party 1, protocol);
+///
+/// The first statement reveals the value to both parties and the second one only to the given
+/// party. The value is a `SecretWire`, e.g., the one returned by `assign!`. Both forms return a
+/// `Result` with the value if the caller learns it. Revealing to a party that is not part of the
+/// protocol fails with `ProtocolError::UnknownParty` before anything is sent.
+#[macro_export]
+macro_rules! reveal {
+    ($a:ident, $p:expr) => {{
+        $p.reveal(&$a, $crate::mpc_core::RevealTo::Both)
+    }};
+    ($a:ident -> party $b:expr, $p:expr) => {{
+        $p.reveal_to($b)
+            .and_then(|auto_generated_to| $p.reveal(&$a, auto_generated_to))
+    }};
+}
+
 #[cfg(test)]
 mod tests {
     use crate::{
-        mpc_core::{GarblingWire, Party, Protocol, Role},
-        plain_garbling::PlainBit,
+        circuit::CircuitBuilder,
+        executor::{receive, send, ProtocolError},
+        free_xor_garbling::FreeXorBit,
+        mpc_core::{
+            evaluate_gate, garble_gate, Gate, Operation, Party, Protocol, Role, SecretWire,
+            WireValue,
+        },
+        ot::OtKind,
+        plain_garbling::{decode, evaluate_plain, garble_u8_gate_plain, PlainBit},
         wires::Wire8Bit,
     };
-    use scuttlebutt::{AbstractChannel, AesRng, Channel};
+    use scuttlebutt::{AesRng, Channel};
     use std::{
         io::{BufReader, BufWriter},
         os::unix::net::UnixStream,
@@ -72,22 +190,102 @@ mod tests {
 
     #[test]
     fn obliv_init() {
-        let rng = AesRng::new();
-        let (sender, _receiver) = UnixStream::pair().unwrap();
-        let reader = BufReader::new(sender.try_clone().unwrap());
-        let writer = BufWriter::new(sender);
+        let (sender, receiver) = UnixStream::pair().unwrap();
+        let handle = std::thread::spawn(move || {
+            // Garbler
+            let reader = BufReader::new(sender.try_clone().unwrap());
+            let writer = BufWriter::new(sender);
+            let channel = Channel::new(reader, writer);
+            let alice = Party { id: 1 };
+            let bob = Party { id: 2 };
+            let mut protocol = Protocol::new(
+                vec![alice.clone(), bob],
+                alice,
+                Role::Garbler,
+                channel,
+                AesRng::new(),
+                OtKind::TrustedDealer,
+            )
+            .unwrap();
+            let a = assign!(a, 3 * 4, protocol, PlainBit, Wire8Bit).unwrap();
+            let b = assign!(b <- party 2, protocol, PlainBit, Wire8Bit).unwrap();
+            assert_eq!(a.garbling().unwrap().bits.len(), 8);
+            assert_eq!(b.garbling().unwrap().bits.len(), 8);
+        });
+
+        // Evaluator
+        let reader = BufReader::new(receiver.try_clone().unwrap());
+        let writer = BufWriter::new(receiver);
         let channel = Channel::new(reader, writer);
         let alice = Party { id: 1 };
         let bob = Party { id: 2 };
-        let mut protocol = Protocol {
-            parties: vec![alice.clone(), bob],
-            me: alice,
-            role: Role::Garbler,
+        let mut protocol = Protocol::new(
+            vec![alice, bob.clone()],
+            bob,
+            Role::Evaluator,
             channel,
-            rng,
-        };
-        let ret = assign!(a, 3 * 4, protocol, PlainBit, Wire8Bit);
-        println!("{:?}", ret);
+            AesRng::new(),
+            OtKind::TrustedDealer,
+        )
+        .unwrap();
+        let a = assign!(a <- party 1, protocol, PlainBit, Wire8Bit).unwrap();
+        let b = assign!(b, 7, protocol, PlainBit, Wire8Bit).unwrap();
+        assert_eq!(decode::<Wire8Bit>(&a.evaluating().unwrap()), 12);
+        assert_eq!(decode::<Wire8Bit>(&b.evaluating().unwrap()), 7);
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn obliv_if() {
+        let mut builder = CircuitBuilder::new();
+        let a = builder.input(1, 8);
+        let b = builder.input(2, 8);
+        let less = builder.less_than(&a, &b, false);
+        let zero = builder.constant(false);
+        let mut max = a.clone();
+        let mut min = b.clone();
+        let mut big = vec![zero];
+        obliv!(
+            builder,
+            if (less) {
+                max = b.clone();
+                min = a.clone();
+            }
+        );
+        obliv!(
+            builder,
+            if (builder.equal(&a, &b)) {
+                max = a.clone();
+            } else {
+                if (max[0]) {
+                    big = vec![builder.constant(true)];
+                    max = builder.sub(&max, &min);
+                } else {
+                    min = builder.add(&min, &min);
+                }
+            }
+        );
+        builder.output(&max);
+        builder.output(&min);
+        builder.output(&big);
+        let circuit = builder.build();
+
+        for &(x, y) in [(3u8, 7u8), (7, 3), (5, 5), (200, 20), (20, 200), (130, 130)].iter() {
+            let got = circuit.compute(&[x.to_bits(), y.to_bits()]);
+            let (mut max, mut min) = (x.max(y), x.min(y));
+            let mut big = false;
+            if x != y {
+                if max >= 128 {
+                    big = true;
+                    max -= min;
+                } else {
+                    min = min.wrapping_add(min);
+                }
+            }
+            assert_eq!(u8::from_bits(&got[0]), max, "{} {}", x, y);
+            assert_eq!(u8::from_bits(&got[1]), min, "{} {}", x, y);
+            assert_eq!(got[2], vec![big], "{} {}", x, y);
+        }
     }
 
     #[test]
@@ -106,83 +304,186 @@ mod tests {
         assert_eq!(val, 1);
     }
 
-    //#[test]
-    //fn plain_circuit_without_ot() {
-    //    // network setup
-    //    let (sender, receiver) = UnixStream::pair().unwrap();
-    //    let handle = std::thread::spawn(move || {
-    //        let mut rng = AesRng::new();
-    //        // Garbler
-    //        let reader = BufReader::new(sender.try_clone().unwrap());
-    //        let writer = BufWriter::new(sender);
-    //        let mut channel = TrackChannel::new(reader, writer);
-    //        // ------------------ Start of the Garbler
-    //        let alice = Party { id: 1 };
-    //        let bob = Party { id: 2 };
-    //        let _protocol = Protocol {
-    //            parties: vec![alice.clone(), bob],
-    //            me: alice,
-    //            role: Role::Garbler,
-    //        };
-
-    //        // assign!(a1 <- party 1, value 10);
-    //        let a1 = GarblingWire::<PlainBit, Wire8Bit>::new(&mut rng);
-    //        let garbled_value_a1 = a1.clone().encode(10);
-    //        let ser = bincode::serialize(&garbled_value_a1).unwrap();
-    //        channel.write_usize(ser.len()).unwrap();
-    //        channel.write_bytes(&ser).unwrap();
-
-    //        // assign!(a2 <- party 1, value 20);
-    //        let a2 = GarblingWire::<PlainBit, Wire8Bit>::new(&mut rng);
-    //        let garbled_value_a2 = a2.clone().encode(20);
-    //        let ser = bincode::serialize(&garbled_value_a2).unwrap();
-    //        channel.write_usize(ser.len()).unwrap();
-    //        channel.write_bytes(&ser).unwrap();
-
-    //        // obliv!(g = a1 + a2);
-    //        let (_, gates) = garble_u8_gate_plain(a1, a2, Operation::AddU8);
-    //        let ser = bincode::serialize(&gates).unwrap();
-    //        channel.write_usize(ser.len()).unwrap();
-    //        channel.write_bytes(&ser).unwrap();
-
-    //        // reveal!(g);
-    //        // TODO: send decoding
-    //        // TODO: receive the plain value
-    //    });
-
-    //    // Evaluator
-    //    let reader = BufReader::new(receiver.try_clone().unwrap());
-    //    let writer = BufWriter::new(receiver);
-    //    let mut channel = TrackChannel::new(reader, writer);
-    //    // ------------------ Start of evaluator
-    //    let alice = Party { id: 1 };
-    //    let bob = Party { id: 2 };
-    //    let _protocol = Protocol {
-    //        parties: vec![alice, bob.clone()],
-    //        me: bob,
-    //        role: Role::Evaluator,
-    //    };
-    //    // assign!(a1 <- party 1);
-    //    let size = channel.read_usize().unwrap();
-    //    let ser = channel.read_vec(size).unwrap();
-    //    let a1: EvaluatingWire<PlainBit> = bincode::deserialize(&ser).unwrap();
-
-    //    // assign!(a2 <- party 1);
-    //    let size = channel.read_usize().unwrap();
-    //    let ser = channel.read_vec(size).unwrap();
-    //    let a2: EvaluatingWire<PlainBit> = bincode::deserialize(&ser).unwrap();
-
-    //    // obliv!(g = a1 + a2);
-    //    let size = channel.read_usize().unwrap();
-    //    let ser = channel.read_vec(size).unwrap();
-    //    let gates: Vec<Gate<PlainBit, Wire8Bit>> = bincode::deserialize(&ser).unwrap();
-    //    let g = evaluate_plain(a1, a2, Operation::AddU8, gates);
-
-    //    // reveal!(g);
-    //    // TODO receive decoding and decode.
-    //    let plain_g = to_u8(&g);
-    //    assert_eq!(plain_g, 30);
-
-    //    handle.join().unwrap();
-    //}
+    #[test]
+    fn obliv_reveal() {
+        let (sender, receiver) = UnixStream::pair().unwrap();
+        let handle = std::thread::spawn(move || {
+            // Garbler
+            let reader = BufReader::new(sender.try_clone().unwrap());
+            let writer = BufWriter::new(sender);
+            let channel = Channel::new(reader, writer);
+            let alice = Party { id: 1 };
+            let bob = Party { id: 2 };
+            let mut protocol = Protocol::new(
+                vec![alice.clone(), bob],
+                alice,
+                Role::Garbler,
+                channel,
+                AesRng::new(),
+                OtKind::NaorPinkas,
+            )
+            .unwrap();
+            assert!(matches!(
+                assign!(a1 <- party 1, protocol, PlainBit, Wire8Bit),
+                Err(ProtocolError::UnknownParty(1))
+            ));
+            assert!(matches!(
+                assign!([a1, a2] <- party 3, protocol, PlainBit, Wire8Bit),
+                Err(ProtocolError::UnknownParty(3))
+            ));
+            let a1 = assign!(a1, 10, protocol, PlainBit, Wire8Bit).unwrap();
+            let a2 = assign!(a2 <- party 2, protocol, PlainBit, Wire8Bit).unwrap();
+            assert_eq!(reveal!(a1 -> party 2, protocol).unwrap(), None);
+            assert_eq!(reveal!(a2 -> party 1, protocol).unwrap(), Some(20));
+
+            // obliv!(g = a1 + a2);
+            let (g, gates) = garble_u8_gate_plain(
+                a1.garbling().unwrap(),
+                a2.garbling().unwrap(),
+                Operation::Add,
+            );
+            send(&mut protocol.channel, &gates).unwrap();
+
+            let g = SecretWire::Garbling(g);
+            assert!(matches!(
+                reveal!(g -> party 3, protocol),
+                Err(ProtocolError::UnknownParty(3))
+            ));
+            assert_eq!(reveal!(g, protocol).unwrap(), Some(30));
+        });
+
+        // Evaluator
+        let reader = BufReader::new(receiver.try_clone().unwrap());
+        let writer = BufWriter::new(receiver);
+        let channel = Channel::new(reader, writer);
+        let alice = Party { id: 1 };
+        let bob = Party { id: 2 };
+        let mut protocol = Protocol::new(
+            vec![alice, bob.clone()],
+            bob,
+            Role::Evaluator,
+            channel,
+            AesRng::new(),
+            OtKind::NaorPinkas,
+        )
+        .unwrap();
+        assert!(matches!(
+            assign!(a1 <- party 3, protocol, PlainBit, Wire8Bit),
+            Err(ProtocolError::UnknownParty(3))
+        ));
+        assert!(matches!(
+            assign!([a1, a2] <- party 2, protocol, PlainBit, Wire8Bit),
+            Err(ProtocolError::UnknownParty(2))
+        ));
+        let a1 = assign!(a1 <- party 1, protocol, PlainBit, Wire8Bit).unwrap();
+        let a2 = assign!(a2, 20, protocol, PlainBit, Wire8Bit).unwrap();
+        assert_eq!(reveal!(a1 -> party 2, protocol).unwrap(), Some(10));
+        assert_eq!(reveal!(a2 -> party 1, protocol).unwrap(), None);
+
+        // obliv!(g = a1 + a2);
+        let gates: Vec<Gate<PlainBit, Wire8Bit>> = receive(&mut protocol.channel).unwrap();
+        let g = evaluate_plain(
+            a1.evaluating().unwrap(),
+            a2.evaluating().unwrap(),
+            Operation::Add,
+            gates,
+        );
+
+        let g = SecretWire::<PlainBit, Wire8Bit>::Evaluating(g);
+        assert!(matches!(
+            reveal!(g -> party 3, protocol),
+            Err(ProtocolError::UnknownParty(3))
+        ));
+        assert_eq!(reveal!(g, protocol).unwrap(), Some(30));
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn obliv_free_xor() {
+        let (sender, receiver) = UnixStream::pair().unwrap();
+        let handle = std::thread::spawn(move || {
+            // Garbler
+            let reader = BufReader::new(sender.try_clone().unwrap());
+            let writer = BufWriter::new(sender);
+            let channel = Channel::new(reader, writer);
+            let alice = Party { id: 1 };
+            let bob = Party { id: 2 };
+            let mut protocol = Protocol::new(
+                vec![alice.clone(), bob],
+                alice,
+                Role::Garbler,
+                channel,
+                AesRng::new(),
+                OtKind::ChouOrlandi,
+            )
+            .unwrap();
+            let a1 = assign!(a1, 202, protocol, FreeXorBit, Wire8Bit)
+                .unwrap()
+                .garbling()
+                .unwrap();
+            let a2 = assign!(a2 <- party 2, protocol, FreeXorBit, Wire8Bit)
+                .unwrap()
+                .garbling()
+                .unwrap();
+
+            // obliv!(sum = a1 + a2; both = a1 & a2);
+            let (sum, mut gates) = garble_gate(
+                a1.clone(),
+                a2.clone(),
+                Operation::Add,
+                &mut protocol.session,
+                &mut protocol.rng,
+            );
+            let (both, and_gates) = garble_gate(
+                a1,
+                a2,
+                Operation::And,
+                &mut protocol.session,
+                &mut protocol.rng,
+            );
+            gates.extend(and_gates);
+            send(&mut protocol.channel, &gates).unwrap();
+
+            let sum = SecretWire::Garbling(sum);
+            let both = SecretWire::Garbling(both);
+            assert_eq!(reveal!(sum, protocol).unwrap(), Some(49));
+            assert_eq!(reveal!(both, protocol).unwrap(), Some(66));
+        });
+
+        // Evaluator
+        let reader = BufReader::new(receiver.try_clone().unwrap());
+        let writer = BufWriter::new(receiver);
+        let channel = Channel::new(reader, writer);
+        let alice = Party { id: 1 };
+        let bob = Party { id: 2 };
+        let mut protocol = Protocol::new(
+            vec![alice, bob.clone()],
+            bob,
+            Role::Evaluator,
+            channel,
+            AesRng::new(),
+            OtKind::ChouOrlandi,
+        )
+        .unwrap();
+        let a1 = assign!(a1 <- party 1, protocol, FreeXorBit, Wire8Bit)
+            .unwrap()
+            .evaluating()
+            .unwrap();
+        let a2 = assign!(a2, 103, protocol, FreeXorBit, Wire8Bit)
+            .unwrap()
+            .evaluating()
+            .unwrap();
+
+        // obliv!(sum = a1 + a2; both = a1 & a2);
+        let mut gates: Vec<Gate<FreeXorBit, Wire8Bit>> = receive(&mut protocol.channel).unwrap();
+        let and_gates = gates.split_off(1);
+        let sum = evaluate_gate(a1.clone(), a2.clone(), Operation::Add, gates);
+        let both = evaluate_gate(a1, a2, Operation::And, and_gates);
+
+        let sum = SecretWire::<FreeXorBit, Wire8Bit>::Evaluating(sum);
+        let both = SecretWire::<FreeXorBit, Wire8Bit>::Evaluating(both);
+        assert_eq!(reveal!(sum, protocol).unwrap(), Some(49));
+        assert_eq!(reveal!(both, protocol).unwrap(), Some(66));
+        handle.join().unwrap();
+    }
 }
@@ -0,0 +1,359 @@
+//! Lets the parties choose the OT protocol at runtime. The evaluator gets the keys of its inputs
+//! with OT, and different deployments need different trade-offs: the base OTs of Chou–Orlandi and
+//! Naor–Pinkas are cheap to set up, KOS is an OT extension that is much faster for many inputs, and
+//! the trusted-dealer OT is an insecure stand-in for tests. The trusted-dealer OT only exists in
+//! tests and with the `insecure-dealer-ot` feature.
+
+use crate::{
+    executor::ProtocolError,
+    mpc_core::{PartyOt, Role},
+};
+use ocelot::ot::{
+    ChouOrlandiReceiver, ChouOrlandiSender, KosReceiver, KosSender, NaorPinkasReceiver,
+    NaorPinkasSender, Receiver, Sender,
+};
+#[cfg(any(test, feature = "insecure-dealer-ot"))]
+use rand::SeedableRng;
+use rand::{CryptoRng, Rng};
+#[cfg(any(test, feature = "insecure-dealer-ot"))]
+use scuttlebutt::AesRng;
+use scuttlebutt::{AbstractChannel, Block};
+
+// ----------------------------------------------------------------------------------------------
+// -                                  Type Definitions                                          -
+// ----------------------------------------------------------------------------------------------
+
+/// The OT protocols that the parties can choose from. Both parties have to choose the same one.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OtKind {
+    /// The base OT of Chou and Orlandi. Every OT costs a few public-key operations.
+    ChouOrlandi,
+    /// The base OT of Naor and Pinkas. Every OT costs a few public-key operations.
+    NaorPinkas,
+    /// The OT extension of Keller, Orsini, and Scholl. It runs 128 base OTs during the setup and
+    /// every later OT only costs symmetric-key operations.
+    Kos,
+    /// An insecure OT for tests, see `DealerSender`.
+    #[cfg(any(test, feature = "insecure-dealer-ot"))]
+    TrustedDealer,
+}
+
+/// The sender of any of the OT protocols in `OtKind`.
+pub enum AnyOtSender {
+    ChouOrlandi(ChouOrlandiSender),
+    NaorPinkas(NaorPinkasSender),
+    Kos(KosSender),
+    #[cfg(any(test, feature = "insecure-dealer-ot"))]
+    TrustedDealer(Box<DealerSender>),
+}
+
+/// The receiver of any of the OT protocols in `OtKind`.
+pub enum AnyOtReceiver {
+    ChouOrlandi(ChouOrlandiReceiver),
+    NaorPinkas(NaorPinkasReceiver),
+    Kos(KosReceiver),
+    #[cfg(any(test, feature = "insecure-dealer-ot"))]
+    TrustedDealer(Box<DealerReceiver>),
+}
+
+/// The sender of the trusted-dealer OT. A trusted dealer hands out random OTs, which are turned
+/// into OTs of the chosen messages with Beaver's trick: the receiver sends its choice bit XORed
+/// with the random one, and the sender masks its messages with the random messages in that order.
+/// The dealer is simulated from a seed that the sender sends to the receiver, so both parties
+/// learn everything. Never use it outside of tests.
+#[cfg(any(test, feature = "insecure-dealer-ot"))]
+pub struct DealerSender {
+    dealer: AesRng,
+}
+
+/// The receiver of the trusted-dealer OT, see `DealerSender`.
+#[cfg(any(test, feature = "insecure-dealer-ot"))]
+pub struct DealerReceiver {
+    dealer: AesRng,
+}
+
+// ----------------------------------------------------------------------------------------------
+// -                                    Impl blocks                                             -
+// ----------------------------------------------------------------------------------------------
+
+impl AnyOtSender {
+    /// Runs the setup of the chosen OT. The other party calls `AnyOtReceiver::new` at the same
+    /// time.
+    pub fn new<C: AbstractChannel, RNG: CryptoRng + Rng>(
+        kind: OtKind,
+        channel: &mut C,
+        rng: &mut RNG,
+    ) -> Result<Self, ocelot::Error> {
+        Ok(match kind {
+            OtKind::ChouOrlandi => AnyOtSender::ChouOrlandi(ChouOrlandiSender::init(channel, rng)?),
+            OtKind::NaorPinkas => AnyOtSender::NaorPinkas(NaorPinkasSender::init(channel, rng)?),
+            OtKind::Kos => AnyOtSender::Kos(KosSender::init(channel, rng)?),
+            #[cfg(any(test, feature = "insecure-dealer-ot"))]
+            OtKind::TrustedDealer => {
+                AnyOtSender::TrustedDealer(Box::new(DealerSender::init(channel, rng)?))
+            }
+        })
+    }
+}
+
+impl AnyOtReceiver {
+    /// Runs the setup of the chosen OT. The other party calls `AnyOtSender::new` at the same time.
+    pub fn new<C: AbstractChannel, RNG: CryptoRng + Rng>(
+        kind: OtKind,
+        channel: &mut C,
+        rng: &mut RNG,
+    ) -> Result<Self, ocelot::Error> {
+        Ok(match kind {
+            OtKind::ChouOrlandi => {
+                AnyOtReceiver::ChouOrlandi(ChouOrlandiReceiver::init(channel, rng)?)
+            }
+            OtKind::NaorPinkas => {
+                AnyOtReceiver::NaorPinkas(NaorPinkasReceiver::init(channel, rng)?)
+            }
+            OtKind::Kos => AnyOtReceiver::Kos(KosReceiver::init(channel, rng)?),
+            #[cfg(any(test, feature = "insecure-dealer-ot"))]
+            OtKind::TrustedDealer => {
+                AnyOtReceiver::TrustedDealer(Box::new(DealerReceiver::init(channel, rng)?))
+            }
+        })
+    }
+}
+
+impl PartyOt<AnyOtSender, AnyOtReceiver> {
+    /// Runs the setup of the chosen OT for the given role. Both parties have to call it at the same
+    /// time with the same `kind`.
+    pub fn new<C: AbstractChannel, RNG: CryptoRng + Rng>(
+        kind: OtKind,
+        role: &Role,
+        channel: &mut C,
+        rng: &mut RNG,
+    ) -> Result<Self, ProtocolError> {
+        Ok(match role {
+            Role::Garbler => PartyOt::Sender(AnyOtSender::new(kind, channel, rng)?),
+            Role::Evaluator => PartyOt::Receiver(AnyOtReceiver::new(kind, channel, rng)?),
+        })
+    }
+}
+
+impl Sender for AnyOtSender {
+    type Msg = Block;
+
+    /// Always fails without touching the channel, because the OT has to be chosen. Use
+    /// `AnyOtSender::new` instead.
+    fn init<C: AbstractChannel, RNG: CryptoRng + Rng>(
+        _channel: &mut C,
+        _rng: &mut RNG,
+    ) -> Result<Self, ocelot::Error> {
+        Err(ocelot::Error::Other(
+            "choose the OT with AnyOtSender::new".to_string(),
+        ))
+    }
+
+    fn send<C: AbstractChannel, RNG: CryptoRng + Rng>(
+        &mut self,
+        channel: &mut C,
+        inputs: &[(Block, Block)],
+        rng: &mut RNG,
+    ) -> Result<(), ocelot::Error> {
+        match self {
+            AnyOtSender::ChouOrlandi(ot) => ot.send(channel, inputs, rng),
+            AnyOtSender::NaorPinkas(ot) => ot.send(channel, inputs, rng),
+            AnyOtSender::Kos(ot) => ot.send(channel, inputs, rng),
+            #[cfg(any(test, feature = "insecure-dealer-ot"))]
+            AnyOtSender::TrustedDealer(ot) => ot.send(channel, inputs, rng),
+        }
+    }
+}
+
+impl Receiver for AnyOtReceiver {
+    type Msg = Block;
+
+    /// Always fails without touching the channel, because the OT has to be chosen. Use
+    /// `AnyOtReceiver::new` instead.
+    fn init<C: AbstractChannel, RNG: CryptoRng + Rng>(
+        _channel: &mut C,
+        _rng: &mut RNG,
+    ) -> Result<Self, ocelot::Error> {
+        Err(ocelot::Error::Other(
+            "choose the OT with AnyOtReceiver::new".to_string(),
+        ))
+    }
+
+    fn receive<C: AbstractChannel, RNG: CryptoRng + Rng>(
+        &mut self,
+        channel: &mut C,
+        inputs: &[bool],
+        rng: &mut RNG,
+    ) -> Result<Vec<Block>, ocelot::Error> {
+        match self {
+            AnyOtReceiver::ChouOrlandi(ot) => ot.receive(channel, inputs, rng),
+            AnyOtReceiver::NaorPinkas(ot) => ot.receive(channel, inputs, rng),
+            AnyOtReceiver::Kos(ot) => ot.receive(channel, inputs, rng),
+            #[cfg(any(test, feature = "insecure-dealer-ot"))]
+            AnyOtReceiver::TrustedDealer(ot) => ot.receive(channel, inputs, rng),
+        }
+    }
+}
+
+#[cfg(any(test, feature = "insecure-dealer-ot"))]
+impl Sender for DealerSender {
+    type Msg = Block;
+
+    fn init<C: AbstractChannel, RNG: CryptoRng + Rng>(
+        channel: &mut C,
+        rng: &mut RNG,
+    ) -> Result<Self, ocelot::Error> {
+        let seed = rng.gen::<Block>();
+        channel.write_block(&seed)?;
+        channel.flush()?;
+        Ok(DealerSender {
+            dealer: AesRng::from_seed(seed),
+        })
+    }
+
+    fn send<C: AbstractChannel, RNG: CryptoRng + Rng>(
+        &mut self,
+        channel: &mut C,
+        inputs: &[(Block, Block)],
+        _rng: &mut RNG,
+    ) -> Result<(), ocelot::Error> {
+        let random = (0..inputs.len())
+            .map(|_| random_ot(&mut self.dealer))
+            .collect::<Vec<(Block, Block, bool)>>();
+        let flips = (0..inputs.len())
+            .map(|_| channel.read_bool())
+            .collect::<Result<Vec<bool>, _>>()?;
+        for (((zero, one), (random_zero, random_one, _)), flip) in
+            inputs.iter().zip(random).zip(flips)
+        {
+            let (mask_zero, mask_one) = if flip {
+                (random_one, random_zero)
+            } else {
+                (random_zero, random_one)
+            };
+            channel.write_block(&(*zero ^ mask_zero))?;
+            channel.write_block(&(*one ^ mask_one))?;
+        }
+        channel.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(any(test, feature = "insecure-dealer-ot"))]
+impl Receiver for DealerReceiver {
+    type Msg = Block;
+
+    fn init<C: AbstractChannel, RNG: CryptoRng + Rng>(
+        channel: &mut C,
+        _rng: &mut RNG,
+    ) -> Result<Self, ocelot::Error> {
+        let seed = channel.read_block()?;
+        Ok(DealerReceiver {
+            dealer: AesRng::from_seed(seed),
+        })
+    }
+
+    fn receive<C: AbstractChannel, RNG: CryptoRng + Rng>(
+        &mut self,
+        channel: &mut C,
+        inputs: &[bool],
+        _rng: &mut RNG,
+    ) -> Result<Vec<Block>, ocelot::Error> {
+        let random = (0..inputs.len())
+            .map(|_| random_ot(&mut self.dealer))
+            .collect::<Vec<(Block, Block, bool)>>();
+        for ((_, _, random_choice), choice) in random.iter().zip(inputs) {
+            channel.write_bool(random_choice ^ choice)?;
+        }
+        channel.flush()?;
+        random
+            .into_iter()
+            .zip(inputs)
+            .map(|((random_zero, random_one, random_choice), choice)| {
+                let masked_zero = channel.read_block()?;
+                let masked_one = channel.read_block()?;
+                let masked = if *choice { masked_one } else { masked_zero };
+                let mask = if random_choice {
+                    random_one
+                } else {
+                    random_zero
+                };
+                Ok(masked ^ mask)
+            })
+            .collect()
+    }
+}
+
+// ----------------------------------------------------------------------------------------------
+// -                                 Utility Functions                                          -
+// ----------------------------------------------------------------------------------------------
+
+/// Returns the next random OT of the dealer: two random messages and a random choice bit.
+#[cfg(any(test, feature = "insecure-dealer-ot"))]
+fn random_ot(dealer: &mut AesRng) -> (Block, Block, bool) {
+    (dealer.gen(), dealer.gen(), dealer.gen())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use scuttlebutt::Channel;
+    use std::{
+        io::{self, BufReader, BufWriter},
+        os::unix::net::UnixStream,
+    };
+
+    #[test]
+    fn test_every_ot_kind() {
+        for &kind in [
+            OtKind::ChouOrlandi,
+            OtKind::NaorPinkas,
+            OtKind::Kos,
+            OtKind::TrustedDealer,
+        ]
+        .iter()
+        {
+            let mut rng = AesRng::new();
+            let inputs = (0..300)
+                .map(|_| (rng.gen::<Block>(), rng.gen::<Block>()))
+                .collect::<Vec<(Block, Block)>>();
+            let choices = (0..300).map(|_| rng.gen::<bool>()).collect::<Vec<bool>>();
+            let sender_inputs = inputs.clone();
+            let (sender, receiver) = UnixStream::pair().unwrap();
+            let handle = std::thread::spawn(move || {
+                let mut rng = AesRng::new();
+                let reader = BufReader::new(sender.try_clone().unwrap());
+                let writer = BufWriter::new(sender);
+                let mut channel = Channel::new(reader, writer);
+                let mut ot = AnyOtSender::new(kind, &mut channel, &mut rng).unwrap();
+                // Two batches check that both parties stay in sync after the first one.
+                ot.send(&mut channel, &sender_inputs[..100], &mut rng)
+                    .unwrap();
+                ot.send(&mut channel, &sender_inputs[100..], &mut rng)
+                    .unwrap();
+            });
+
+            let reader = BufReader::new(receiver.try_clone().unwrap());
+            let writer = BufWriter::new(receiver);
+            let mut channel = Channel::new(reader, writer);
+            let mut ot = AnyOtReceiver::new(kind, &mut channel, &mut rng).unwrap();
+            let mut got = ot.receive(&mut channel, &choices[..100], &mut rng).unwrap();
+            got.append(&mut ot.receive(&mut channel, &choices[100..], &mut rng).unwrap());
+            let want = inputs
+                .iter()
+                .zip(choices)
+                .map(|((zero, one), choice)| if choice { *one } else { *zero })
+                .collect::<Vec<Block>>();
+            assert_eq!(got, want, "{:?}", kind);
+            handle.join().unwrap();
+        }
+    }
+
+    #[test]
+    fn test_init_needs_a_kind() {
+        let mut rng = AesRng::new();
+        let mut channel = Channel::new(io::empty(), io::sink());
+        assert!(AnyOtSender::init(&mut channel, &mut rng).is_err());
+        assert!(AnyOtReceiver::init(&mut channel, &mut rng).is_err());
+    }
+}
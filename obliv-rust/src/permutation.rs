@@ -0,0 +1,524 @@
+//! Implements the Waksman permutation network on top of `CircuitBuilder`. The network is made of
+//! 2x2 switches that either pass their two items through or swap them, and it can apply any
+//! permutation of its items for the right settings of the switches. It needs
+//! `sum(ceil(log2(i)))` switches for `i` from 1 to `n`, i.e., about `n log n - n`, which is fewer
+//! than the comparators of a sorting network.
+//!
+//! A party that knows a permutation computes the switch settings in the clear with
+//! `waksman_switches`, and inputs them through its private input path. Neither the other party nor
+//! the garbled circuit learns anything about the permutation. Applying a permutation of each party
+//! in turn gives a shuffle that neither party knows.
+
+use crate::{
+    circuit::{Circuit, CircuitBuilder, WireId},
+    executor::{Executor, ProtocolError},
+};
+use rand::seq::SliceRandom;
+use rand_core::{CryptoRng, RngCore};
+
+impl CircuitBuilder {
+    /// Permutes items with a Waksman network whose switches are set by `switches`, in the order
+    /// that `waksman_switches` computes them. A switch costs one AND gate per wire of an item.
+    pub fn waksman_permute(
+        &mut self,
+        items: &[Vec<WireId>],
+        switches: &[WireId],
+    ) -> Vec<Vec<WireId>> {
+        assert_eq!(
+            switches.len(),
+            waksman_switch_count(items.len()),
+            "wrong number of switches"
+        );
+        let mut switches = switches.iter().cloned();
+        self.waksman_network(items, &mut switches)
+    }
+
+    fn waksman_network<I>(&mut self, items: &[Vec<WireId>], switches: &mut I) -> Vec<Vec<WireId>>
+    where
+        I: Iterator<Item = WireId>,
+    {
+        let count = items.len();
+        if count <= 1 {
+            return items.to_vec();
+        }
+        let half = count / 2;
+        let mut top = vec![];
+        let mut bottom = vec![];
+        for pair in items.chunks(2) {
+            if let [first, second] = pair {
+                let (first, second) = self.switch(first, second, next_switch(switches));
+                top.push(first);
+                bottom.push(second);
+            } else {
+                bottom.push(pair[0].clone());
+            }
+        }
+        let top = self.waksman_network(&top, switches);
+        let bottom = self.waksman_network(&bottom, switches);
+
+        let mut output = Vec::with_capacity(count);
+        for (index, (first, second)) in top.iter().zip(bottom.iter()).enumerate() {
+            // The last pair of an even number of items does not need a switch.
+            if count % 2 == 1 || index < half - 1 {
+                let (first, second) = self.switch(first, second, next_switch(switches));
+                output.push(first);
+                output.push(second);
+            } else {
+                output.push(first.clone());
+                output.push(second.clone());
+            }
+        }
+        if count % 2 == 1 {
+            output.push(bottom[half].clone());
+        }
+        output
+    }
+
+    /// Runs a Waksman network backwards, which applies the inverse of the permutation of
+    /// `waksman_permute` for the same switches: the item at position `i` moves to position
+    /// `permutation[i]`. It costs as many AND gates as `waksman_permute`.
+    pub fn waksman_unpermute(
+        &mut self,
+        items: &[Vec<WireId>],
+        switches: &[WireId],
+    ) -> Vec<Vec<WireId>> {
+        assert_eq!(
+            switches.len(),
+            waksman_switch_count(items.len()),
+            "wrong number of switches"
+        );
+        self.reverse_waksman_network(items, switches)
+    }
+
+    fn reverse_waksman_network(
+        &mut self,
+        items: &[Vec<WireId>],
+        switches: &[WireId],
+    ) -> Vec<Vec<WireId>> {
+        let count = items.len();
+        if count <= 1 {
+            return items.to_vec();
+        }
+        let half = count / 2;
+        // The switches are in the order of `waksman_network`: the input layer, the top and the
+        // bottom subnetworks, and the output layer.
+        let (input_layer, rest) = switches.split_at(half);
+        let (top_switches, rest) = rest.split_at(waksman_switch_count(half));
+        let (bottom_switches, output_layer) = rest.split_at(waksman_switch_count(count - half));
+
+        let mut top = vec![];
+        let mut bottom = vec![];
+        for (index, pair) in items.chunks(2).enumerate() {
+            if let [first, second] = pair {
+                if index < output_layer.len() {
+                    let (first, second) = self.switch(first, second, output_layer[index]);
+                    top.push(first);
+                    bottom.push(second);
+                } else {
+                    top.push(first.clone());
+                    bottom.push(second.clone());
+                }
+            } else {
+                bottom.push(pair[0].clone());
+            }
+        }
+        let top = self.reverse_waksman_network(&top, top_switches);
+        let bottom = self.reverse_waksman_network(&bottom, bottom_switches);
+
+        let mut output = Vec::with_capacity(count);
+        for ((first, second), swap) in top.iter().zip(bottom.iter()).zip(input_layer) {
+            let (first, second) = self.switch(first, second, *swap);
+            output.push(first);
+            output.push(second);
+        }
+        if count % 2 == 1 {
+            output.push(bottom[half].clone());
+        }
+        output
+    }
+
+    /// Swaps two items if `swap` is set.
+    fn switch(
+        &mut self,
+        first: &[WireId],
+        second: &[WireId],
+        swap: WireId,
+    ) -> (Vec<WireId>, Vec<WireId>) {
+        let mut first = first.to_vec();
+        let mut second = second.to_vec();
+        for (bit_1, bit_2) in first.iter_mut().zip(second.iter_mut()) {
+            let difference = self.xor(*bit_1, *bit_2);
+            let masked = self.and(swap, difference);
+            *bit_1 = self.xor(*bit_1, masked);
+            *bit_2 = self.xor(*bit_2, masked);
+        }
+        (first, second)
+    }
+}
+
+/// Returns the number of switches of a Waksman network of `count` items.
+pub fn waksman_switch_count(count: usize) -> usize {
+    (1..=count)
+        .map(|index| (usize::BITS - (index - 1).leading_zeros()) as usize)
+        .sum()
+}
+
+/// Returns the switch settings of a Waksman network that moves the item at `permutation[i]` to
+/// position `i`. It runs in the clear, on the side of the party that knows the permutation.
+pub fn waksman_switches(permutation: &[usize]) -> Vec<bool> {
+    let count = permutation.len();
+    let mut inverse = vec![usize::MAX; count];
+    for (output, input) in permutation.iter().enumerate() {
+        assert!(
+            *input < count && inverse[*input] == usize::MAX,
+            "not a permutation"
+        );
+        inverse[*input] = output;
+    }
+    let mut switches = vec![];
+    program(permutation, &inverse, &mut switches);
+    switches
+}
+
+/// Sets the switches of a network and its subnetworks, in the order `waksman_network` reads them.
+///
+/// Every path from an input to its output goes through the top or the bottom subnetwork. The two
+/// items of a switch must go through different subnetworks, at the input and at the output. The
+/// paths and these constraints form chains, which the looping algorithm colors by walking along
+/// them.
+fn program(permutation: &[usize], inverse: &[usize], switches: &mut Vec<bool>) {
+    let count = permutation.len();
+    if count <= 1 {
+        return;
+    }
+    let half = count / 2;
+    // The subnetwork of the path to every output, where true is the bottom subnetwork.
+    let mut bottom: Vec<Option<bool>> = vec![None; count];
+    let color = |start: usize, value: bool, bottom: &mut Vec<Option<bool>>| {
+        let mut pending = vec![(start, value)];
+        while let Some((output, value)) = pending.pop() {
+            if let Some(existing) = bottom[output] {
+                assert_eq!(existing, value, "the looping algorithm found a conflict");
+                continue;
+            }
+            bottom[output] = Some(value);
+            let input = permutation[output];
+            // The other input of the input switch, unless the input is the last of an odd count.
+            if input ^ 1 < count {
+                pending.push((inverse[input ^ 1], !value));
+            }
+            if output ^ 1 < count {
+                pending.push((output ^ 1, !value));
+            }
+        }
+    };
+    // The items that bypass a switch are fixed: the last input and output of an odd count go
+    // through the bottom, and the last output pair of an even count has no switch.
+    if count % 2 == 1 {
+        color(count - 1, true, &mut bottom);
+        color(inverse[count - 1], true, &mut bottom);
+    } else {
+        color(count - 1, true, &mut bottom);
+    }
+    for output in 0..count {
+        if bottom[output].is_none() {
+            color(output, false, &mut bottom);
+        }
+    }
+    let bottom = bottom
+        .into_iter()
+        .map(|value| value.expect("every path is colored"))
+        .collect::<Vec<bool>>();
+
+    // An input switch is set if its first input goes through the bottom.
+    for pair in 0..half {
+        switches.push(bottom[inverse[2 * pair]]);
+    }
+    let mut top_permutation = vec![0; half];
+    let mut bottom_permutation = vec![0; count - half];
+    for output in 0..count {
+        if bottom[output] {
+            bottom_permutation[output / 2] = permutation[output] / 2;
+        } else {
+            top_permutation[output / 2] = permutation[output] / 2;
+        }
+    }
+    for permutation in [top_permutation, bottom_permutation].iter() {
+        let mut inverse = vec![0; permutation.len()];
+        for (output, input) in permutation.iter().enumerate() {
+            inverse[*input] = output;
+        }
+        program(permutation, &inverse, switches);
+    }
+    // An output switch is set if its first output comes from the bottom.
+    for pair in 0..half {
+        if count % 2 == 1 || pair < half - 1 {
+            switches.push(bottom[2 * pair]);
+        }
+    }
+}
+
+fn next_switch<I: Iterator<Item = WireId>>(switches: &mut I) -> WireId {
+    switches.next().expect("wrong number of switches")
+}
+
+/// Returns the switch settings of a Waksman network for a uniformly random permutation of
+/// `count` items.
+pub fn random_waksman_switches<R: RngCore + CryptoRng>(count: usize, rng: &mut R) -> Vec<bool> {
+    let mut permutation = (0..count).collect::<Vec<usize>>();
+    permutation.shuffle(rng);
+    waksman_switches(&permutation)
+}
+
+/// Permutes elements with a permutation that only one party knows. That party passes its
+/// permutation, which moves the element at `permutation[i]` to position `i`, and inputs the
+/// switch settings through its input path. The other party passes `None`.
+pub fn permute_elements<E: Executor>(
+    elements: Vec<Vec<E::Key>>,
+    permutation: Option<&[usize]>,
+    executor: &mut E,
+) -> Result<Vec<Vec<E::Key>>, ProtocolError> {
+    let count = elements.len();
+    let switches = match permutation {
+        Some(permutation) => {
+            assert_eq!(
+                permutation.len(),
+                count,
+                "the permutation has the wrong length"
+            );
+            executor.input(&waksman_switches(permutation))?
+        }
+        None => executor.peer_input(waksman_switch_count(count))?,
+    };
+    let bits = elements.first().map_or(0, |element| element.len());
+    let mut inputs = vec![switches];
+    inputs.extend(elements);
+    executor.execute(&permute_circuit(count, bits), inputs)
+}
+
+/// Compiles a Waksman network. The input groups are the switches and then the elements. The
+/// output groups are the permuted elements.
+fn permute_circuit(count: usize, bits: usize) -> Circuit {
+    let mut builder = CircuitBuilder::new();
+    let switches = builder.input(0, waksman_switch_count(count) as u32);
+    let elements = (0..count)
+        .map(|_| builder.input(0, bits as u32))
+        .collect::<Vec<Vec<WireId>>>();
+    for element in builder.waksman_permute(&elements, &switches) {
+        builder.output(&element);
+    }
+    builder.build()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        assign,
+        executor::{Evaluator, Garbler},
+        free_xor_garbling::FreeXorBit,
+        mpc_core::{
+            EvaluatingWire, GarblingSession, GarblingWire, Party, Protocol, Role, SecretWire,
+            WireValue,
+        },
+        ot::OtKind,
+        reveal,
+        wires::Wire8Bit,
+    };
+    use ocelot::ot::{ChouOrlandiReceiver, ChouOrlandiSender, Receiver, Sender};
+    use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+    use scuttlebutt::{AesRng, Channel, TrackChannel};
+    use std::{
+        io::{BufReader, BufWriter},
+        os::unix::net::UnixStream,
+    };
+    const SEED: [u8; 32] = [42u8; 32];
+
+    #[test]
+    fn test_waksman_permute() {
+        let mut rng = StdRng::from_seed(SEED);
+        for count in 0..20 {
+            let mut builder = CircuitBuilder::new();
+            let items = (0..count).map(|_| builder.input(1, 8)).collect::<Vec<_>>();
+            let switches = builder.input(2, waksman_switch_count(count) as u32);
+            for item in builder.waksman_permute(&items, &switches) {
+                builder.output(&item);
+            }
+            let circuit = builder.build();
+            assert_eq!(circuit.and_count(), 8 * waksman_switch_count(count));
+
+            let mut builder = CircuitBuilder::new();
+            let items = (0..count).map(|_| builder.input(1, 8)).collect::<Vec<_>>();
+            let switches = builder.input(2, waksman_switch_count(count) as u32);
+            for item in builder.waksman_unpermute(&items, &switches) {
+                builder.output(&item);
+            }
+            let inverse = builder.build();
+            assert_eq!(inverse.and_count(), circuit.and_count());
+
+            for _ in 0..10 {
+                let mut permutation = (0..count).collect::<Vec<usize>>();
+                permutation.shuffle(&mut rng);
+                let mut inputs = (0..count)
+                    .map(|index| (index as u8 * 7).to_bits())
+                    .collect::<Vec<_>>();
+                inputs.push(waksman_switches(&permutation));
+                let got = circuit
+                    .compute(&inputs)
+                    .iter()
+                    .map(|bits| u8::from_bits(bits))
+                    .collect::<Vec<u8>>();
+                let want = permutation
+                    .iter()
+                    .map(|index| *index as u8 * 7)
+                    .collect::<Vec<u8>>();
+                assert_eq!(got, want, "{:?}", permutation);
+
+                let mut inputs = want.iter().map(|value| value.to_bits()).collect::<Vec<_>>();
+                inputs.push(waksman_switches(&permutation));
+                let got = inverse
+                    .compute(&inputs)
+                    .iter()
+                    .map(|bits| u8::from_bits(bits))
+                    .collect::<Vec<u8>>();
+                let want = (0..count).map(|index| index as u8 * 7).collect::<Vec<u8>>();
+                assert_eq!(got, want, "{:?}", permutation);
+            }
+        }
+    }
+
+    #[test]
+    fn test_shuffle_with_both_permutations() {
+        const COUNT: usize = 10;
+        let mut rng = StdRng::from_seed(SEED);
+        let mut garbler_permutation = (0..COUNT).collect::<Vec<usize>>();
+        garbler_permutation.shuffle(&mut rng);
+        let mut evaluator_permutation = (0..COUNT).collect::<Vec<usize>>();
+        evaluator_permutation.shuffle(&mut rng);
+        let values = (0..COUNT as u16)
+            .map(|index| index * 11)
+            .collect::<Vec<u16>>();
+        let shuffled = evaluator_permutation
+            .iter()
+            .map(|index| values[garbler_permutation[*index]])
+            .collect::<Vec<u16>>();
+
+        let (sender, receiver) = UnixStream::pair().unwrap();
+        let garbler_values = values.clone();
+        let handle = std::thread::spawn(move || {
+            let mut rng = AesRng::new();
+            let reader = BufReader::new(sender.try_clone().unwrap());
+            let writer = BufWriter::new(sender);
+            let mut channel = TrackChannel::new(reader, writer);
+            let mut session = GarblingSession::new(&mut rng);
+            let mut ot = ChouOrlandiSender::init(&mut channel, &mut rng).unwrap();
+            let mut garbler =
+                Garbler::<FreeXorBit, _, _, _>::new(&mut channel, &mut ot, &mut session, &mut rng);
+
+            let elements = garbler_values
+                .iter()
+                .map(|value| garbler.input(&value.to_bits()).unwrap())
+                .collect::<Vec<_>>();
+            let elements =
+                permute_elements(elements, Some(&garbler_permutation), &mut garbler).unwrap();
+            let elements = permute_elements(elements, None, &mut garbler).unwrap();
+            elements
+                .iter()
+                .map(|element| u16::from_bits(&garbler.reveal(element).unwrap()))
+                .collect::<Vec<u16>>()
+        });
+
+        let mut rng = AesRng::new();
+        let reader = BufReader::new(receiver.try_clone().unwrap());
+        let writer = BufWriter::new(receiver);
+        let mut channel = TrackChannel::new(reader, writer);
+        let mut ot = ChouOrlandiReceiver::init(&mut channel, &mut rng).unwrap();
+        let mut evaluator = Evaluator::<FreeXorBit, _, _, _>::new(&mut channel, &mut ot, &mut rng);
+
+        let elements = (0..COUNT)
+            .map(|_| evaluator.peer_input(16).unwrap())
+            .collect::<Vec<_>>();
+        let elements = permute_elements(elements, None, &mut evaluator).unwrap();
+        let elements =
+            permute_elements(elements, Some(&evaluator_permutation), &mut evaluator).unwrap();
+        let got = elements
+            .iter()
+            .map(|element| u16::from_bits(&evaluator.reveal(element).unwrap()))
+            .collect::<Vec<u16>>();
+        assert_eq!(got, shuffled);
+        assert_eq!(handle.join().unwrap(), shuffled);
+    }
+
+    #[test]
+    fn test_permute_assigned_values() {
+        // The wires of assign! go through the protocol's executor, and reveal! reads the result.
+        let permutation = vec![2, 0, 3, 1];
+        let (sender, receiver) = UnixStream::pair().unwrap();
+        let handle = std::thread::spawn(move || {
+            // Garbler
+            let reader = BufReader::new(sender.try_clone().unwrap());
+            let writer = BufWriter::new(sender);
+            let channel = Channel::new(reader, writer);
+            let alice = Party { id: 1 };
+            let bob = Party { id: 2 };
+            let mut protocol = Protocol::new(
+                vec![alice.clone(), bob],
+                alice,
+                Role::Garbler,
+                channel,
+                AesRng::new(),
+                OtKind::ChouOrlandi,
+            )
+            .unwrap();
+            let mut elements = assign!([a = 10, b = 20], protocol, FreeXorBit, Wire8Bit).unwrap();
+            elements.extend(assign!([c, d] <- party 2, protocol, FreeXorBit, Wire8Bit).unwrap());
+            let elements = elements
+                .into_iter()
+                .map(|element| element.garbling().unwrap().bits)
+                .collect::<Vec<_>>();
+            let elements =
+                permute_elements(elements, Some(&permutation), &mut protocol.garbler()).unwrap();
+            elements
+                .into_iter()
+                .map(|element| {
+                    let element =
+                        SecretWire::<_, Wire8Bit>::Garbling(GarblingWire::from_bits(element));
+                    reveal!(element, protocol).unwrap().unwrap()
+                })
+                .collect::<Vec<u8>>()
+        });
+
+        // Evaluator
+        let reader = BufReader::new(receiver.try_clone().unwrap());
+        let writer = BufWriter::new(receiver);
+        let channel = Channel::new(reader, writer);
+        let alice = Party { id: 1 };
+        let bob = Party { id: 2 };
+        let mut protocol = Protocol::new(
+            vec![alice, bob.clone()],
+            bob,
+            Role::Evaluator,
+            channel,
+            AesRng::new(),
+            OtKind::ChouOrlandi,
+        )
+        .unwrap();
+        let mut elements = assign!([a, b] <- party 1, protocol, FreeXorBit, Wire8Bit).unwrap();
+        elements.extend(assign!([c = 30, d = 40], protocol, FreeXorBit, Wire8Bit).unwrap());
+        let elements = elements
+            .into_iter()
+            .map(|element| element.evaluating().unwrap().bits)
+            .collect::<Vec<_>>();
+        let elements = permute_elements(elements, None, &mut protocol.evaluator()).unwrap();
+        let got = elements
+            .into_iter()
+            .map(|bits| {
+                let element =
+                    SecretWire::<FreeXorBit, Wire8Bit>::Evaluating(EvaluatingWire { bits });
+                reveal!(element, protocol).unwrap().unwrap()
+            })
+            .collect::<Vec<u8>>();
+        assert_eq!(got, vec![30, 10, 40, 20]);
+        assert_eq!(handle.join().unwrap(), got);
+    }
+}
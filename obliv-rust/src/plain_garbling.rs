@@ -1,11 +1,14 @@
 //! Implements the Plain (insecure) garbling mode that can be used for debug purposes.
 
 use crate::{
-    mpc_core::{EvaluatingWire, GarblingMode, GarblingWire, Gate, Operation},
+    mpc_core::{
+        evaluate_gate, garble_gate, DecodingInfo, Delta, EvaluatingWire, GarblingMode,
+        GarblingSession, GarblingWire, Gate, GateGarbling, Operation, Wire,
+    },
     wires::Wire8Bit,
 };
 use rand_core::{CryptoRng, RngCore};
-use scuttlebutt::Block;
+use scuttlebutt::{AesRng, Block};
 use serde::{Deserialize, Serialize};
 
 // ----------------------------------------------------------------------------------------------
@@ -24,113 +27,135 @@ impl GarblingMode for PlainBit {
     fn to_block(&self) -> Block {
         self.0
     }
+
+    fn from_block(block: Block) -> Self {
+        Self(block)
+    }
 }
 
-// ----------------------------------------------------------------------------------------------
-//                              Garble and Evaluate Operations                                  -
-// ----------------------------------------------------------------------------------------------
+/// Plain keys are the values themselves, so the gates are computed in the clear and no garbled
+/// table is ever needed.
+impl GateGarbling for PlainBit {
+    fn garble_and<R: RngCore + CryptoRng>(
+        _input_1: &(Self, Self),
+        _input_2: &(Self, Self),
+        _id: u64,
+        _delta: &Delta,
+        rng: &mut R,
+    ) -> ((Self, Self), Vec<Block>) {
+        (Self::pair(rng), vec![])
+    }
 
-pub fn garble_u8_gate_plain(
-    input_1: GarblingWire<PlainBit, Wire8Bit>,
-    input_2: GarblingWire<PlainBit, Wire8Bit>,
-    operation: Operation,
-) -> (
-    GarblingWire<PlainBit, Wire8Bit>,
-    Vec<Gate<PlainBit, Wire8Bit>>,
-) {
-    // use another macro to generate the gates
-    match operation {
-        Operation::AddU8 => garble_add_u8_plain_scheme(input_1, input_2),
-        Operation::MulU8 => garble_mul_u8_plain_scheme(input_1, input_2),
+    fn evaluate_and(input_1: &Self, input_2: &Self, _id: u64, _table: &[Block]) -> Self {
+        Self(input_1.0 & input_2.0)
     }
-}
 
-pub fn evaluate_plain(
-    input_1: EvaluatingWire<PlainBit>,
-    input_2: EvaluatingWire<PlainBit>,
-    operation: Operation,
-    gates: Vec<Gate<PlainBit, Wire8Bit>>,
-) -> EvaluatingWire<PlainBit> {
-    // use another macro to generate the gates
-    match operation {
-        Operation::AddU8 => evaluate_add_u8_plain_scheme(input_1, input_2, gates),
-        Operation::MulU8 => evaluate_mul_u8_plain_scheme(input_1, input_2, gates),
+    fn garble_xor<R: RngCore + CryptoRng>(
+        _input_1: &(Self, Self),
+        _input_2: &(Self, Self),
+        _id: u64,
+        _delta: &Delta,
+        rng: &mut R,
+    ) -> ((Self, Self), Vec<Block>) {
+        (Self::pair(rng), vec![])
+    }
+
+    fn evaluate_xor(input_1: &Self, input_2: &Self, _id: u64, _table: &[Block]) -> Self {
+        Self(input_1.0 ^ input_2.0)
+    }
+
+    fn garble_not(input: &(Self, Self)) -> (Self, Self) {
+        input.clone()
+    }
+
+    fn evaluate_not(input: &Self) -> Self {
+        Self(input.0 ^ Block::default().set_lsb())
     }
 }
 
 // ----------------------------------------------------------------------------------------------
-//                                   Internal Functions                                         -
+//                              Garble and Evaluate Operations                                  -
 // ----------------------------------------------------------------------------------------------
 
-fn garble_add_u8_plain_scheme(
-    input_1: GarblingWire<PlainBit, Wire8Bit>,
-    _input_2: GarblingWire<PlainBit, Wire8Bit>,
-) -> (
-    GarblingWire<PlainBit, Wire8Bit>,
-    Vec<Gate<PlainBit, Wire8Bit>>,
-) {
-    // Plain wires are always 0 and 1 so it does not matter whether we generate a new output or
-    // reuse input.
-    (
-        input_1.clone(),
-        vec![Gate::<PlainBit, Wire8Bit> { output: input_1 }],
-    )
-}
-
-fn garble_mul_u8_plain_scheme(
+/// Garbles an operation in the plain mode. Plain keys are fixed, so neither the session nor the
+/// rng carry any secret and they are not exposed to the caller.
+pub fn garble_u8_gate_plain(
     input_1: GarblingWire<PlainBit, Wire8Bit>,
-    _input_2: GarblingWire<PlainBit, Wire8Bit>,
+    input_2: GarblingWire<PlainBit, Wire8Bit>,
+    operation: Operation,
 ) -> (
     GarblingWire<PlainBit, Wire8Bit>,
     Vec<Gate<PlainBit, Wire8Bit>>,
 ) {
-    (input_1, vec![])
+    let mut rng = AesRng::new();
+    let mut session = GarblingSession::new(&mut rng);
+    garble_gate(input_1, input_2, operation, &mut session, &mut rng)
 }
 
-fn evaluate_add_u8_plain_scheme(
+pub fn evaluate_plain(
     input_1: EvaluatingWire<PlainBit>,
     input_2: EvaluatingWire<PlainBit>,
+    operation: Operation,
     gates: Vec<Gate<PlainBit, Wire8Bit>>,
 ) -> EvaluatingWire<PlainBit> {
-    let sum = to_u8(&input_1) + to_u8(&input_2);
-    gates[0].clone().output.encode(sum)
-}
-
-fn evaluate_mul_u8_plain_scheme(
-    input_1: EvaluatingWire<PlainBit>,
-    _input_2: EvaluatingWire<PlainBit>,
-    _gates: Vec<Gate<PlainBit, Wire8Bit>>,
-) -> EvaluatingWire<PlainBit> {
-    input_1
+    evaluate_gate(input_1, input_2, operation, gates)
 }
 
-pub(crate) fn to_u8(garbled_value: &EvaluatingWire<PlainBit>) -> u8 {
-    garbled_value
-        .bits
-        .iter()
-        .rev()
-        .fold((0, 0), |(acc, idx), p_bit| {
-            let bit = if p_bit.0 == Block::default() { 0 } else { 1 };
-            (acc + 2u8.pow(idx) * bit, idx + 1)
-        })
-        .0
+/// Decodes a plain wire. Plain keys are the values themselves, so no decoding information is
+/// needed.
+pub fn decode<W: Wire>(garbled_value: &EvaluatingWire<PlainBit>) -> W::ValueType {
+    DecodingInfo::<W>::new(vec![false; garbled_value.bits.len()]).decode(garbled_value)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::mpc_core::{
-        to_bit_arr, EvaluatingWire, GarblingWire, Gate, Operation, Party, Protocol, Role,
+    use crate::{
+        assign,
+        mpc_core::{GarblingWire, Gate, Operation, Party, Protocol, Role, SecretWire},
+        ot::OtKind,
+        reveal,
+        wires::Wire32Bit,
     };
-    use ocelot::ot::{ChouOrlandiReceiver, ChouOrlandiSender, Receiver, Sender};
-    use rand::{rngs::StdRng, SeedableRng};
+    use rand::{rngs::StdRng, Rng, SeedableRng};
     use scuttlebutt::{AbstractChannel, AesRng, Block, TrackChannel};
     use std::{
-        io::{BufReader, BufWriter},
+        cell::Cell,
+        io::{self, BufReader, BufWriter},
         os::unix::net::UnixStream,
+        rc::Rc,
     };
     const SEED: [u8; 32] = [42u8; 32];
 
+    /// Counts the flushes of a channel. Every round of a protocol ends with a flush, so it shows
+    /// how many messages a step sends.
+    struct FlushCounter<C> {
+        channel: C,
+        flushes: Rc<Cell<usize>>,
+    }
+
+    impl<C: AbstractChannel> AbstractChannel for FlushCounter<C> {
+        fn read_bytes(&mut self, bytes: &mut [u8]) -> io::Result<()> {
+            self.channel.read_bytes(bytes)
+        }
+
+        fn write_bytes(&mut self, bytes: &[u8]) -> io::Result<()> {
+            self.channel.write_bytes(bytes)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            self.flushes.set(self.flushes.get() + 1);
+            self.channel.flush()
+        }
+
+        fn clone(&self) -> Self {
+            FlushCounter {
+                channel: self.channel.clone(),
+                flushes: self.flushes.clone(),
+            }
+        }
+    }
+
     #[test]
     fn test_plain_garbling() {
         let mut rng = StdRng::from_seed(SEED);
@@ -147,12 +172,12 @@ mod tests {
     }
 
     #[test]
-    fn test_plain_decode_to_u8() {
+    fn test_plain_decode() {
         let mut rng = StdRng::from_seed(SEED);
 
         let garbled_wires = GarblingWire::<PlainBit, Wire8Bit>::new(&mut rng);
         let garbled_value = garbled_wires.encode(6);
-        let got = to_u8(&garbled_value);
+        let got = decode::<Wire8Bit>(&garbled_value);
         assert_eq!(6, got);
     }
 
@@ -164,10 +189,10 @@ mod tests {
         let garbled_value_1 = garbled_wires_1.clone().encode(6);
         let garbled_wires_2 = GarblingWire::<PlainBit, Wire8Bit>::new(&mut rng);
         let garbled_value_2 = garbled_wires_2.clone().encode(6);
-        let (_, gates) = garble_add_u8_plain_scheme(garbled_wires_1, garbled_wires_2);
-        let result = evaluate_add_u8_plain_scheme(garbled_value_1, garbled_value_2, gates);
+        let (_, gates) = garble_u8_gate_plain(garbled_wires_1, garbled_wires_2, Operation::Add);
+        let result = evaluate_plain(garbled_value_1, garbled_value_2, Operation::Add, gates);
 
-        let got = to_u8(&result);
+        let got = decode::<Wire8Bit>(&result);
 
         assert_eq!(12, got);
     }
@@ -176,21 +201,17 @@ mod tests {
     fn test_serde_plain_gate() {
         let mut rng = StdRng::from_seed(SEED);
 
-        let garbled_wires = GarblingWire::<PlainBit, Wire8Bit>::new(&mut rng);
-        let gate = Gate::<PlainBit, Wire8Bit> {
-            output: garbled_wires,
-        };
+        let garbled_wires_1 = GarblingWire::<PlainBit, Wire8Bit>::new(&mut rng);
+        let garbled_wires_2 = GarblingWire::<PlainBit, Wire8Bit>::new(&mut rng);
+        let (_, gates) = garble_u8_gate_plain(garbled_wires_1, garbled_wires_2, Operation::Add);
 
-        let serialized_gate = bincode::serialize(&gate).unwrap();
-        let deserialized_gate: Gate<PlainBit, Wire8Bit> =
-            bincode::deserialize(&serialized_gate).unwrap();
-        assert!(gate
-            .clone()
-            .output
-            .bits
+        let serialized_gates = bincode::serialize(&gates).unwrap();
+        let deserialized_gates: Vec<Gate<PlainBit, Wire8Bit>> =
+            bincode::deserialize(&serialized_gates).unwrap();
+        assert!(gates
             .into_iter()
-            .zip(deserialized_gate.output.bits)
-            .fold(true, |acc, (want, got)| acc || (want.0 == got.0)));
+            .zip(deserialized_gates)
+            .all(|(want, got)| want.id == got.id && want.tables == got.tables));
     }
 
     #[test]
@@ -230,37 +251,28 @@ mod tests {
             // ------------------ Start of the Garbler
             let alice = Party { id: 1 };
             let bob = Party { id: 2 };
-            let mut protocol = Protocol {
-                parties: vec![alice.clone(), bob],
-                me: alice,
-                role: Role::Garbler,
+            let mut protocol = Protocol::new(
+                vec![alice.clone(), bob],
+                alice,
+                Role::Garbler,
                 channel,
                 rng,
-            };
-
-            // assign!(a1 <- party 1, value 10);
-            let a1 = GarblingWire::<PlainBit, Wire8Bit>::new(&mut protocol.rng);
-            let garbled_value_a1 = a1.clone().encode(10);
-            let ser = bincode::serialize(&garbled_value_a1).unwrap();
-            protocol.channel.write_usize(ser.len()).unwrap();
-            protocol.channel.write_bytes(&ser).unwrap();
+                OtKind::TrustedDealer,
+            )
+            .unwrap();
 
-            // assign!(a2 <- party 1, value 20);
-            let a2 = GarblingWire::<PlainBit, Wire8Bit>::new(&mut protocol.rng);
-            let garbled_value_a2 = a2.clone().encode(20);
-            let ser = bincode::serialize(&garbled_value_a2).unwrap();
-            protocol.channel.write_usize(ser.len()).unwrap();
-            protocol.channel.write_bytes(&ser).unwrap();
+            let a1 = assign!(a1, 10, protocol, PlainBit, Wire8Bit).unwrap();
+            let a2 = assign!(a2, 20, protocol, PlainBit, Wire8Bit).unwrap();
+            let (a1, a2) = (a1.garbling().unwrap(), a2.garbling().unwrap());
 
             // obliv!(g = a1 + a2);
-            let (_, gates) = garble_u8_gate_plain(a1, a2, Operation::AddU8);
+            let (g, gates) = garble_u8_gate_plain(a1, a2, Operation::Add);
             let ser = bincode::serialize(&gates).unwrap();
             protocol.channel.write_usize(ser.len()).unwrap();
             protocol.channel.write_bytes(&ser).unwrap();
 
-            // reveal!(g);
-            // TODO: send decoding
-            // TODO: receive the plain value
+            let g = SecretWire::Garbling(g);
+            assert_eq!(reveal!(g, protocol).unwrap(), Some(30));
         });
 
         // Evaluator
@@ -271,33 +283,29 @@ mod tests {
         // ------------------ Start of evaluator
         let alice = Party { id: 1 };
         let bob = Party { id: 2 };
-        let mut protocol = Protocol {
-            parties: vec![alice, bob.clone()],
-            me: bob,
-            role: Role::Evaluator,
+        let mut protocol = Protocol::new(
+            vec![alice, bob.clone()],
+            bob,
+            Role::Evaluator,
             channel,
             rng,
-        };
-        // assign!(a1 <- party 1);
-        let size = protocol.channel.read_usize().unwrap();
-        let ser = protocol.channel.read_vec(size).unwrap();
-        let a1: EvaluatingWire<PlainBit> = bincode::deserialize(&ser).unwrap();
-
-        // assign!(a2 <- party 1);
-        let size = protocol.channel.read_usize().unwrap();
-        let ser = protocol.channel.read_vec(size).unwrap();
-        let a2: EvaluatingWire<PlainBit> = bincode::deserialize(&ser).unwrap();
+            OtKind::TrustedDealer,
+        )
+        .unwrap();
+        let a1 = assign!(a1 <- party 1, protocol, PlainBit, Wire8Bit).unwrap();
+        let a2 = assign!(a2 <- party 1, protocol, PlainBit, Wire8Bit).unwrap();
+        let (a1, a2) = (a1.evaluating().unwrap(), a2.evaluating().unwrap());
 
         // obliv!(g = a1 + a2);
         let size = protocol.channel.read_usize().unwrap();
         let ser = protocol.channel.read_vec(size).unwrap();
         let gates: Vec<Gate<PlainBit, Wire8Bit>> = bincode::deserialize(&ser).unwrap();
-        let g = evaluate_plain(a1, a2, Operation::AddU8, gates);
+        let g = evaluate_plain(a1, a2, Operation::Add, gates);
 
-        // reveal!(g);
-        // TODO receive decoding and decode.
-        let plain_g = to_u8(&g);
+        let plain_g = decode::<Wire8Bit>(&g);
         assert_eq!(plain_g, 30);
+        let g = SecretWire::<PlainBit, Wire8Bit>::Evaluating(g);
+        assert_eq!(reveal!(g, protocol).unwrap(), Some(30));
 
         handle.join().unwrap();
         println!(
@@ -323,75 +331,56 @@ mod tests {
             // ------------------ Start of the Garbler
             let alice = Party { id: 1 };
             let bob = Party { id: 2 };
-            let mut protocol = Protocol {
-                parties: vec![alice.clone(), bob],
-                me: alice,
-                role: Role::Garbler,
+            let mut protocol = Protocol::new(
+                vec![alice.clone(), bob],
+                alice,
+                Role::Garbler,
                 channel,
                 rng,
-            };
-            let mut ot = ChouOrlandiSender::init(&mut protocol.channel, &mut protocol.rng).unwrap();
-
-            // assign!(a1 <- party 1, value 10);
-            let a1 = GarblingWire::<PlainBit, Wire8Bit>::new(&mut protocol.rng);
-            let garbled_value_a1 = a1.clone().encode(10);
-            let ser = bincode::serialize(&garbled_value_a1).unwrap();
-            protocol.channel.write_usize(ser.len()).unwrap();
-            protocol.channel.write_bytes(&ser).unwrap();
-            protocol.channel.flush().unwrap();
-
-            // assign!(a2 <- party 1, value 20);
-            let a2 = GarblingWire::<PlainBit, Wire8Bit>::new(&mut protocol.rng);
-            let garbled_value_a2 = a2.clone().encode(20);
-            let ser = bincode::serialize(&garbled_value_a2).unwrap();
-            protocol.channel.write_usize(ser.len()).unwrap();
-            protocol.channel.write_bytes(&ser).unwrap();
-            protocol.channel.flush().unwrap();
-
-            // assign!(b1 <- party 2);
-            let b1 = GarblingWire::<PlainBit, Wire8Bit>::new(&mut protocol.rng);
-            ot.send(
-                &mut protocol.channel,
-                &b1.clone().to_blocks(),
-                &mut protocol.rng,
+                OtKind::Kos,
             )
             .unwrap();
 
-            // assign!(b2 <- party 2);
-            let b2 = GarblingWire::<PlainBit, Wire8Bit>::new(&mut protocol.rng);
-            ot.send(
-                &mut protocol.channel,
-                &b2.clone().to_blocks(),
-                &mut protocol.rng,
-            )
-            .unwrap();
+            let a1 = assign!(a1, 10, protocol, PlainBit, Wire8Bit)
+                .unwrap()
+                .garbling()
+                .unwrap();
+            let a2 = assign!(a2, 20, protocol, PlainBit, Wire8Bit)
+                .unwrap()
+                .garbling()
+                .unwrap();
+            let mut bs = assign!([b1, b2] <- party 2, protocol, PlainBit, Wire8Bit)
+                .unwrap()
+                .into_iter()
+                .map(|b| b.garbling().unwrap());
+            let (b1, b2) = (bs.next().unwrap(), bs.next().unwrap());
 
             // -------------------------- Proceed to garbling deeper layers next.
 
             // obliv!(c = a1 + b1);
-            let (c, gates) = garble_u8_gate_plain(a1, b1, Operation::AddU8);
+            let (c, gates) = garble_u8_gate_plain(a1, b1, Operation::Add);
             let ser = bincode::serialize(&gates).unwrap();
             protocol.channel.write_usize(ser.len()).unwrap();
             protocol.channel.write_bytes(&ser).unwrap();
             protocol.channel.flush().unwrap();
 
             // obliv!(d = a2 + b2);
-            let (d, gates) = garble_u8_gate_plain(a2, b2, Operation::AddU8);
+            let (d, gates) = garble_u8_gate_plain(a2, b2, Operation::Add);
             let ser = bincode::serialize(&gates).unwrap();
             protocol.channel.write_usize(ser.len()).unwrap();
             protocol.channel.write_bytes(&ser).unwrap();
             protocol.channel.flush().unwrap();
 
             // obliv!(e = c * d);
-            let (_e, gates) = garble_u8_gate_plain(c, d, Operation::AddU8);
+            let (e, gates) = garble_u8_gate_plain(c, d, Operation::Add);
             let ser = bincode::serialize(&gates).unwrap();
             protocol.channel.write_usize(ser.len()).unwrap();
             protocol.channel.write_bytes(&ser).unwrap();
             protocol.channel.flush().unwrap();
 
-            //// reveal!(e);
-            //// TODO send(gates.mapping);
-            //// TODO receive(plain_e);
+            let e = SecretWire::Garbling(e);
+            assert_eq!(reveal!(e -> party 1, protocol).unwrap(), Some(85));
+            assert_eq!(reveal!(e -> party 2, protocol).unwrap(), None);
         });
         let rng = AesRng::new();
         let reader = BufReader::new(receiver.try_clone().unwrap());
@@ -402,77 +391,149 @@ mod tests {
 
         let alice = Party { id: 1 };
         let bob = Party { id: 2 };
-        let mut protocol = Protocol {
-            parties: vec![alice, bob.clone()],
-            me: bob,
-            role: Role::Evaluator,
+        let mut protocol = Protocol::new(
+            vec![alice, bob.clone()],
+            bob,
+            Role::Evaluator,
             channel,
             rng,
-        };
-        let mut ot = ChouOrlandiReceiver::init(&mut protocol.channel, &mut protocol.rng).unwrap();
+            OtKind::Kos,
+        )
+        .unwrap();
 
-        // assign!(a1 <- party 1);
-        let size = protocol.channel.read_usize().unwrap();
-        let ser = protocol.channel.read_vec(size).unwrap();
-        let a1: EvaluatingWire<PlainBit> = bincode::deserialize(&ser).unwrap();
-        assert_eq!(to_u8(&a1), 10, "a1");
-
-        // assign!(a2 <- party 1);
-        let size = protocol.channel.read_usize().unwrap();
-        let ser = protocol.channel.read_vec(size).unwrap();
-        let a2: EvaluatingWire<PlainBit> = bincode::deserialize(&ser).unwrap();
-        assert_eq!(to_u8(&a2), 20, "a2");
-
-        // assign!(b1 <- party 2, value 25);
-        let bs = to_bit_arr(25, 8);
-        let results = ot
-            .receive(&mut protocol.channel, &bs, &mut protocol.rng)
+        let a1 = assign!(a1 <- party 1, protocol, PlainBit, Wire8Bit)
+            .unwrap()
+            .evaluating()
             .unwrap();
-        let bits = results
-            .into_iter()
-            .map(|block| PlainBit(block))
-            .collect::<Vec<PlainBit>>();
-        let b1 = EvaluatingWire::<PlainBit> { bits };
-        println!("{:?}", bs);
-        assert_eq!(to_u8(&b1), 25, "b1");
-
-        // assign!(b2 <- party 2, value 30);
-        let bs = to_bit_arr(30, 8);
-        let results = ot
-            .receive(&mut protocol.channel, &bs, &mut protocol.rng)
+        assert_eq!(decode::<Wire8Bit>(&a1), 10, "a1");
+        let a2 = assign!(a2 <- party 1, protocol, PlainBit, Wire8Bit)
+            .unwrap()
+            .evaluating()
             .unwrap();
-        let bits = results
+        assert_eq!(decode::<Wire8Bit>(&a2), 20, "a2");
+        let mut bs = assign!([b1 = 25, b2 = 30], protocol, PlainBit, Wire8Bit)
+            .unwrap()
             .into_iter()
-            .map(|block| PlainBit(block))
-            .collect::<Vec<PlainBit>>();
-        let b2 = EvaluatingWire::<PlainBit> { bits };
-        assert_eq!(to_u8(&b2), 30, "b2");
+            .map(|b| b.evaluating().unwrap());
+        let (b1, b2) = (bs.next().unwrap(), bs.next().unwrap());
+        assert_eq!(decode::<Wire8Bit>(&b1), 25, "b1");
+        assert_eq!(decode::<Wire8Bit>(&b2), 30, "b2");
 
         // obliv!(c = a1 + b1);
         let size = protocol.channel.read_usize().unwrap();
         let ser = protocol.channel.read_vec(size).unwrap();
         let gates: Vec<Gate<PlainBit, Wire8Bit>> = bincode::deserialize(&ser).unwrap();
-        let c = evaluate_plain(a1, b1, Operation::AddU8, gates);
-        assert_eq!(to_u8(&c), 35, "c");
+        let c = evaluate_plain(a1, b1, Operation::Add, gates);
+        assert_eq!(decode::<Wire8Bit>(&c), 35, "c");
 
         // obliv!(d = a2 + b2);
         let size = protocol.channel.read_usize().unwrap();
         let ser = protocol.channel.read_vec(size).unwrap();
         let gates: Vec<Gate<PlainBit, Wire8Bit>> = bincode::deserialize(&ser).unwrap();
-        let d = evaluate_plain(a2, b2, Operation::AddU8, gates);
-        assert_eq!(to_u8(&d), 50, "d");
+        let d = evaluate_plain(a2, b2, Operation::Add, gates);
+        assert_eq!(decode::<Wire8Bit>(&d), 50, "d");
 
         // obliv!(e = c * d);
         let size = protocol.channel.read_usize().unwrap();
         let ser = protocol.channel.read_vec(size).unwrap();
         let gates: Vec<Gate<PlainBit, Wire8Bit>> = bincode::deserialize(&ser).unwrap();
-        let e = evaluate_plain(c, d, Operation::AddU8, gates);
-        assert_eq!(to_u8(&e), 85, "e");
+        let e = evaluate_plain(c, d, Operation::Add, gates);
+        assert_eq!(decode::<Wire8Bit>(&e), 85, "e");
 
-        //// reveal!(e);
-        //// TODO send(gates.mapping);
-        //// TODO receive(plain_e);
+        let e = SecretWire::<PlainBit, Wire8Bit>::Evaluating(e);
+        assert_eq!(reveal!(e -> party 1, protocol).unwrap(), None);
+        assert_eq!(reveal!(e -> party 2, protocol).unwrap(), Some(85));
 
         handle.join().unwrap();
     }
+
+    #[test]
+    fn test_batched_inputs_with_ot_extension() {
+        let mut rng = StdRng::from_seed(SEED);
+        let garbler_values = (0..100).map(|_| rng.gen::<u32>()).collect::<Vec<u32>>();
+        let evaluator_values = (0..256).map(|_| rng.gen::<u32>()).collect::<Vec<u32>>();
+        let expected_values = garbler_values.clone();
+        let (sender, receiver) = UnixStream::pair().unwrap();
+        let handle = std::thread::spawn(move || {
+            // Garbler
+            let reader = BufReader::new(sender.try_clone().unwrap());
+            let writer = BufWriter::new(sender);
+            let channel = TrackChannel::new(reader, writer);
+            let alice = Party { id: 1 };
+            let bob = Party { id: 2 };
+            let mut protocol = Protocol::new(
+                vec![alice.clone(), bob],
+                alice,
+                Role::Garbler,
+                channel,
+                AesRng::new(),
+                OtKind::Kos,
+            )
+            .unwrap();
+            let mine = protocol
+                .garbler_inputs::<PlainBit, Wire32Bit>(&garbler_values)
+                .unwrap();
+            let theirs = protocol
+                .garbler_peer_inputs::<PlainBit, Wire32Bit>(256)
+                .unwrap();
+            let more = protocol
+                .garbler_peer_inputs::<PlainBit, Wire32Bit>(256)
+                .unwrap();
+            assert_eq!(mine.len(), 100);
+            assert_eq!(theirs.len(), 256);
+            assert_eq!(more.len(), 256);
+        });
+
+        // Evaluator
+        let reader = BufReader::new(receiver.try_clone().unwrap());
+        let writer = BufWriter::new(receiver);
+        let flushes = Rc::new(Cell::new(0));
+        let channel = FlushCounter {
+            channel: TrackChannel::new(reader, writer),
+            flushes: flushes.clone(),
+        };
+        let alice = Party { id: 1 };
+        let bob = Party { id: 2 };
+        let mut protocol = Protocol::new(
+            vec![alice, bob.clone()],
+            bob,
+            Role::Evaluator,
+            channel,
+            AesRng::new(),
+            OtKind::Kos,
+        )
+        .unwrap();
+        let theirs = protocol
+            .evaluator_peer_inputs::<PlainBit, Wire32Bit>(100)
+            .unwrap();
+
+        // The base OTs run once in `Protocol::new`, and every batch of inputs is a single
+        // extension with a few rounds. One OT per value would take at least 256 rounds.
+        let mut batch = |values: &[u32]| {
+            let written = protocol.channel.channel.kilobits_written();
+            let rounds = flushes.get();
+            let wires = protocol
+                .evaluator_inputs::<PlainBit, Wire32Bit>(values)
+                .unwrap();
+            let written = protocol.channel.channel.kilobits_written() - written;
+            (wires, written, flushes.get() - rounds)
+        };
+        let (mine, written, rounds) = batch(&evaluator_values);
+        let (more, more_written, more_rounds) = batch(&evaluator_values);
+        assert!(rounds <= 8, "{} rounds", rounds);
+        assert_eq!(more_rounds, rounds);
+        assert!(more_written <= written, "{} > {}", more_written, written);
+        // A public-key OT sends at least one 32-byte group element per choice bit.
+        let choices = (evaluator_values.len() * 32) as f64;
+        let bytes_per_choice = written * 1000.0 / 8.0 / choices;
+        assert!(bytes_per_choice < 24.0, "{} bytes per OT", bytes_per_choice);
+
+        let got = theirs.iter().map(decode::<Wire32Bit>).collect::<Vec<u32>>();
+        assert_eq!(got, expected_values);
+        let got = mine.iter().map(decode::<Wire32Bit>).collect::<Vec<u32>>();
+        assert_eq!(got, evaluator_values);
+        let got = more.iter().map(decode::<Wire32Bit>).collect::<Vec<u32>>();
+        assert_eq!(got, evaluator_values);
+        handle.join().unwrap();
+    }
 }
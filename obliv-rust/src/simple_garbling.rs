@@ -1,13 +1,25 @@
-//! Implements a test garbling scheme. It is used only for the purpose of identitfiyng proper API.
-//! Once the API is stable, this will be removed and replaced with a fast and secure garabling
-//! scheme.
+//! Implements the classic Yao garbling scheme with point-and-permute. The zero-key and one-key of
+//! a wire are independent random keys with opposite point-and-permute bits, and every 1-bit gate
+//! is garbled as a four-row encrypted truth table. It does not rely on Free-XOR, so XOR gates cost
+//! as much as AND gates.
 
-use crate::mpc_core::GarblingMode;
+use crate::{
+    mpc_core::{
+        evaluate_gate, garble_gate, Delta, EvaluatingWire, GarblingMode, GarblingSession,
+        GarblingWire, Gate, GateGarbling, Operation,
+    },
+    wires::Wire8Bit,
+};
 use rand_core::{CryptoRng, RngCore};
-use scuttlebutt::Block;
+use scuttlebutt::{Block, AES_HASH};
 use serde::{Deserialize, Serialize};
 
-/// A garbled bit where the zero-key and one-key are generated randomly.
+// ----------------------------------------------------------------------------------------------
+//                              Type Definion and Impl Blocks                                   -
+// ----------------------------------------------------------------------------------------------
+
+/// A garbled bit where the zero-key and one-key are generated randomly. The least significant bit
+/// of a key is its point-and-permute bit.
 #[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
 pub struct GarbledBit(pub Block);
 
@@ -19,7 +31,10 @@ impl GarblingMode for GarbledBit {
 
         buffer = [0; 16];
         rng.fill_bytes(&mut buffer);
-        let one = Block::from(buffer);
+        let mut one = Block::from(buffer);
+        if one.lsb() == zero.lsb() {
+            one ^= Block::from(1u128);
+        }
 
         (Self(zero), Self(one))
     }
@@ -27,17 +42,126 @@ impl GarblingMode for GarbledBit {
     fn to_block(&self) -> Block {
         self.0
     }
+
+    fn from_block(block: Block) -> Self {
+        Self(block)
+    }
+}
+
+impl GateGarbling for GarbledBit {
+    fn garble_and<R: RngCore + CryptoRng>(
+        input_1: &(Self, Self),
+        input_2: &(Self, Self),
+        id: u64,
+        _delta: &Delta,
+        rng: &mut R,
+    ) -> ((Self, Self), Vec<Block>) {
+        garble_table(input_1, input_2, id, rng, |x, y| x && y)
+    }
+
+    fn evaluate_and(input_1: &Self, input_2: &Self, id: u64, table: &[Block]) -> Self {
+        evaluate_table(input_1, input_2, id, table)
+    }
+
+    fn garble_xor<R: RngCore + CryptoRng>(
+        input_1: &(Self, Self),
+        input_2: &(Self, Self),
+        id: u64,
+        _delta: &Delta,
+        rng: &mut R,
+    ) -> ((Self, Self), Vec<Block>) {
+        garble_table(input_1, input_2, id, rng, |x, y| x ^ y)
+    }
+
+    fn evaluate_xor(input_1: &Self, input_2: &Self, id: u64, table: &[Block]) -> Self {
+        evaluate_table(input_1, input_2, id, table)
+    }
+}
+
+// ----------------------------------------------------------------------------------------------
+//                              Garble and Evaluate Operations                                  -
+// ----------------------------------------------------------------------------------------------
+
+pub fn garble_u8_gate_simple<R: RngCore + CryptoRng>(
+    input_1: GarblingWire<GarbledBit, Wire8Bit>,
+    input_2: GarblingWire<GarbledBit, Wire8Bit>,
+    operation: Operation,
+    session: &mut GarblingSession,
+    rng: &mut R,
+) -> (
+    GarblingWire<GarbledBit, Wire8Bit>,
+    Vec<Gate<GarbledBit, Wire8Bit>>,
+) {
+    garble_gate(input_1, input_2, operation, session, rng)
+}
+
+pub fn evaluate_simple(
+    input_1: EvaluatingWire<GarbledBit>,
+    input_2: EvaluatingWire<GarbledBit>,
+    operation: Operation,
+    gates: Vec<Gate<GarbledBit, Wire8Bit>>,
+) -> EvaluatingWire<GarbledBit> {
+    evaluate_gate(input_1, input_2, operation, gates)
+}
+
+// ----------------------------------------------------------------------------------------------
+//                                   Internal Functions                                         -
+// ----------------------------------------------------------------------------------------------
+
+/// The double-key hash used to encrypt a row of a garbled table.
+fn hash(key_1: &GarbledBit, key_2: &GarbledBit, id: u64) -> Block {
+    let tweak = (id as u128) << 1;
+    AES_HASH.tccr_hash(Block::from(tweak), key_1.0)
+        ^ AES_HASH.tccr_hash(Block::from(tweak | 1), key_2.0)
+}
+
+/// Encrypts the truth table of `function` and sorts the rows by the point-and-permute bits.
+fn garble_table<R: RngCore + CryptoRng, F: Fn(bool, bool) -> bool>(
+    input_1: &(GarbledBit, GarbledBit),
+    input_2: &(GarbledBit, GarbledBit),
+    id: u64,
+    rng: &mut R,
+    function: F,
+) -> ((GarbledBit, GarbledBit), Vec<Block>) {
+    let output = GarbledBit::pair(rng);
+    let mut table = vec![Block::default(); 4];
+    for &x in [false, true].iter() {
+        for &y in [false, true].iter() {
+            let key_1 = if x { &input_1.1 } else { &input_1.0 };
+            let key_2 = if y { &input_2.1 } else { &input_2.0 };
+            let key_out = if function(x, y) { &output.1 } else { &output.0 };
+            table[row(key_1, key_2)] = hash(key_1, key_2, id) ^ key_out.0;
+        }
+    }
+    (output, table)
+}
+
+/// Decrypts the row of the garbled table pointed to by the input keys.
+fn evaluate_table(
+    input_1: &GarbledBit,
+    input_2: &GarbledBit,
+    id: u64,
+    table: &[Block],
+) -> GarbledBit {
+    GarbledBit(table[row(input_1, input_2)] ^ hash(input_1, input_2, id))
+}
+
+fn row(key_1: &GarbledBit, key_2: &GarbledBit) -> usize {
+    ((key_1.0.lsb() as usize) << 1) | key_2.0.lsb() as usize
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::ot::OtKind;
     use crate::{
-        mpc_core::{EvaluatingWire, GarblingWire, Gate},
+        assign,
+        mpc_core::{EvaluatingWire, GarblingWire, Gate, Party, Protocol, Role, SecretWire},
+        reveal,
         wires::Wire8Bit,
     };
-    use rand::{rngs::StdRng, SeedableRng};
-    use scuttlebutt::{AbstractChannel, Block, TrackChannel};
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+    use scuttlebutt::{AbstractChannel, AesRng, Block, TrackChannel};
     use std::{
         io::{BufReader, BufWriter},
         os::unix::net::UnixStream,
@@ -107,20 +231,20 @@ mod tests {
         let mut rng = StdRng::from_seed(SEED);
 
         let garbled_wires = GarblingWire::<GarbledBit, Wire8Bit>::new(&mut rng);
-        let gate = Gate::<GarbledBit, Wire8Bit> {
-            output: garbled_wires,
-        };
+        let gate = Gate::<GarbledBit, Wire8Bit>::new(
+            0,
+            garbled_wires
+                .to_blocks()
+                .into_iter()
+                .map(|(zero, one)| vec![zero, one])
+                .collect(),
+        );
 
         let serialized_gate = bincode::serialize(&gate).unwrap();
         let deserialized_gate: Gate<GarbledBit, Wire8Bit> =
             bincode::deserialize(&serialized_gate).unwrap();
-        assert!(gate
-            .clone()
-            .output
-            .bits
-            .into_iter()
-            .zip(deserialized_gate.output.bits)
-            .fold(true, |acc, (want, got)| acc || (want.0 == got.0)));
+        assert_eq!(gate.id, deserialized_gate.id);
+        assert_eq!(gate.tables, deserialized_gate.tables);
     }
 
     #[test]
@@ -145,4 +269,198 @@ mod tests {
         handle.join().unwrap();
         assert_eq!(message_clone, res);
     }
+
+    #[test]
+    fn test_garbled_bit_truth_tables() {
+        let mut rng = StdRng::from_seed(SEED);
+        let mut session = GarblingSession::new(&mut rng);
+
+        let input_1 = GarbledBit::pair(&mut rng);
+        let input_2 = GarbledBit::pair(&mut rng);
+        assert_ne!(input_1.0 .0.lsb(), input_1.1 .0.lsb());
+
+        let and_id = session.reserve(1);
+        let (and_output, and_table) =
+            GarbledBit::garble_and(&input_1, &input_2, and_id, &session.delta, &mut rng);
+        let xor_id = session.reserve(1);
+        let (xor_output, xor_table) =
+            GarbledBit::garble_xor(&input_1, &input_2, xor_id, &session.delta, &mut rng);
+        assert_eq!(and_table.len(), 4);
+        assert_eq!(xor_table.len(), 4);
+
+        for (x, y) in [(false, false), (false, true), (true, false), (true, true)].iter() {
+            let key_1 = if *x { &input_1.1 } else { &input_1.0 };
+            let key_2 = if *y { &input_2.1 } else { &input_2.0 };
+            let want_and = if *x && *y {
+                &and_output.1
+            } else {
+                &and_output.0
+            };
+            let want_xor = if *x ^ *y {
+                &xor_output.1
+            } else {
+                &xor_output.0
+            };
+            assert_eq!(
+                want_and,
+                &GarbledBit::evaluate_and(key_1, key_2, and_id, &and_table)
+            );
+            assert_eq!(
+                want_xor,
+                &GarbledBit::evaluate_xor(key_1, key_2, xor_id, &xor_table)
+            );
+        }
+    }
+
+    #[test]
+    fn test_garbled_add_and_mul_u8() {
+        let mut rng = StdRng::from_seed(SEED);
+        let mut session = GarblingSession::new(&mut rng);
+
+        for _ in 0..20 {
+            let value_1: u8 = rng.gen();
+            let value_2: u8 = rng.gen();
+            for &(operation, want) in [
+                (Operation::Add, value_1.wrapping_add(value_2)),
+                (Operation::Mul, value_1.wrapping_mul(value_2)),
+            ]
+            .iter()
+            {
+                let garbled_wires_1 = GarblingWire::<GarbledBit, Wire8Bit>::new(&mut rng);
+                let garbled_value_1 = garbled_wires_1.clone().encode(value_1);
+                let garbled_wires_2 = GarblingWire::<GarbledBit, Wire8Bit>::new(&mut rng);
+                let garbled_value_2 = garbled_wires_2.clone().encode(value_2);
+
+                let (output, gates) = garble_u8_gate_simple(
+                    garbled_wires_1,
+                    garbled_wires_2,
+                    operation,
+                    &mut session,
+                    &mut rng,
+                );
+                let result = evaluate_simple(garbled_value_1, garbled_value_2, operation, gates);
+                assert_eq!(output.decoding_info().decode(&result), want);
+            }
+        }
+    }
+
+    #[test]
+    fn test_garbled_circuit_with_ot() {
+        let (sender, receiver) = UnixStream::pair().unwrap();
+        let handle = std::thread::spawn(move || {
+            // Garbler
+            let rng = AesRng::new();
+            let reader = BufReader::new(sender.try_clone().unwrap());
+            let writer = BufWriter::new(sender);
+            let channel = TrackChannel::new(reader, writer);
+
+            // ------------------ Start of the Garbler
+            let alice = Party { id: 1 };
+            let bob = Party { id: 2 };
+            let mut protocol = Protocol::new(
+                vec![alice.clone(), bob],
+                alice,
+                Role::Garbler,
+                channel,
+                rng,
+                OtKind::ChouOrlandi,
+            )
+            .unwrap();
+            let a1 = assign!(a1, 10, protocol, GarbledBit, Wire8Bit).unwrap();
+            let a2 = assign!(a2, 20, protocol, GarbledBit, Wire8Bit).unwrap();
+            let b1 = assign!(b1 <- party 2, protocol, GarbledBit, Wire8Bit).unwrap();
+            let b2 = assign!(b2 <- party 2, protocol, GarbledBit, Wire8Bit).unwrap();
+            let (a1, a2) = (a1.garbling().unwrap(), a2.garbling().unwrap());
+            let (b1, b2) = (b1.garbling().unwrap(), b2.garbling().unwrap());
+
+            // obliv!(c = a1 + b1);
+            let (c, gates) = garble_u8_gate_simple(
+                a1,
+                b1,
+                Operation::Add,
+                &mut protocol.session,
+                &mut protocol.rng,
+            );
+            let ser = bincode::serialize(&gates).unwrap();
+            protocol.channel.write_usize(ser.len()).unwrap();
+            protocol.channel.write_bytes(&ser).unwrap();
+            protocol.channel.flush().unwrap();
+
+            // obliv!(d = a2 + b2);
+            let (d, gates) = garble_u8_gate_simple(
+                a2,
+                b2,
+                Operation::Add,
+                &mut protocol.session,
+                &mut protocol.rng,
+            );
+            let ser = bincode::serialize(&gates).unwrap();
+            protocol.channel.write_usize(ser.len()).unwrap();
+            protocol.channel.write_bytes(&ser).unwrap();
+            protocol.channel.flush().unwrap();
+
+            // obliv!(e = c * d);
+            let (e, gates) = garble_u8_gate_simple(
+                c,
+                d,
+                Operation::Mul,
+                &mut protocol.session,
+                &mut protocol.rng,
+            );
+            let ser = bincode::serialize(&gates).unwrap();
+            protocol.channel.write_usize(ser.len()).unwrap();
+            protocol.channel.write_bytes(&ser).unwrap();
+            protocol.channel.flush().unwrap();
+
+            let e = SecretWire::Garbling(e);
+            assert_eq!(reveal!(e, protocol).unwrap(), Some(35u8.wrapping_mul(50)));
+        });
+        let rng = AesRng::new();
+        let reader = BufReader::new(receiver.try_clone().unwrap());
+        let writer = BufWriter::new(receiver);
+        let channel = TrackChannel::new(reader, writer);
+
+        // ------------------ Start of evaluator
+        let alice = Party { id: 1 };
+        let bob = Party { id: 2 };
+        let mut protocol = Protocol::new(
+            vec![alice, bob.clone()],
+            bob,
+            Role::Evaluator,
+            channel,
+            rng,
+            OtKind::ChouOrlandi,
+        )
+        .unwrap();
+
+        let a1 = assign!(a1 <- party 1, protocol, GarbledBit, Wire8Bit).unwrap();
+        let a2 = assign!(a2 <- party 1, protocol, GarbledBit, Wire8Bit).unwrap();
+        let b1 = assign!(b1, 25, protocol, GarbledBit, Wire8Bit).unwrap();
+        let b2 = assign!(b2, 30, protocol, GarbledBit, Wire8Bit).unwrap();
+        let (a1, a2) = (a1.evaluating().unwrap(), a2.evaluating().unwrap());
+        let (b1, b2) = (b1.evaluating().unwrap(), b2.evaluating().unwrap());
+
+        // obliv!(c = a1 + b1);
+        let size = protocol.channel.read_usize().unwrap();
+        let ser = protocol.channel.read_vec(size).unwrap();
+        let gates: Vec<Gate<GarbledBit, Wire8Bit>> = bincode::deserialize(&ser).unwrap();
+        let c = evaluate_simple(a1, b1, Operation::Add, gates);
+
+        // obliv!(d = a2 + b2);
+        let size = protocol.channel.read_usize().unwrap();
+        let ser = protocol.channel.read_vec(size).unwrap();
+        let gates: Vec<Gate<GarbledBit, Wire8Bit>> = bincode::deserialize(&ser).unwrap();
+        let d = evaluate_simple(a2, b2, Operation::Add, gates);
+
+        // obliv!(e = c * d);
+        let size = protocol.channel.read_usize().unwrap();
+        let ser = protocol.channel.read_vec(size).unwrap();
+        let gates: Vec<Gate<GarbledBit, Wire8Bit>> = bincode::deserialize(&ser).unwrap();
+        let e = evaluate_simple(c, d, Operation::Mul, gates);
+
+        let e = SecretWire::<GarbledBit, Wire8Bit>::Evaluating(e);
+        assert_eq!(reveal!(e, protocol).unwrap(), Some(35u8.wrapping_mul(50)));
+
+        handle.join().unwrap();
+    }
 }
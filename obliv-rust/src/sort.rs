@@ -0,0 +1,371 @@
+//! Implements oblivious sorting gadgets on top of `CircuitBuilder`. A sorting network compares
+//! and swaps fixed pairs of items, so the circuit does not depend on the values being sorted.
+//!
+//! An item is a group of wires. The order is given by a comparator gadget that looks at two items
+//! and returns a wire that is set if the first item comes before the second one. It can compare
+//! any part of the items, and the rest of the wires are carried along as the payload. The sorts
+//! are not stable: items that are equal for the comparator may end up in any order.
+//!
+//! `sort_elements` runs the same networks on secret elements that the parties already hold, e.g.
+//! the wires of `assign!`, through an `Executor`.
+
+use crate::{
+    circuit::{Circuit, CircuitBuilder, WireId},
+    executor::{Executor, ProtocolError},
+};
+
+// ----------------------------------------------------------------------------------------------
+// -                                  Type Definitions                                          -
+// ----------------------------------------------------------------------------------------------
+
+/// The sorting networks of Batcher. Both work for any number of items and use `O(n log^2 n)`
+/// comparators.
+#[derive(Clone, Copy, Debug)]
+pub enum SortingNetwork {
+    /// The bitonic sorter, which compares items in both directions.
+    Bitonic,
+    /// The odd-even merge sorter, which uses fewer comparators than the bitonic sorter.
+    OddEvenMerge,
+}
+
+// ----------------------------------------------------------------------------------------------
+// -                                    Impl blocks                                             -
+// ----------------------------------------------------------------------------------------------
+
+impl CircuitBuilder {
+    /// Sorts items in the order given by `less`, which returns a wire that is set if its first
+    /// item comes before its second item. Every comparator costs one call to `less` and one AND
+    /// gate per wire of an item.
+    pub fn sort_by<F>(&mut self, items: &mut [Vec<WireId>], network: SortingNetwork, mut less: F)
+    where
+        F: FnMut(&mut CircuitBuilder, &[WireId], &[WireId]) -> WireId,
+    {
+        match network {
+            SortingNetwork::Bitonic => self.bitonic_sort(items, true, &mut less),
+            SortingNetwork::OddEvenMerge => self.odd_even_merge_sort(items, &mut less),
+        }
+    }
+
+    /// Sorts items in ascending order of their first `key_bits` wires, read as an unsigned value.
+    /// The rest of the wires of an item are carried along as its payload. It uses the bitonic
+    /// sorter.
+    pub fn sort_by_key(&mut self, items: &mut [Vec<WireId>], key_bits: usize) {
+        self.sort_by(items, SortingNetwork::Bitonic, |builder, item_1, item_2| {
+            builder.less_than(&item_1[..key_bits], &item_2[..key_bits], false)
+        });
+    }
+
+    fn bitonic_sort<F>(&mut self, items: &mut [Vec<WireId>], ascending: bool, less: &mut F)
+    where
+        F: FnMut(&mut CircuitBuilder, &[WireId], &[WireId]) -> WireId,
+    {
+        let count = items.len();
+        if count <= 1 {
+            return;
+        }
+        let half = count / 2;
+        self.bitonic_sort(&mut items[..half], !ascending, less);
+        self.bitonic_sort(&mut items[half..], ascending, less);
+        self.bitonic_merge(items, ascending, less);
+    }
+
+    fn bitonic_merge<F>(&mut self, items: &mut [Vec<WireId>], ascending: bool, less: &mut F)
+    where
+        F: FnMut(&mut CircuitBuilder, &[WireId], &[WireId]) -> WireId,
+    {
+        let count = items.len();
+        if count <= 1 {
+            return;
+        }
+        // The largest power of two below `count` keeps the merge correct for any number of items.
+        let distance = count.next_power_of_two() / 2;
+        for index in 0..count - distance {
+            self.compare_and_swap(items, index, index + distance, ascending, less);
+        }
+        self.bitonic_merge(&mut items[..distance], ascending, less);
+        self.bitonic_merge(&mut items[distance..], ascending, less);
+    }
+
+    /// Runs the odd-even merge sorter of the next power of two and drops the comparators that
+    /// touch items past the end. It is correct because every comparator moves the smaller item
+    /// to the lower position, so missing items that are larger than all others would never move.
+    fn odd_even_merge_sort<F>(&mut self, items: &mut [Vec<WireId>], less: &mut F)
+    where
+        F: FnMut(&mut CircuitBuilder, &[WireId], &[WireId]) -> WireId,
+    {
+        let count = items.len();
+        let mut size = 1;
+        while size < count {
+            let mut distance = size;
+            while distance >= 1 {
+                let mut start = distance % size;
+                while start + distance < count {
+                    for offset in 0..distance.min(count - start - distance) {
+                        let first = start + offset;
+                        let second = first + distance;
+                        // Only the items of the same block of `2 * size` items are merged.
+                        if first / (2 * size) == second / (2 * size) {
+                            self.compare_and_swap(items, first, second, true, less);
+                        }
+                    }
+                    start += 2 * distance;
+                }
+                distance /= 2;
+            }
+            size *= 2;
+        }
+    }
+
+    /// Swaps two items if they are not in the requested order. It costs one AND gate per wire of
+    /// an item on top of the comparison.
+    fn compare_and_swap<F>(
+        &mut self,
+        items: &mut [Vec<WireId>],
+        first: usize,
+        second: usize,
+        ascending: bool,
+        less: &mut F,
+    ) where
+        F: FnMut(&mut CircuitBuilder, &[WireId], &[WireId]) -> WireId,
+    {
+        let swap = if ascending {
+            less(self, &items[second], &items[first])
+        } else {
+            less(self, &items[first], &items[second])
+        };
+        for bit in 0..items[first].len() {
+            let difference = self.xor(items[first][bit], items[second][bit]);
+            let masked = self.and(swap, difference);
+            items[first][bit] = self.xor(items[first][bit], masked);
+            items[second][bit] = self.xor(items[second][bit], masked);
+        }
+    }
+}
+
+// ----------------------------------------------------------------------------------------------
+// -                              Garble and Evaluate Circuits                                  -
+// ----------------------------------------------------------------------------------------------
+
+/// Sorts secret elements in the order given by `less`, as `CircuitBuilder::sort_by` does. Both
+/// parties pass the same network and comparator, so they compile the same circuit.
+pub fn sort_elements<E: Executor, F>(
+    elements: Vec<Vec<E::Key>>,
+    network: SortingNetwork,
+    less: F,
+    executor: &mut E,
+) -> Result<Vec<Vec<E::Key>>, ProtocolError>
+where
+    F: FnMut(&mut CircuitBuilder, &[WireId], &[WireId]) -> WireId,
+{
+    let bits = elements.first().map_or(0, |element| element.len());
+    executor.execute(&sort_circuit(elements.len(), bits, network, less), elements)
+}
+
+/// Compiles a sorting network. The input and output groups are the elements.
+fn sort_circuit<F>(count: usize, bits: usize, network: SortingNetwork, less: F) -> Circuit
+where
+    F: FnMut(&mut CircuitBuilder, &[WireId], &[WireId]) -> WireId,
+{
+    let mut builder = CircuitBuilder::new();
+    let mut elements = (0..count)
+        .map(|_| builder.input(0, bits as u32))
+        .collect::<Vec<Vec<WireId>>>();
+    builder.sort_by(&mut elements, network, less);
+    for element in elements.iter() {
+        builder.output(element);
+    }
+    builder.build()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{sort_elements, SortingNetwork};
+    use crate::{
+        circuit::{CircuitBuilder, WireId},
+        executor::{Evaluator, Executor, Garbler},
+        free_xor_garbling::FreeXorBit,
+        mpc_core::{GarblingSession, WireValue},
+    };
+    use ocelot::ot::{ChouOrlandiReceiver, ChouOrlandiSender, Receiver, Sender};
+    use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+    use scuttlebutt::{AesRng, TrackChannel};
+    use std::{
+        io::{BufReader, BufWriter},
+        os::unix::net::UnixStream,
+    };
+    const SEED: [u8; 32] = [42u8; 32];
+
+    #[test]
+    fn test_sort_by_key() {
+        let mut rng = StdRng::from_seed(SEED);
+        for &count in [1, 2, 5, 8, 13].iter() {
+            let mut builder = CircuitBuilder::new();
+            let mut items = (0..count).map(|_| builder.input(1, 16)).collect::<Vec<_>>();
+            builder.sort_by_key(&mut items, 8);
+            for item in items.iter() {
+                builder.output(item);
+            }
+            let circuit = builder.build();
+
+            for _ in 0..10 {
+                // Small keys give ties, which may end up in any order.
+                let values = (0..count)
+                    .map(|_| rng.gen_range(0, 8u16) << 8 | rng.gen::<u8>() as u16)
+                    .collect::<Vec<u16>>();
+                let inputs = values
+                    .iter()
+                    .map(|value| value.to_bits())
+                    .collect::<Vec<_>>();
+                let got = circuit
+                    .compute(&inputs)
+                    .iter()
+                    .map(|bits| u16::from_bits(bits))
+                    .collect::<Vec<u16>>();
+                let keys = got.iter().map(|value| value >> 8).collect::<Vec<u16>>();
+                let mut sorted_keys = keys.clone();
+                sorted_keys.sort_unstable();
+                assert_eq!(keys, sorted_keys, "{:?}", values);
+                let mut want = values.clone();
+                want.sort_unstable();
+                let mut got = got;
+                got.sort_unstable();
+                assert_eq!(got, want, "{:?}", values);
+            }
+        }
+    }
+
+    #[test]
+    fn test_sort_by() {
+        let mut rng = StdRng::from_seed(SEED);
+        for network in [SortingNetwork::Bitonic, SortingNetwork::OddEvenMerge].iter() {
+            for &count in [1, 2, 3, 6, 11, 16].iter() {
+                // The items are a 16-bit payload and then a signed 8-bit key, sorted in
+                // descending order of the key.
+                let mut builder = CircuitBuilder::new();
+                let mut items = (0..count).map(|_| builder.input(1, 24)).collect::<Vec<_>>();
+                builder.sort_by(&mut items, *network, |builder, item_1, item_2| {
+                    builder.greater_than(&item_1[16..], &item_2[16..], true)
+                });
+                for item in items.iter() {
+                    builder.output(item);
+                }
+                let circuit = builder.build();
+
+                for _ in 0..10 {
+                    let values = (0..count)
+                        .map(|_| (rng.gen::<u16>(), rng.gen_range(-4, 4i8)))
+                        .collect::<Vec<(u16, i8)>>();
+                    let inputs = values
+                        .iter()
+                        .map(|(payload, key)| [payload.to_bits(), key.to_bits()].concat())
+                        .collect::<Vec<_>>();
+                    let got = circuit
+                        .compute(&inputs)
+                        .iter()
+                        .map(|bits| (u16::from_bits(&bits[..16]), i8::from_bits(&bits[16..])))
+                        .collect::<Vec<(u16, i8)>>();
+                    let keys = got.iter().map(|(_, key)| *key).collect::<Vec<i8>>();
+                    let mut sorted_keys = keys.clone();
+                    sorted_keys.sort_unstable_by(|key_1, key_2| key_2.cmp(key_1));
+                    assert_eq!(keys, sorted_keys, "{:?} {:?}", network, values);
+                    let mut want = values.clone();
+                    want.sort_unstable();
+                    let mut got = got;
+                    got.sort_unstable();
+                    assert_eq!(got, want, "{:?} {:?}", network, values);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_odd_even_merge_uses_fewer_comparators() {
+        let mut comparators = vec![];
+        for network in [SortingNetwork::Bitonic, SortingNetwork::OddEvenMerge].iter() {
+            let mut builder = CircuitBuilder::new();
+            let mut items = (0..16).map(|_| builder.input(1, 1)).collect::<Vec<_>>();
+            let mut count = 0;
+            builder.sort_by(&mut items, *network, |builder, item_1, item_2| {
+                count += 1;
+                builder.less_than(item_1, item_2, false)
+            });
+            comparators.push(count);
+        }
+        // Batcher's networks for 16 items.
+        assert_eq!(comparators, vec![80, 63]);
+    }
+
+    #[test]
+    fn test_sort_elements() {
+        // Each party inputs half of the elements: an 8-bit key and then an 8-bit payload, sorted
+        // in ascending order of the key.
+        const COUNT: usize = 12;
+        let mut rng = StdRng::from_seed(SEED);
+        let mut keys = (0..COUNT as u8).map(|key| key * 7).collect::<Vec<u8>>();
+        keys.shuffle(&mut rng);
+        let values = keys
+            .iter()
+            .map(|key| u16::from(*key) << 8 | u16::from(rng.gen::<u8>()))
+            .collect::<Vec<u16>>();
+        let mut want = values.clone();
+        want.sort_unstable();
+        let less = |builder: &mut CircuitBuilder, item_1: &[WireId], item_2: &[WireId]| {
+            builder.less_than(&item_1[..8], &item_2[..8], false)
+        };
+
+        for network in [SortingNetwork::Bitonic, SortingNetwork::OddEvenMerge].iter() {
+            let network = *network;
+            let (sender, receiver) = UnixStream::pair().unwrap();
+            let garbler_values = values.clone();
+            let handle = std::thread::spawn(move || {
+                let mut rng = AesRng::new();
+                let reader = BufReader::new(sender.try_clone().unwrap());
+                let writer = BufWriter::new(sender);
+                let mut channel = TrackChannel::new(reader, writer);
+                let mut session = GarblingSession::new(&mut rng);
+                let mut ot = ChouOrlandiSender::init(&mut channel, &mut rng).unwrap();
+                let mut garbler = Garbler::<FreeXorBit, _, _, _>::new(
+                    &mut channel,
+                    &mut ot,
+                    &mut session,
+                    &mut rng,
+                );
+
+                let mut elements = garbler_values[..COUNT / 2]
+                    .iter()
+                    .map(|value| garbler.input(&value.to_bits()).unwrap())
+                    .collect::<Vec<_>>();
+                for _ in COUNT / 2..COUNT {
+                    elements.push(garbler.peer_input(16).unwrap());
+                }
+                let elements = sort_elements(elements, network, less, &mut garbler).unwrap();
+                elements
+                    .iter()
+                    .map(|element| u16::from_bits(&garbler.reveal(element).unwrap()))
+                    .collect::<Vec<u16>>()
+            });
+
+            let mut rng = AesRng::new();
+            let reader = BufReader::new(receiver.try_clone().unwrap());
+            let writer = BufWriter::new(receiver);
+            let mut channel = TrackChannel::new(reader, writer);
+            let mut ot = ChouOrlandiReceiver::init(&mut channel, &mut rng).unwrap();
+            let mut evaluator =
+                Evaluator::<FreeXorBit, _, _, _>::new(&mut channel, &mut ot, &mut rng);
+
+            let mut elements = (0..COUNT / 2)
+                .map(|_| evaluator.peer_input(16).unwrap())
+                .collect::<Vec<_>>();
+            for value in values[COUNT / 2..].iter() {
+                elements.push(evaluator.input(&value.to_bits()).unwrap());
+            }
+            let elements = sort_elements(elements, network, less, &mut evaluator).unwrap();
+            let got = elements
+                .iter()
+                .map(|element| u16::from_bits(&evaluator.reveal(element).unwrap()))
+                .collect::<Vec<u16>>();
+            assert_eq!(got, want, "{:?}", network);
+            assert_eq!(handle.join().unwrap(), want, "{:?}", network);
+        }
+    }
+}
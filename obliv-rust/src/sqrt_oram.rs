@@ -0,0 +1,496 @@
+//! Implements the Square-Root ORAM of Zahur et al. ("Revisiting Square-Root ORAM", S&P 2016) on
+//! top of garbled circuits. It gives secret-indexed access to large arrays in sublinear time.
+//!
+//! The elements and `T` dummy elements are stored in a secret random permutation, tagged with their
+//! logical index. Every access first scans a stash of the elements accessed since the last
+//! shuffle. If the element is not there, the position map gives its physical position; otherwise
+//! the position of the next unused dummy is used. The position is revealed to both parties, which
+//! is safe because every physical position is read at most once between two shuffles, and the
+//! element read there is appended to the stash. After `T` accesses, the stash is written back to
+//! the positions it was read from, and everything is shuffled again with a fresh permutation that
+//! neither party knows.
+//!
+//! A shuffle applies a Waksman network programmed with a random permutation of the garbler, and
+//! then one of the evaluator. Running both networks backwards on the constant positions gives the
+//! position map, and running them backwards on the elements puts them back in logical order before
+//! the next shuffle. Every network costs `O(n log n)` AND gates per bit of an element, so the
+//! shuffles add `O(sqrt(n log n))` AND gates per bit to every access.
+//!
+//! The position map is itself a Square-Root ORAM that packs several positions in every element,
+//! down to a size at which a linear scan is cheaper.
+
+use crate::{
+    circuit::{Circuit, CircuitBuilder, WireId},
+    executor::{Executor, ProtocolError},
+    mpc_core::WireValue,
+    obliv_array::{read_elements, ObliviousMemory},
+    permutation::waksman_switch_count,
+};
+
+/// The number of positions packed in every element of a recursive position map.
+const PACKING: usize = 8;
+const PACKING_BITS: usize = 3;
+
+/// Position maps with at most this many entries are scanned linearly instead of recursing.
+const LINEAR_THRESHOLD: usize = 64;
+
+// ----------------------------------------------------------------------------------------------
+// -                                  Type Definitions                                          -
+// ----------------------------------------------------------------------------------------------
+
+/// A Square-Root ORAM on either side of the computation. `K` is the key type of the executor, so
+/// both parties hold the same structure with their own keys.
+pub struct SquareRootOram<K> {
+    length: usize,
+    value_bits: usize,
+    /// The width of logical indices and physical positions, which go up to `length + period`.
+    index_bits: usize,
+    /// The number of accesses between two shuffles, which is also the number of dummies.
+    period: usize,
+    /// The shuffled elements. Each one is its logical index followed by its value.
+    physical: Vec<Vec<K>>,
+    /// The switches of the two Waksman networks of the last shuffle, the garbler's first.
+    switches: Vec<Vec<K>>,
+    /// The physical positions that were read since the last shuffle, in the order of the stash.
+    /// Both parties know them.
+    read: Vec<usize>,
+    /// The elements read since the last shuffle, in the same layout as `physical`.
+    stash: Vec<Vec<K>>,
+    position_map: PositionMap<K>,
+}
+
+enum PositionMap<K> {
+    /// One position per entry.
+    Linear(Vec<Vec<K>>),
+    /// `PACKING` positions per element.
+    Recursive(Box<SquareRootOram<K>>),
+}
+
+// ----------------------------------------------------------------------------------------------
+// -                                    Impl blocks                                             -
+// ----------------------------------------------------------------------------------------------
+
+impl<K: Clone> SquareRootOram<K> {
+    /// Creates an ORAM that holds the given elements, which must all have the same width. It
+    /// shuffles the elements, so both parties have to call it in lockstep.
+    pub fn new<E: Executor<Key = K>>(
+        elements: Vec<Vec<K>>,
+        executor: &mut E,
+    ) -> Result<SquareRootOram<K>, ProtocolError> {
+        assert!(!elements.is_empty(), "an ORAM needs at least one element");
+        let length = elements.len();
+        let value_bits = elements[0].len();
+        let period = period(length);
+        let index_bits = bits(length + period - 1);
+        let circuit = initial_circuit(length, period, index_bits, value_bits);
+        let physical = executor.execute(&circuit, elements)?;
+
+        let mut oram = SquareRootOram {
+            length,
+            value_bits,
+            index_bits,
+            period,
+            physical,
+            switches: vec![],
+            read: vec![],
+            stash: vec![],
+            position_map: PositionMap::Linear(vec![]),
+        };
+        oram.shuffle(executor)?;
+        Ok(oram)
+    }
+
+    /// Returns the number of accesses between two shuffles.
+    pub fn period(&self) -> usize {
+        self.period
+    }
+
+    /// Reads an element and writes `value` over it, if any. Returns the value that was read.
+    fn access<E: Executor<Key = K>>(
+        &mut self,
+        index: &[K],
+        value: Option<&[K]>,
+        executor: &mut E,
+    ) -> Result<Vec<K>, ProtocolError> {
+        let accesses = self.stash.len();
+        let circuit = lookup_circuit(
+            self.length,
+            index.len(),
+            accesses,
+            self.index_bits,
+            self.value_bits,
+        );
+        let mut inputs = vec![index.to_vec()];
+        inputs.extend(self.stash.iter().cloned());
+        let mut outputs = executor.execute(&circuit, inputs)?.into_iter();
+        let mut output = || outputs.next().expect("the lookup has four outputs");
+        let (flags, logical_index, lookup, stash_value) = (output(), output(), output(), output());
+
+        let position = self.position(lookup, executor)?;
+        let position = u128::from_bits(&executor.reveal(&position)?) as usize;
+        assert!(
+            !self.read.contains(&position),
+            "a physical position is read twice"
+        );
+        self.read.push(position);
+        let fetched = self.physical[position].clone();
+
+        let circuit = result_circuit(self.index_bits, self.value_bits);
+        let inputs = vec![flags.clone(), stash_value, fetched.clone()];
+        let result = executor.execute(&circuit, inputs)?.remove(0);
+        self.stash.push(fetched);
+        if let Some(value) = value {
+            let circuit = update_circuit(self.stash.len(), self.index_bits, self.value_bits);
+            let mut inputs = vec![flags, logical_index, value.to_vec()];
+            inputs.append(&mut self.stash);
+            self.stash = executor.execute(&circuit, inputs)?;
+        }
+
+        if self.stash.len() == self.period {
+            self.refresh(executor)?;
+        }
+        Ok(result)
+    }
+
+    /// Returns the secret physical position of the element or dummy at a logical index.
+    fn position<E: Executor<Key = K>>(
+        &mut self,
+        lookup: Vec<K>,
+        executor: &mut E,
+    ) -> Result<Vec<K>, ProtocolError> {
+        match &mut self.position_map {
+            PositionMap::Linear(positions) => read_elements(positions.clone(), &lookup, executor),
+            PositionMap::Recursive(oram) => {
+                let (block, slot) = lookup.split_at(self.index_bits - PACKING_BITS);
+                let positions = oram.access(block, None, executor)?;
+                let circuit = slot_circuit(self.index_bits);
+                let mut outputs = executor.execute(&circuit, vec![positions, slot.to_vec()])?;
+                Ok(outputs.remove(0))
+            }
+        }
+    }
+
+    /// Shuffles the elements and dummies, which must be in logical order, with a fresh secret
+    /// permutation and builds the position map of the new permutation.
+    fn shuffle<E: Executor<Key = K>>(&mut self, executor: &mut E) -> Result<(), ProtocolError> {
+        let count = self.physical.len();
+        self.switches = executor.random_switches(count)?;
+        let circuit = network_circuit(count, self.index_bits + self.value_bits, false);
+        let mut inputs = self.switches.clone();
+        inputs.append(&mut self.physical);
+        self.physical = executor.execute(&circuit, inputs)?;
+
+        let recursive = count > LINEAR_THRESHOLD;
+        let packing = if recursive { PACKING } else { 1 };
+        let circuit = position_circuit(count, self.index_bits, packing);
+        let positions = executor.execute(&circuit, self.switches.clone())?;
+        self.read = vec![];
+        self.stash = vec![];
+        self.position_map = if recursive {
+            PositionMap::Recursive(Box::new(SquareRootOram::new(positions, executor)?))
+        } else {
+            PositionMap::Linear(positions)
+        };
+        Ok(())
+    }
+
+    /// Writes the stash back to the positions it was read from, undoes the last shuffle, and
+    /// shuffles again.
+    fn refresh<E: Executor<Key = K>>(&mut self, executor: &mut E) -> Result<(), ProtocolError> {
+        for (position, entry) in self.read.drain(..).zip(self.stash.drain(..)) {
+            self.physical[position] = entry;
+        }
+        let count = self.physical.len();
+        let circuit = network_circuit(count, self.index_bits + self.value_bits, true);
+        let mut inputs = std::mem::take(&mut self.switches);
+        inputs.append(&mut self.physical);
+        self.physical = executor.execute(&circuit, inputs)?;
+        self.shuffle(executor)
+    }
+}
+
+impl<E: Executor> ObliviousMemory<E> for SquareRootOram<E::Key> {
+    fn len(&self) -> usize {
+        self.length
+    }
+
+    fn read(&mut self, index: &[E::Key], executor: &mut E) -> Result<Vec<E::Key>, ProtocolError> {
+        self.access(index, None, executor)
+    }
+
+    fn write(
+        &mut self,
+        index: &[E::Key],
+        value: &[E::Key],
+        executor: &mut E,
+    ) -> Result<(), ProtocolError> {
+        assert_eq!(
+            value.len(),
+            self.value_bits,
+            "the value has the wrong width"
+        );
+        self.access(index, Some(value), executor)?;
+        Ok(())
+    }
+}
+
+// ----------------------------------------------------------------------------------------------
+// -                                 Utility Functions                                          -
+// ----------------------------------------------------------------------------------------------
+
+/// Returns the average number of AND gates of a read from an ORAM of `length` elements of
+/// `value_bits` bits, over the accesses between two shuffles. It includes the stash scans, the
+/// position maps and the shuffles. A network counts one AND gate per switch and per bit of an item,
+/// which slightly overestimates the networks that start on constant positions.
+pub fn access_and_count(length: usize, value_bits: usize) -> usize {
+    period_and_count(length, value_bits, bits(length - 1)) / period(length)
+}
+
+/// Returns the number of AND gates of the reads between two shuffles with an index of `width`
+/// bits, and of the shuffle that follows them.
+fn period_and_count(length: usize, value_bits: usize, width: usize) -> usize {
+    let accesses = period(length);
+    let count = length + accesses;
+    let index_bits = bits(count - 1);
+    // The lookup scans the stash, so its cost grows linearly with the number of accesses.
+    let first = lookup_circuit(length, width, 0, index_bits, value_bits).and_count();
+    let last = lookup_circuit(length, width, accesses - 1, index_bits, value_bits).and_count();
+    let lookups = accesses * (first + last) / 2;
+    let results = accesses * result_circuit(index_bits, value_bits).and_count();
+    let positions = if count > LINEAR_THRESHOLD {
+        let packed_length = (count - 1) / PACKING + 1;
+        let packed_bits = PACKING * index_bits;
+        let map = period_and_count(packed_length, packed_bits, index_bits - PACKING_BITS);
+        accesses * (map / period(packed_length) + slot_circuit(index_bits).and_count())
+    } else {
+        let mut builder = CircuitBuilder::new();
+        let lookup = builder.input(0, index_bits as u32);
+        let positions = (0..count)
+            .map(|_| builder.input(0, index_bits as u32))
+            .collect::<Vec<Vec<WireId>>>();
+        let output = builder.array_read(&positions, &lookup);
+        builder.output(&output);
+        accesses * builder.build().and_count()
+    };
+    let undo = 2 * (index_bits + value_bits) * waksman_switch_count(count);
+    lookups + results + positions + undo + shuffle_and_count(length, value_bits)
+}
+
+/// Returns the number of AND gates of a shuffle, including the new recursive position map.
+fn shuffle_and_count(length: usize, value_bits: usize) -> usize {
+    let count = length + period(length);
+    let index_bits = bits(count - 1);
+    let networks = 2 * (2 * index_bits + value_bits) * waksman_switch_count(count);
+    if count > LINEAR_THRESHOLD {
+        let packed_length = (count - 1) / PACKING + 1;
+        networks + shuffle_and_count(packed_length, PACKING * index_bits)
+    } else {
+        networks
+    }
+}
+
+/// Returns the number of accesses between two shuffles, `sqrt(n log n)`. It balances the cost of
+/// the shuffles against the cost of scanning the stash.
+fn period(length: usize) -> usize {
+    let mut period = 1;
+    while (period + 1) * (period + 1) <= length * bits(length) {
+        period += 1;
+    }
+    period
+}
+
+/// Returns the number of bits needed to write `value`, at least one.
+fn bits(value: usize) -> usize {
+    ((usize::BITS - value.leading_zeros()) as usize).max(1)
+}
+
+/// Tags every element with its logical index and appends the dummies. The input groups are the
+/// elements.
+fn initial_circuit(length: usize, period: usize, index_bits: usize, value_bits: usize) -> Circuit {
+    let mut builder = CircuitBuilder::new();
+    let elements = (0..length)
+        .map(|_| builder.input(0, value_bits as u32))
+        .collect::<Vec<Vec<WireId>>>();
+    let dummy = builder.constant_word(0, value_bits);
+    for (logical, element) in elements
+        .iter()
+        .chain(vec![dummy; period].iter())
+        .enumerate()
+    {
+        let mut entry = builder.constant_word(logical as u128, index_bits);
+        entry.extend_from_slice(element);
+        builder.output(&entry);
+    }
+    builder.build()
+}
+
+/// Applies the two Waksman networks of a shuffle, or undoes them. The input groups are the
+/// switches of the garbler's network, the switches of the evaluator's network, and the entries.
+/// The output groups are the entries.
+fn network_circuit(count: usize, width: usize, undo: bool) -> Circuit {
+    let mut builder = CircuitBuilder::new();
+    let garbler_switches = builder.input(0, waksman_switch_count(count) as u32);
+    let evaluator_switches = builder.input(0, waksman_switch_count(count) as u32);
+    let entries = (0..count)
+        .map(|_| builder.input(0, width as u32))
+        .collect::<Vec<Vec<WireId>>>();
+    let entries = if undo {
+        let entries = builder.waksman_unpermute(&entries, &evaluator_switches);
+        builder.waksman_unpermute(&entries, &garbler_switches)
+    } else {
+        let entries = builder.waksman_permute(&entries, &garbler_switches);
+        builder.waksman_permute(&entries, &evaluator_switches)
+    };
+    for entry in entries.iter() {
+        builder.output(entry);
+    }
+    builder.build()
+}
+
+/// Undoes the two Waksman networks of a shuffle on the constant physical positions, which moves
+/// every position to the logical index of the entry that the shuffle put there. The input groups
+/// are the switches of both networks. The output groups are the positions, packed `packing` at a
+/// time.
+fn position_circuit(count: usize, index_bits: usize, packing: usize) -> Circuit {
+    let mut builder = CircuitBuilder::new();
+    let garbler_switches = builder.input(0, waksman_switch_count(count) as u32);
+    let evaluator_switches = builder.input(0, waksman_switch_count(count) as u32);
+    let positions = (0..count)
+        .map(|position| builder.constant_word(position as u128, index_bits))
+        .collect::<Vec<Vec<WireId>>>();
+    let positions = builder.waksman_unpermute(&positions, &evaluator_switches);
+    let positions = builder.waksman_unpermute(&positions, &garbler_switches);
+    let zero = builder.constant_word(0, index_bits);
+    for block in positions.chunks(packing) {
+        let mut output = block.concat();
+        for _ in block.len()..packing {
+            output.extend_from_slice(&zero);
+        }
+        builder.output(&output);
+    }
+    builder.build()
+}
+
+/// Scans the stash for the logical index and picks the logical index to look up in the position
+/// map. The input groups are the index and the stash entries. The output groups are the flags
+/// (found in the stash, in bounds), the logical index, the lookup index, and the value found in the
+/// stash.
+fn lookup_circuit(
+    length: usize,
+    width: usize,
+    accesses: usize,
+    index_bits: usize,
+    value_bits: usize,
+) -> Circuit {
+    let mut builder = CircuitBuilder::new();
+    let index = builder.input(0, width as u32);
+    let stash = (0..accesses)
+        .map(|_| builder.input(0, (index_bits + value_bits) as u32))
+        .collect::<Vec<Vec<WireId>>>();
+
+    let compare_width = width.max(bits(length));
+    let extended = builder.extend(&index, compare_width, false);
+    let bound = builder.constant_word(length as u128, compare_width);
+    let in_bounds = builder.less_than(&extended, &bound, false);
+    let logical_index = if width >= index_bits {
+        index[width - index_bits..].to_vec()
+    } else {
+        builder.extend(&index, index_bits, false)
+    };
+
+    let mut found = builder.constant(false);
+    let mut stash_value = builder.constant_word(0, value_bits);
+    for entry in stash.iter() {
+        let hit = builder.equal(&entry[..index_bits], &logical_index);
+        found = builder.or(found, hit);
+        // Every logical index is at most once in the stash.
+        let masked = builder.bitwise_and(&entry[index_bits..], &vec![hit; value_bits]);
+        stash_value = builder.bitwise_xor(&stash_value, &masked);
+    }
+    let found = builder.and(found, in_bounds);
+    let missing = builder.inv(in_bounds);
+    let use_dummy = builder.or(found, missing);
+    let dummy = builder.constant_word((length + accesses) as u128, index_bits);
+    let lookup = builder.mux(use_dummy, &dummy, &logical_index);
+
+    builder.output(&[found, in_bounds]);
+    builder.output(&logical_index);
+    builder.output(&lookup);
+    builder.output(&stash_value);
+    builder.build()
+}
+
+/// Picks the value from the stash or from the fetched entry. The input groups are the flags, the
+/// value found in the stash, and the fetched entry.
+fn result_circuit(index_bits: usize, value_bits: usize) -> Circuit {
+    let mut builder = CircuitBuilder::new();
+    let flags = builder.input(0, 2);
+    let stash_value = builder.input(0, value_bits as u32);
+    let fetched = builder.input(0, (index_bits + value_bits) as u32);
+    let value = builder.mux(flags[0], &stash_value, &fetched[index_bits..]);
+    let value = builder.bitwise_and(&value, &vec![flags[1]; value_bits]);
+    builder.output(&value);
+    builder.build()
+}
+
+/// Writes a value over the stash entry of the logical index. The input groups are the flags, the
+/// logical index, the value, and the stash entries. The output groups are the stash entries.
+fn update_circuit(accesses: usize, index_bits: usize, value_bits: usize) -> Circuit {
+    let mut builder = CircuitBuilder::new();
+    let flags = builder.input(0, 2);
+    let logical_index = builder.input(0, index_bits as u32);
+    let value = builder.input(0, value_bits as u32);
+    for _ in 0..accesses {
+        let entry = builder.input(0, (index_bits + value_bits) as u32);
+        let hit = builder.equal(&entry[..index_bits], &logical_index);
+        let hit = builder.and(hit, flags[1]);
+        let updated = builder.mux(hit, &value, &entry[index_bits..]);
+        builder.output(&[&entry[..index_bits], updated.as_slice()].concat());
+    }
+    builder.build()
+}
+
+/// Picks a position out of a packed element of a recursive position map. The input groups are the
+/// packed positions and the least significant bits of the logical index.
+fn slot_circuit(index_bits: usize) -> Circuit {
+    let mut builder = CircuitBuilder::new();
+    let positions = builder.input(0, (PACKING * index_bits) as u32);
+    let slot = builder.input(0, PACKING_BITS as u32);
+    let positions = positions
+        .chunks(index_bits)
+        .map(|position| position.to_vec())
+        .collect::<Vec<Vec<WireId>>>();
+    let output = builder.array_read(&positions, &slot);
+    builder.output(&output);
+    builder.build()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::obliv_array::testing::check_memory;
+
+    #[test]
+    fn test_square_root_oram() {
+        // The position map recurses once, and the accesses go through a few shuffles.
+        check_memory(
+            70,
+            60,
+            |elements, garbler| {
+                let oram = SquareRootOram::new(elements, garbler).unwrap();
+                assert!(oram.period() < 30);
+                oram
+            },
+            |elements, evaluator| SquareRootOram::new(elements, evaluator).unwrap(),
+        );
+    }
+
+    #[test]
+    fn test_access_cost_is_below_linear_scan() {
+        // A linear scan masks every bit of every element with an AND gate, on top of decoding
+        // the index.
+        let length = 1 << 16;
+        let cost = access_and_count(length, 32);
+        assert!(cost < length * 32, "{} AND gates per access", cost);
+    }
+}
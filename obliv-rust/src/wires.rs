@@ -1,15 +1,245 @@
-//! Defines a struct for each type that is supported by the 2PC protocol.
+//! Defines a struct for each type that is supported by the 2PC protocol. Signed integers use the
+//! two's complement representation, so their bits are the bits of the unsigned integer of the same
+//! width.
 
-use crate::mpc_core::Wire;
+use crate::{
+    circuit::{Circuit, CircuitBuilder},
+    mpc_core::{Comparison, Operation, Wire, WireValue},
+};
 use serde::{Deserialize, Serialize};
 
-/// A wire that represents a `u8` value.
+/// A wire that represents a `bool` value.
 #[derive(Clone, Serialize, Deserialize, Debug)]
-pub struct Wire8Bit {}
+pub struct WireBool {}
 
-impl Wire for Wire8Bit {
-    type ValueType = u8;
+impl Wire for WireBool {
+    type ValueType = bool;
     fn bits() -> u32 {
-        8
+        1
+    }
+}
+
+impl WireValue for bool {
+    fn to_bits(&self) -> Vec<bool> {
+        vec![*self]
+    }
+
+    fn from_bits(bits: &[bool]) -> Self {
+        bits[bits.len() - 1]
+    }
+}
+
+/// A wire that represents an IEEE-754 `f32` value.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct WireF32 {}
+
+impl Wire for WireF32 {
+    type ValueType = f32;
+    fn bits() -> u32 {
+        32
+    }
+
+    /// Compiles the arithmetic with the IEEE-754 gadgets. The bitwise operations and the shifts
+    /// work on the raw bits of the float.
+    fn operation_circuit(operation: Operation) -> Circuit {
+        let mut builder = CircuitBuilder::new();
+        let input_1 = builder.input(0, 32);
+        let input_2 = builder.input(0, 32);
+        let output = match operation {
+            Operation::Add => builder.add_f32(&input_1, &input_2),
+            Operation::Sub => builder.sub_f32(&input_1, &input_2),
+            Operation::Mul => builder.mul_f32(&input_1, &input_2),
+            Operation::Div => builder.div_f32(&input_1, &input_2),
+            _ => return operation.circuit(32, false),
+        };
+        builder.output(&output);
+        builder.build()
+    }
+
+    /// Compiles the comparison with the IEEE-754 gadgets, so that NaN is unordered and the zeros
+    /// of both signs are equal.
+    fn comparison_circuit(comparison: Comparison) -> Circuit {
+        let mut builder = CircuitBuilder::new();
+        let input_1 = builder.input(0, 32);
+        let input_2 = builder.input(0, 32);
+        let output = match comparison {
+            Comparison::Lt => builder.lt_f32(&input_1, &input_2),
+            Comparison::Le => builder.le_f32(&input_1, &input_2),
+            Comparison::Gt => builder.lt_f32(&input_2, &input_1),
+            Comparison::Ge => builder.le_f32(&input_2, &input_1),
+            Comparison::Eq => builder.eq_f32(&input_1, &input_2),
+            Comparison::Ne => {
+                let equal = builder.eq_f32(&input_1, &input_2);
+                builder.inv(equal)
+            }
+        };
+        builder.output(&[output]);
+        builder.build()
+    }
+}
+
+impl WireValue for f32 {
+    fn to_bits(&self) -> Vec<bool> {
+        f32::to_bits(*self).to_bits()
+    }
+
+    fn from_bits(bits: &[bool]) -> Self {
+        f32::from_bits(u32::from_bits(bits))
+    }
+}
+
+macro_rules! integer_wire {
+    ($(#[$doc:meta])* $name:ident, $type:ty, $signed:expr) => {
+        $(#[$doc])*
+        #[derive(Clone, Serialize, Deserialize, Debug)]
+        pub struct $name {}
+
+        impl Wire for $name {
+            type ValueType = $type;
+            fn bits() -> u32 {
+                <$type>::BITS
+            }
+            fn is_signed() -> bool {
+                $signed
+            }
+        }
+
+        impl WireValue for $type {
+            fn to_bits(&self) -> Vec<bool> {
+                (0..<$type>::BITS)
+                    .rev()
+                    .map(|index| (self >> index) & 1 == 1)
+                    .collect()
+            }
+
+            fn from_bits(bits: &[bool]) -> Self {
+                bits.iter().fold(0, |acc, bit| (acc << 1) | *bit as $type)
+            }
+        }
+    };
+}
+
+integer_wire!(
+    /// A wire that represents a `u8` value.
+    Wire8Bit,
+    u8,
+    false
+);
+integer_wire!(
+    /// A wire that represents a `u16` value.
+    Wire16Bit,
+    u16,
+    false
+);
+integer_wire!(
+    /// A wire that represents a `u32` value.
+    Wire32Bit,
+    u32,
+    false
+);
+integer_wire!(
+    /// A wire that represents a `u64` value.
+    Wire64Bit,
+    u64,
+    false
+);
+integer_wire!(
+    /// A wire that represents a `u128` value.
+    Wire128Bit,
+    u128,
+    false
+);
+integer_wire!(
+    /// A wire that represents an `i8` value.
+    WireSigned8Bit,
+    i8,
+    true
+);
+integer_wire!(
+    /// A wire that represents an `i16` value.
+    WireSigned16Bit,
+    i16,
+    true
+);
+integer_wire!(
+    /// A wire that represents an `i32` value.
+    WireSigned32Bit,
+    i32,
+    true
+);
+integer_wire!(
+    /// A wire that represents an `i64` value.
+    WireSigned64Bit,
+    i64,
+    true
+);
+integer_wire!(
+    /// A wire that represents an `i128` value.
+    WireSigned128Bit,
+    i128,
+    true
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        free_xor_garbling::FreeXorBit,
+        mpc_core::{Delta, GarblingWire},
+    };
+    use rand::{
+        distributions::{Distribution, Standard},
+        rngs::StdRng,
+        Rng, SeedableRng,
+    };
+    use std::fmt::Debug;
+    const SEED: [u8; 32] = [42u8; 32];
+
+    fn check_round_trip<W: Wire + Clone>()
+    where
+        W::ValueType: Copy + Debug + PartialEq,
+        Standard: Distribution<W::ValueType>,
+    {
+        let mut rng = StdRng::from_seed(SEED);
+        let delta = Delta::new(&mut rng);
+        for _ in 0..20 {
+            let value: W::ValueType = rng.gen();
+            assert_eq!(value.to_bits().len(), W::bits() as usize);
+            assert_eq!(W::ValueType::from_bits(&value.to_bits()), value);
+
+            let wire = GarblingWire::<FreeXorBit, W>::with_delta(&mut rng, &delta);
+            let garbled_value = wire.clone().encode(value);
+            assert_eq!(wire.decoding_info().decode(&garbled_value), value);
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        check_round_trip::<WireBool>();
+        check_round_trip::<Wire8Bit>();
+        check_round_trip::<Wire16Bit>();
+        check_round_trip::<Wire32Bit>();
+        check_round_trip::<Wire64Bit>();
+        check_round_trip::<Wire128Bit>();
+        check_round_trip::<WireSigned8Bit>();
+        check_round_trip::<WireSigned16Bit>();
+        check_round_trip::<WireSigned32Bit>();
+        check_round_trip::<WireSigned64Bit>();
+        check_round_trip::<WireSigned128Bit>();
+    }
+
+    #[test]
+    fn test_twos_complement() {
+        assert_eq!(
+            6u8.to_bits(),
+            vec![false, false, false, false, false, true, true, false]
+        );
+        assert_eq!(
+            (-2i8).to_bits(),
+            vec![true, true, true, true, true, true, true, false]
+        );
+        assert_eq!((-2i16).to_bits(), 65534u16.to_bits());
+        assert_eq!(i64::from_bits(&u64::MAX.to_bits()), -1);
+        assert_eq!(i128::from_bits(&(1u128 << 127).to_bits()), i128::MIN);
     }
 }